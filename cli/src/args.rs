@@ -3,12 +3,54 @@
 
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use starknet_replay::histogram::{HistogramOptions, SortOrder};
+use starknet_replay::profiler::replay_statistics::{LibfuncGranularity, LibfuncWeight};
+use starknet_replay::profiler::time_series_statistics::BucketSize;
 use url::Url;
 
-/// This is the struct of the command line arguments accepted by
-/// `starknet-replay`.
+/// This is the struct of the command line interface of `starknet-replay`.
+///
+/// Without a command, the arguments of the command `replay` are accepted so
+/// that `starknet-replay --rpc-url ...` keeps working as before the commands
+/// were introduced.
 #[derive(Clone, Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// The command to run.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// The arguments of the command `replay`, used if no command is given.
+    #[command(flatten)]
+    pub replay: Option<Args>,
+}
+
+/// The commands supported by `starknet-replay`.
+#[derive(Clone, Subcommand, Debug)]
+pub enum Command {
+    /// Replay the transactions in a range of blocks and profile the libfuncs
+    /// called.
+    ///
+    /// This is the default command.
+    Replay(Args),
+
+    /// Generate the reports from the visited program counters saved with
+    /// `--visited-pcs-out`, without replaying the transactions again.
+    Analyze(AnalyzeArgs),
+
+    /// Compare the libfunc usage statistics of two replays saved with
+    /// `--txt-out` or `--json-out`.
+    Diff(DiffArgs),
+
+    /// Merge the libfunc usage statistics of several replays saved with
+    /// `--txt-out` or `--json-out`.
+    Merge(MergeArgs),
+}
+
+/// This is the struct of the command line arguments accepted by the command
+/// `replay`.
+#[derive(Clone, clap::Args, Debug)]
 pub struct Args {
     /// The url of the RPC node.
     #[arg(long)]
@@ -23,35 +65,512 @@ pub struct Args {
     #[arg(long)]
     pub end_block: u64,
 
+    /// The filename to output transaction traces from the replay.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub trace_out: Option<PathBuf>,
+
+    /// Set to overwrite `trace_out`, `isolation_report_out`,
+    /// `conflict_graph_json_out`, `conflict_graph_dot_out`, `visited_pcs_out`,
+    /// `results_db_out`, `flamegraph_out`, `pprof_out`, `pprof_dir`,
+    /// `addresses_csv_out`, `addresses_json_out`, `legacy_functions_csv_out`,
+    /// `legacy_functions_json_out` and the report outputs if they already
+    /// exists.
+    #[arg(long)]
+    pub overwrite: bool,
+
+    /// Set to perform serial replay of blocks.
+    ///
+    /// Slower, but forces the initial state of block `n+1` to be consistent
+    /// with the final state of block `n`. This is not ensured with parallel
+    /// replay because the final state may differ with the final state
+    /// fetched from the chain.
+    #[arg(long)]
+    pub serial_replay: bool,
+
+    /// Set to replay each transaction against the state of the parent block,
+    /// ignoring the effects of the preceding transactions in the same block.
+    ///
+    /// Transactions within a block are replayed in parallel. Not compatible
+    /// with `serial_replay`.
+    #[arg(long, conflicts_with = "serial_replay")]
+    pub isolated_replay: bool,
+
+    /// The filename to output the list of transactions which have a different
+    /// outcome when replayed in isolation.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long, requires = "isolated_replay")]
+    pub isolation_report_out: Option<PathBuf>,
+
+    /// The filename to output the conflict graph of the transactions of each
+    /// block in JSON format.
+    ///
+    /// The conflict graph reports the critical path length, the number of
+    /// independent groups of transactions and the theoretical parallel
+    /// speedup of each block. Not compatible with `isolated_replay`.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long, conflicts_with = "isolated_replay")]
+    pub conflict_graph_json_out: Option<PathBuf>,
+
+    /// The filename to output the conflict graph of the transactions of each
+    /// block in DOT format.
+    ///
+    /// Not compatible with `isolated_replay`.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long, conflicts_with = "isolated_replay")]
+    pub conflict_graph_dot_out: Option<PathBuf>,
+
+    /// The filename to output the visited program counters of each
+    /// transaction in compact binary format.
+    ///
+    /// The file is read by the command `analyze` to generate the reports
+    /// without replaying the blocks again.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub visited_pcs_out: Option<PathBuf>,
+
+    /// The filename to output the results of the replay in a SQLite database.
+    ///
+    /// The database contains the transactions replayed (block, hash, type,
+    /// revert status, fee and steps), the time spent replaying each block and
+    /// the libfunc usage of each contract class.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub results_db_out: Option<PathBuf>,
+
+    /// The filename to output the call stacks of the replayed transactions in
+    /// folded stacks format, to be rendered as a flamegraph.
+    ///
+    /// Each call stack is made of the contract calls, the Sierra user
+    /// functions and the libfunc executed, followed by the number of CASM
+    /// steps.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub flamegraph_out: Option<PathBuf>,
+
+    /// The filename to output the profile of the replayed transactions in
+    /// gzipped pprof format, to be opened with `go tool pprof` or speedscope.
+    ///
+    /// The samples have the same call stacks of `flamegraph_out` with the
+    /// number of CASM steps, libfunc calls and builtin calls as values.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub pprof_out: Option<PathBuf>,
+
+    /// The directory to output a profile in gzipped pprof format for each
+    /// contract class, named `<class hash>.pb.gz`.
+    ///
+    /// The call stacks of each profile start at the entry point of the
+    /// contract class.
+    ///
+    /// If `None`, output files are skipped.
+    #[arg(long)]
+    pub pprof_dir: Option<PathBuf>,
+
+    /// The filename to output the libfunc usage statistics of each contract
+    /// address in CSV format.
+    ///
+    /// Unlike `contracts_csv_out`, contracts deployed with the same class are
+    /// reported separately. The statistics are extracted from the call traces
+    /// of the replay, therefore they aren't available with the command
+    /// `analyze`.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub addresses_csv_out: Option<PathBuf>,
+
+    /// The filename to output the libfunc usage statistics of each contract
+    /// address in JSON format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub addresses_json_out: Option<PathBuf>,
+
+    /// The filename to output the calls and CASM steps executed in each
+    /// function of legacy Cairo 0 contract classes in CSV format.
+    ///
+    /// Cairo 0 contracts are executed without recording their visited program
+    /// counters, therefore the steps of each call are attributed to the
+    /// function of the entry point called. The statistics are extracted from
+    /// the call traces of the replay, therefore they aren't available with the
+    /// command `analyze`.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub legacy_functions_csv_out: Option<PathBuf>,
+
+    /// The filename to output the calls and CASM steps executed in each
+    /// function of legacy Cairo 0 contract classes in JSON format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub legacy_functions_json_out: Option<PathBuf>,
+
+    /// The reports generated from the visited program counters.
+    #[command(flatten)]
+    pub reports: ReportArgs,
+}
+
+/// This is the struct of the command line arguments to generate the reports
+/// from the visited program counters, shared by the commands `replay` and
+/// `analyze`.
+#[derive(Clone, clap::Args, Debug)]
+pub struct ReportArgs {
     /// The filename of the histogram SVG image.
     ///
     /// If `None`, histogram generation is skipped.
     #[arg(long)]
     pub svg_out: Option<PathBuf>,
 
+    /// The options of the histogram SVG image.
+    #[command(flatten)]
+    pub histogram: HistogramArgs,
+
     /// The filename to output the raw libfunc usage statistics.
     ///
     /// If `None`, output file is skipped.
     #[arg(long)]
     pub txt_out: Option<PathBuf>,
 
-    /// The filename to output transaction traces from the replay.
+    /// The filename to output the raw libfunc usage statistics in JSON
+    /// format.
+    ///
+    /// Unlike `txt_out`, the JSON file contains the generic libfuncs and the
+    /// cost of the libfuncs too.
     ///
     /// If `None`, output file is skipped.
     #[arg(long)]
-    pub trace_out: Option<PathBuf>,
+    pub json_out: Option<PathBuf>,
+
+    /// The filename to output the libfunc usage statistics of each contract
+    /// class in CSV format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub contracts_csv_out: Option<PathBuf>,
+
+    /// The filename to output the libfunc usage statistics of each contract
+    /// class in JSON format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub contracts_json_out: Option<PathBuf>,
+
+    /// The filename to output the libfunc usage statistics of each entry point
+    /// of each contract class in CSV format.
+    ///
+    /// Entry points are named after the functions in the ABI of the contract
+    /// class.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub entry_points_csv_out: Option<PathBuf>,
+
+    /// The filename to output the libfunc usage statistics of each entry point
+    /// of each contract class in JSON format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub entry_points_json_out: Option<PathBuf>,
+
+    /// The filename to output the libfunc usage statistics of each pair of
+    /// Sierra version and compiler version of the contract classes in CSV
+    /// format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub versions_csv_out: Option<PathBuf>,
+
+    /// The filename to output the libfunc usage statistics of each pair of
+    /// Sierra version and compiler version of the contract classes in JSON
+    /// format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub versions_json_out: Option<PathBuf>,
+
+    /// The filename to output the libfunc usage statistics of each time bucket
+    /// in long CSV format, with one row for each pair of bucket and libfunc.
+    ///
+    /// The size of the buckets is set with `time_series_bucket`.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub time_series_csv_out: Option<PathBuf>,
+
+    /// The filename of the SVG line chart of the usage over time of the most
+    /// used libfuncs.
+    ///
+    /// The size of the buckets is set with `time_series_bucket`.
+    ///
+    /// If `None`, line chart generation is skipped.
+    #[arg(long)]
+    pub time_series_svg_out: Option<PathBuf>,
+
+    /// The size of the time buckets of `time_series_csv_out` and
+    /// `time_series_svg_out`.
+    ///
+    /// Accepted values are a number of blocks (e.g. `1000`), `day` and `week`.
+    /// Days and weeks are computed from the block timestamps in UTC and weeks
+    /// start on Monday.
+    #[arg(long, default_value_t = BucketSize::Day)]
+    pub time_series_bucket: BucketSize,
+
+    /// The granularity of the libfunc names in all the libfunc usage reports
+    /// and in the histogram.
+    ///
+    /// Accepted values are `concrete` (e.g. `store_temp<felt252>`), `generic`
+    /// (e.g. `store_temp`) and `both`. With `both`, generic libfuncs are
+    /// reported with the suffix ` (generic)`.
+    #[arg(long, default_value_t = LibfuncGranularity::Concrete)]
+    pub libfunc_granularity: LibfuncGranularity,
+
+    /// The weight used to rank libfuncs in all the libfunc usage reports and
+    /// in the histogram.
+    ///
+    /// Accepted values are `calls` (number of calls), `steps` (number of CASM
+    /// steps executed) and `gas` (estimated Sierra gas cost). The branch
+    /// taken by each call isn't recorded, therefore `gas` charges each call
+    /// the cost of the most expensive branch of the libfunc.
+    #[arg(long, default_value_t = LibfuncWeight::Calls)]
+    pub libfunc_weight: LibfuncWeight,
+
+    /// The filename to output the contract classes skipped by the libfunc
+    /// analysis in CSV format.
+    ///
+    /// Each class is reported with the reason why it has been skipped and the
+    /// number of visited program counters missing from the statistics.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub skipped_classes_csv_out: Option<PathBuf>,
+
+    /// The filename to output the contract classes skipped by the libfunc
+    /// analysis in JSON format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub skipped_classes_json_out: Option<PathBuf>,
+
+    /// Set to fail instead of skipping contract classes which can't be
+    /// analysed.
+    ///
+    /// It applies to all the reports which map visited program counters to
+    /// Sierra statements, including flamegraphs and pprof profiles.
+    ///
+    /// Legacy Cairo 0 classes don't make it fail because they don't call
+    /// libfuncs. They are profiled by `legacy_functions_csv_out` and
+    /// `legacy_functions_json_out` instead.
+    #[arg(long)]
+    pub strict: bool,
 
-    /// Set to overwrite `svg_out`, `txt_out`, `trace_out` if they already
+    /// The filename to output the coverage of the Sierra statements of each
+    /// contract class in lcov format.
+    ///
+    /// Each Sierra statement is a line of the source file of the contract
+    /// class. Statements never executed are reported as not covered. The source
+    /// file `<class hash>.sierra` of each contract class is written in the
+    /// same directory.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub coverage_out: Option<PathBuf>,
+
+    /// The filename to output the frequency of CASM instruction kinds, hint
+    /// types and memory-access patterns in CSV format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub instructions_csv_out: Option<PathBuf>,
+
+    /// The filename to output the frequency of CASM instruction kinds, hint
+    /// types and memory-access patterns in JSON format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub instructions_json_out: Option<PathBuf>,
+
+    /// Set to place the statistics of the JSON reports below the metadata of
+    /// the run: the range of blocks, the chain id, the Starknet versions, the
+    /// number of transactions, the version of `starknet-replay` and the
+    /// settings used.
+    ///
+    /// The metadata requires querying the header of each block in the range.
+    #[arg(long)]
+    pub json_metadata: bool,
+}
+
+/// This is the struct of the command line arguments accepted by the command
+/// `analyze`.
+#[derive(Clone, clap::Args, Debug)]
+pub struct AnalyzeArgs {
+    /// The url of the RPC node.
+    ///
+    /// The contract classes of the visited program counters are queried from
+    /// the RPC node.
+    #[arg(long)]
+    pub rpc_url: Url,
+
+    /// The visited program counters of the transactions, as saved with
+    /// `--visited-pcs-out`.
+    #[arg(long)]
+    pub visited_pcs: PathBuf,
+
+    /// Set to overwrite the output files if they already exists.
+    #[arg(long)]
+    pub overwrite: bool,
+
+    /// The reports generated from the visited program counters.
+    #[command(flatten)]
+    pub reports: ReportArgs,
+}
+
+/// This is the struct of the command line arguments accepted by the command
+/// `diff`.
+#[derive(Clone, clap::Args, Debug)]
+pub struct DiffArgs {
+    /// The libfunc usage statistics of the first replay, as saved with
+    /// `--txt-out` or `--json-out`.
+    #[arg(long)]
+    pub before: PathBuf,
+
+    /// The libfunc usage statistics of the second replay, as saved with
+    /// `--txt-out` or `--json-out`.
+    #[arg(long)]
+    pub after: PathBuf,
+
+    /// The filename to output the change of each libfunc in CSV format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub csv_out: Option<PathBuf>,
+
+    /// The filename to output the diff report in Markdown format.
+    ///
+    /// If `None` and no other output file is set, the report is printed to
+    /// the standard output.
+    #[arg(long)]
+    pub markdown_out: Option<PathBuf>,
+
+    /// The filename of the SVG bar chart of the change in share of the
+    /// libfuncs with the biggest change.
+    ///
+    /// If `None`, bar chart generation is skipped.
+    #[arg(long)]
+    pub svg_out: Option<PathBuf>,
+
+    /// Set to overwrite `csv_out`, `markdown_out`, `svg_out` if they already
     /// exists.
     #[arg(long)]
     pub overwrite: bool,
+}
 
-    /// Set to perform serial replay of blocks.
+/// This is the struct of the command line arguments accepted by the command
+/// `merge`.
+#[derive(Clone, clap::Args, Debug)]
+pub struct MergeArgs {
+    /// The libfunc usage statistics to merge, as saved with `--txt-out` or
+    /// `--json-out`. Files with extension `json` are read in JSON format, any
+    /// other file in CSV format.
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// The filename to output the merged libfunc usage statistics in CSV
+    /// format.
     ///
-    /// Slower, but forces the initial state of block `n+1` to be consistent
-    /// with the final state of block `n`. This is not ensured with parallel
-    /// replay because the final state may differ with the final state
-    /// fetched from the chain.
+    /// If `None`, output file is skipped.
     #[arg(long)]
-    pub serial_replay: bool,
+    pub txt_out: Option<PathBuf>,
+
+    /// The filename to output the merged libfunc usage statistics in JSON
+    /// format.
+    ///
+    /// If `None`, output file is skipped.
+    #[arg(long)]
+    pub json_out: Option<PathBuf>,
+
+    /// The filename of the histogram SVG image of the merged libfunc usage
+    /// statistics.
+    ///
+    /// If `None`, histogram generation is skipped.
+    #[arg(long)]
+    pub svg_out: Option<PathBuf>,
+
+    /// The options of the histogram SVG image.
+    #[command(flatten)]
+    pub histogram: HistogramArgs,
+
+    /// Set to overwrite `txt_out`, `json_out`, `svg_out` if they already
+    /// exists.
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
+/// This is the struct of the command line arguments to filter, sort and scale
+/// the libfuncs plotted in the histogram, shared by the commands `replay`,
+/// `analyze` and `merge`.
+#[derive(Clone, clap::Args, Debug)]
+pub struct HistogramArgs {
+    /// The cumulative share in percentage of the libfunc calls plotted in the
+    /// histogram. The most called libfuncs are plotted until their cumulative
+    /// share exceeds this value.
+    ///
+    /// Set to 100 to plot the long tail of rarely called libfuncs.
+    #[arg(long, default_value_t = 80, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub histogram_cumulative_share: u8,
+
+    /// The maximum number of libfuncs plotted in the histogram.
+    ///
+    /// If `None`, the number of libfuncs isn't limited.
+    #[arg(long)]
+    pub histogram_top: Option<usize>,
+
+    /// The regular expression matching the names of the libfuncs plotted in
+    /// the histogram.
+    ///
+    /// If `None`, all the libfuncs are plotted.
+    #[arg(long, value_parser = parse_pattern)]
+    pub histogram_include: Option<String>,
+
+    /// The regular expression matching the names of the libfuncs not plotted
+    /// in the histogram.
+    ///
+    /// If `None`, no libfunc is removed.
+    #[arg(long, value_parser = parse_pattern)]
+    pub histogram_exclude: Option<String>,
+
+    /// Set to plot the y axis of the histogram in logarithmic scale.
+    #[arg(long)]
+    pub histogram_log_scale: bool,
+
+    /// The order of the libfuncs in the histogram.
+    ///
+    /// Accepted values are `value` (from the highest weight) and `name`.
+    #[arg(long, default_value_t = SortOrder::Value)]
+    pub histogram_sort: SortOrder,
+}
+
+/// Returns `pattern` if it's a valid regular expression to filter the libfuncs
+/// plotted in the histogram.
+///
+/// # Arguments
+///
+/// - `pattern`: The regular expression.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `pattern` isn't a valid regular expression.
+fn parse_pattern(pattern: &str) -> Result<String, String> {
+    HistogramOptions::default()
+        .with_include(pattern)
+        .map_err(|err| err.to_string())?;
+    Ok(pattern.to_string())
 }
@@ -17,16 +17,70 @@ use std::path::PathBuf;
 use std::{fs, process};
 
 use anyhow::bail;
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use exitcode::{OK, SOFTWARE};
-use starknet_replay::histogram::export as export_histogram;
-use starknet_replay::profiler::analysis::extract_libfuncs_weight;
-use starknet_replay::profiler::report::write_to_file;
+use starknet_replay::block_number::BlockNumber;
+use starknet_replay::histogram::{
+    export as export_histogram,
+    export_diff,
+    export_time_series,
+    HistogramOptions,
+};
+use starknet_replay::profiler::analysis::{
+    extract_folded_stacks,
+    extract_folded_stacks_by_class,
+    extract_instruction_statistics,
+    extract_legacy_function_steps,
+    extract_libfuncs_weight_by_address,
+    extract_libfuncs_weight_by_bucket,
+    extract_libfuncs_weight_by_entry_point,
+    extract_statement_coverage,
+};
+use starknet_replay::profiler::class_cache::ClassCache;
+use starknet_replay::profiler::pprof::to_pprof_bytes;
+use starknet_replay::profiler::report::{
+    read_and_merge_files,
+    read_from_file,
+    write_coverage_lcov,
+    write_json_report,
+    write_pprof_by_class,
+    write_report,
+    write_to_file,
+};
+use starknet_replay::profiler::results_database::write_results_database;
+use starknet_replay::profiler::run_metadata::RunMetadata;
+use starknet_replay::profiler::skipped_classes::SkippedClasses;
+use starknet_replay::profiler::statistics_diff::StatisticsDiff;
+use starknet_replay::runner::call_trace::CallTrace;
+use starknet_replay::runner::conflict_graph::{
+    write_conflict_graph_dot,
+    write_conflict_graph_json,
+};
+use starknet_replay::runner::isolation::write_isolation_report;
+use starknet_replay::runner::pc_histogram::EntryPointHistograms;
+use starknet_replay::runner::replay_class_hash::VisitedPcs;
+use starknet_replay::runner::replay_options::ReplayOptions;
 use starknet_replay::runner::replay_range::ReplayRange;
+use starknet_replay::runner::replay_record::ReplayRecords;
 use starknet_replay::runner::run_replay;
+use starknet_replay::runner::transaction_visited_pcs::{
+    merge_visited_pcs,
+    read_visited_pcs,
+    write_visited_pcs,
+};
 use starknet_replay::storage::rpc::RpcStorage;
 
-use crate::args::Args;
+use crate::args::{
+    AnalyzeArgs,
+    Args,
+    Cli,
+    Command,
+    DiffArgs,
+    HistogramArgs,
+    MergeArgs,
+    ReportArgs,
+};
 
 mod args;
 
@@ -46,9 +100,27 @@ fn main() {
         .compact()
         .init();
 
-    let args = Args::parse();
+    let cli = Cli::parse();
+    // The command `replay` is the default command.
+    let command = match (cli.command, cli.replay) {
+        (Some(command), _) => command,
+        (None, Some(args)) => Command::Replay(args),
+        (None, None) => Cli::command()
+            .error(
+                ErrorKind::MissingSubcommand,
+                "A command or the arguments of the command `replay` are required.",
+            )
+            .exit(),
+    };
 
-    match run(args) {
+    let result = match command {
+        Command::Replay(args) => run(args),
+        Command::Analyze(args) => run_analyze(args),
+        Command::Diff(args) => run_diff(args),
+        Command::Merge(args) => run_merge(args),
+    };
+
+    match result {
         Ok(()) => process::exit(OK),
         Err(e) => {
             eprintln!("Internal software error: {e}");
@@ -81,7 +153,30 @@ fn check_file(path: Option<&PathBuf>, overwrite: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
-/// Take the command line input arguments and call the replayer.
+/// Returns an error if the directory exists already and can't be overwritten.
+///
+/// If the directory exists and it can be overwritten, the files in the
+/// directory are overwritten when saving the output.
+///
+/// # Arguments
+///
+/// - `path`: The directory to write.
+/// - `overwrite`: If `true`, the directory can be overwritten.
+fn check_directory(path: Option<&PathBuf>, overwrite: bool) -> anyhow::Result<()> {
+    if let Some(directory) = path {
+        if directory.exists() && !overwrite {
+            let directory = directory.as_path().display();
+            bail!(
+                "The directory {0:?} exists already. To ignore it, pass the flag --overwrite.",
+                directory
+            )
+        }
+    }
+    Ok(())
+}
+
+/// Take the command line input arguments of the command `replay` and call the
+/// replayer.
 ///
 /// Sanitisation of the inputs is done in this function.
 ///
@@ -101,15 +196,38 @@ fn run(args: Args) -> anyhow::Result<()> {
     let rpc_url = args.rpc_url;
     let start_block = args.start_block;
     let end_block = args.end_block;
-    let svg_path = args.svg_out;
-    let txt_out = args.txt_out;
     let trace_out = args.trace_out;
     let overwrite = args.overwrite;
     let serial_replay = args.serial_replay;
+    let isolated_replay = args.isolated_replay;
+    let isolation_report_out = args.isolation_report_out;
+    let conflict_graph_json_out = args.conflict_graph_json_out;
+    let conflict_graph_dot_out = args.conflict_graph_dot_out;
+    let visited_pcs_out = args.visited_pcs_out;
+    let results_db_out = args.results_db_out;
+    let flamegraph_out = args.flamegraph_out;
+    let pprof_out = args.pprof_out;
+    let pprof_dir = args.pprof_dir;
+    let addresses_csv_out = args.addresses_csv_out;
+    let addresses_json_out = args.addresses_json_out;
+    let legacy_functions_csv_out = args.legacy_functions_csv_out;
+    let legacy_functions_json_out = args.legacy_functions_json_out;
+    let reports = args.reports;
 
-    check_file(svg_path.as_ref(), overwrite)?;
-    check_file(txt_out.as_ref(), overwrite)?;
     check_file(trace_out.as_ref(), overwrite)?;
+    check_file(isolation_report_out.as_ref(), overwrite)?;
+    check_file(conflict_graph_json_out.as_ref(), overwrite)?;
+    check_file(conflict_graph_dot_out.as_ref(), overwrite)?;
+    check_file(visited_pcs_out.as_ref(), overwrite)?;
+    check_file(results_db_out.as_ref(), overwrite)?;
+    check_file(flamegraph_out.as_ref(), overwrite)?;
+    check_file(pprof_out.as_ref(), overwrite)?;
+    check_directory(pprof_dir.as_ref(), overwrite)?;
+    check_file(addresses_csv_out.as_ref(), overwrite)?;
+    check_file(addresses_json_out.as_ref(), overwrite)?;
+    check_file(legacy_functions_csv_out.as_ref(), overwrite)?;
+    check_file(legacy_functions_json_out.as_ref(), overwrite)?;
+    check_report_files(&reports, overwrite)?;
 
     let storage = RpcStorage::new(rpc_url, serial_replay);
 
@@ -118,24 +236,619 @@ fn run(args: Args) -> anyhow::Result<()> {
     tracing::info!(%start_block, %end_block, "Re-executing blocks");
     let start_time = std::time::Instant::now();
 
-    let visited_pcs = run_replay(&replay_range, &trace_out, &storage, serial_replay)?;
+    let options = ReplayOptions {
+        trace_out,
+        serial_replay,
+        isolated_replay,
+        record_call_traces: flamegraph_out.is_some()
+            || pprof_out.is_some()
+            || pprof_dir.is_some()
+            || addresses_csv_out.is_some()
+            || addresses_json_out.is_some()
+            || legacy_functions_csv_out.is_some()
+            || legacy_functions_json_out.is_some(),
+        record_access_sets: conflict_graph_json_out.is_some() || conflict_graph_dot_out.is_some(),
+        record_transaction_visited_pcs: visited_pcs_out.is_some(),
+    };
+    let replay_output = run_replay(&replay_range, &storage, &options)?;
 
     let elapsed = start_time.elapsed();
     tracing::info!(?elapsed, "Finished");
 
-    if txt_out.is_some() || svg_path.is_some() {
-        let libfunc_stats = extract_libfuncs_weight(&visited_pcs, &storage)?;
+    if isolated_replay {
+        tracing::info!(
+            "{} transactions have a different outcome in isolation",
+            replay_output.isolation_mismatches.len()
+        );
+    }
+    if let Some(filename) = isolation_report_out {
+        write_isolation_report(&filename, &replay_output.isolation_mismatches)?;
+    }
+    if let Some(filename) = conflict_graph_json_out {
+        write_conflict_graph_json(&filename, &replay_output.conflict_graphs)?;
+    }
+    if let Some(filename) = conflict_graph_dot_out {
+        write_conflict_graph_dot(&filename, &replay_output.conflict_graphs)?;
+    }
+    let total_call_trace_pcs: usize = replay_output
+        .call_traces
+        .iter()
+        .flat_map(CallTrace::calls)
+        .map(|call_trace| call_trace.pcs.len())
+        .sum();
+    // Each contract class is compiled once and shared by all the reports.
+    let class_cache = ClassCache::new();
+    if flamegraph_out.is_some() || pprof_out.is_some() {
+        let (folded_stacks, skipped_classes) =
+            extract_folded_stacks(&replay_output.call_traces, &storage, &class_cache);
+        check_skipped_classes(&skipped_classes, total_call_trace_pcs, reports.strict)?;
+        if let Some(filename) = &flamegraph_out {
+            write_report(filename, folded_stacks.to_bytes()?)?;
+        }
+        if let Some(filename) = &pprof_out {
+            write_report(filename, to_pprof_bytes(&folded_stacks)?)?;
+        }
+    }
+    if let Some(directory) = &pprof_dir {
+        let (folded_stacks, skipped_classes) =
+            extract_folded_stacks_by_class(&replay_output.call_traces, &storage, &class_cache);
+        check_skipped_classes(&skipped_classes, total_call_trace_pcs, reports.strict)?;
+        write_pprof_by_class(directory, &folded_stacks)?;
+    }
+    if let Some(filename) = visited_pcs_out {
+        write_visited_pcs(&filename, &replay_output.transactions)?;
+    }
+    let metadata = RunMetadata::new(BlockNumber::new(start_block), BlockNumber::new(end_block))
+        .with_records(&replay_output.records)
+        .with_setting("command", &"replay")
+        .with_setting("serial_replay", &serial_replay)
+        .with_setting("isolated_replay", &isolated_replay);
+    let results_db = results_db_out.map(|filename| (filename, replay_output.records));
+    let call_trace_reports = CallTraceReports {
+        call_traces: &replay_output.call_traces,
+        addresses_csv_out,
+        addresses_json_out,
+        legacy_functions_csv_out,
+        legacy_functions_json_out,
+    };
+    write_reports(
+        &replay_output.visited_pcs,
+        &storage,
+        &class_cache,
+        reports,
+        metadata,
+        results_db,
+        Some(call_trace_reports),
+    )
+}
+
+/// Take the command line input arguments of the command `analyze` and
+/// generate the reports from the visited program counters saved by the
+/// command `replay`.
+///
+/// # Arguments
+///
+/// - `args`: The list of command line input arguments.
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - Any of the output files exists already and can't be overwritten.
+/// - The file of visited program counters can't be read or parsed.
+/// - The file of visited program counters doesn't contain any transaction.
+/// - Any error generating the reports.
+fn run_analyze(args: AnalyzeArgs) -> anyhow::Result<()> {
+    let rpc_url = args.rpc_url;
+    let visited_pcs_path = args.visited_pcs;
+    let overwrite = args.overwrite;
+    let reports = args.reports;
+
+    check_report_files(&reports, overwrite)?;
+
+    let transactions = read_visited_pcs(&visited_pcs_path)?;
+    tracing::info!(
+        "Loaded visited program counters of {} transactions",
+        transactions.len()
+    );
+    let visited_pcs = merge_visited_pcs(&transactions);
+
+    let storage = RpcStorage::new(rpc_url, false);
+
+    let start_block = transactions
+        .iter()
+        .map(|transaction| transaction.block_number)
+        .min();
+    let end_block = transactions
+        .iter()
+        .map(|transaction| transaction.block_number)
+        .max();
+    let (Some(start_block), Some(end_block)) = (start_block, end_block) else {
+        bail!(
+            "The file {} doesn't contain any transaction.",
+            visited_pcs_path.display()
+        );
+    };
+    let metadata = RunMetadata::new(start_block, end_block)
+        .with_transactions(&transactions)
+        .with_setting("command", &"analyze")
+        .with_setting("visited_pcs", &visited_pcs_path.display());
+    write_reports(
+        &visited_pcs,
+        &storage,
+        &ClassCache::new(),
+        reports,
+        metadata,
+        None,
+        None,
+    )
+}
+
+/// Returns an error if any of the report files exists already and can't be
+/// overwritten.
+///
+/// # Arguments
+///
+/// - `reports`: The command line arguments of the reports.
+/// - `overwrite`: If `true`, the files can be overwritten.
+fn check_report_files(reports: &ReportArgs, overwrite: bool) -> anyhow::Result<()> {
+    check_file(reports.svg_out.as_ref(), overwrite)?;
+    check_file(reports.txt_out.as_ref(), overwrite)?;
+    check_file(reports.json_out.as_ref(), overwrite)?;
+    check_file(reports.contracts_csv_out.as_ref(), overwrite)?;
+    check_file(reports.contracts_json_out.as_ref(), overwrite)?;
+    check_file(reports.entry_points_csv_out.as_ref(), overwrite)?;
+    check_file(reports.entry_points_json_out.as_ref(), overwrite)?;
+    check_file(reports.versions_csv_out.as_ref(), overwrite)?;
+    check_file(reports.versions_json_out.as_ref(), overwrite)?;
+    check_file(reports.time_series_csv_out.as_ref(), overwrite)?;
+    check_file(reports.time_series_svg_out.as_ref(), overwrite)?;
+    check_file(reports.skipped_classes_csv_out.as_ref(), overwrite)?;
+    check_file(reports.skipped_classes_json_out.as_ref(), overwrite)?;
+    check_file(reports.coverage_out.as_ref(), overwrite)?;
+    check_file(reports.instructions_csv_out.as_ref(), overwrite)?;
+    check_file(reports.instructions_json_out.as_ref(), overwrite)?;
+    Ok(())
+}
+
+/// Returns the options of the histogram set on the command line.
+///
+/// # Arguments
+///
+/// - `histogram`: The command line arguments of the histogram.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the include or exclude pattern isn't a valid regular
+/// expression.
+fn histogram_options(histogram: HistogramArgs) -> anyhow::Result<HistogramOptions> {
+    let mut options = HistogramOptions {
+        cumulative_share: Some(histogram.histogram_cumulative_share.into()),
+        top: histogram.histogram_top,
+        log_scale: histogram.histogram_log_scale,
+        sort: histogram.histogram_sort,
+        ..HistogramOptions::default()
+    };
+    if let Some(include) = histogram.histogram_include {
+        options = options.with_include(&include)?;
+    }
+    if let Some(exclude) = histogram.histogram_exclude {
+        options = options.with_exclude(&exclude)?;
+    }
+    Ok(options)
+}
+
+/// Logs the contract classes skipped by an analysis and returns an error in
+/// strict mode if any of them causes the analysis to undercount.
+///
+/// # Arguments
+///
+/// - `skipped_classes`: The contract classes skipped by the analysis.
+/// - `total_visited_pcs`: The number of visited program counters analysed.
+/// - `strict`: If `true`, skipped classes which aren't legacy Cairo 0 classes
+///   are an error.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `strict` is `true` and any class other than legacy Cairo
+/// 0 classes has been skipped.
+fn check_skipped_classes(
+    skipped_classes: &SkippedClasses,
+    total_visited_pcs: usize,
+    strict: bool,
+) -> anyhow::Result<()> {
+    if !skipped_classes.is_empty() {
+        tracing::warn!(
+            "{} contract classes skipped, {} of {} visited program counters not analysed",
+            skipped_classes.classes.len(),
+            skipped_classes.total_visited_pcs(),
+            total_visited_pcs
+        );
+    }
+
+    let undercounted = skipped_classes.undercounted();
+    if strict && !undercounted.is_empty() {
+        let class_hashes = undercounted
+            .iter()
+            .map(|skipped_class| {
+                format!(
+                    "{:#x} ({})",
+                    skipped_class.class_hash.0, skipped_class.reason
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        bail!(
+            "{} contract classes can't be analysed: {class_hashes}",
+            undercounted.len()
+        );
+    }
+    Ok(())
+}
+
+/// The reports generated from the call traces of the command `replay`.
+struct CallTraceReports<'a> {
+    /// The call traces of the transactions replayed.
+    call_traces: &'a [CallTrace],
+
+    /// The file to output the libfunc usage of each contract address in CSV
+    /// format, if requested.
+    addresses_csv_out: Option<PathBuf>,
+
+    /// The file to output the libfunc usage of each contract address in JSON
+    /// format, if requested.
+    addresses_json_out: Option<PathBuf>,
+
+    /// The file to output the steps of each Cairo 0 function in CSV format, if
+    /// requested.
+    legacy_functions_csv_out: Option<PathBuf>,
+
+    /// The file to output the steps of each Cairo 0 function in JSON format,
+    /// if requested.
+    legacy_functions_json_out: Option<PathBuf>,
+}
+
+/// Generates the reports requested in `reports` from the visited program
+/// counters.
+///
+/// # Arguments
+///
+/// - `visited_pcs`: The visited program counters of the replay.
+/// - `storage`: The object to query the contract classes.
+/// - `class_cache`: The contract classes already compiled, shared by all the
+///   reports.
+/// - `reports`: The command line arguments of the reports.
+/// - `metadata`: The metadata of the run, used in the chart titles and added to
+///   the JSON reports if requested.
+/// - `results_db`: The file of the results database and the records of the
+///   replay to save in it, if requested.
+/// - `call_trace_reports`: The reports generated from the call traces and the
+///   call traces to generate them from, if the call traces are available.
+///
+/// # Errors
+///
+/// Returns [`Err`] if any report can't be generated or written.
+fn write_reports(
+    visited_pcs: &VisitedPcs,
+    storage: &RpcStorage,
+    class_cache: &ClassCache,
+    reports: ReportArgs,
+    metadata: RunMetadata,
+    results_db: Option<(PathBuf, ReplayRecords)>,
+    call_trace_reports: Option<CallTraceReports<'_>>,
+) -> anyhow::Result<()> {
+    let svg_path = reports.svg_out;
+    let histogram_options = histogram_options(reports.histogram)?;
+    let txt_out = reports.txt_out;
+    let json_out = reports.json_out;
+    let contracts_csv_out = reports.contracts_csv_out;
+    let contracts_json_out = reports.contracts_json_out;
+    let entry_points_csv_out = reports.entry_points_csv_out;
+    let entry_points_json_out = reports.entry_points_json_out;
+    let versions_csv_out = reports.versions_csv_out;
+    let versions_json_out = reports.versions_json_out;
+    let time_series_csv_out = reports.time_series_csv_out;
+    let time_series_svg_out = reports.time_series_svg_out;
+    let time_series_bucket = reports.time_series_bucket;
+    let libfunc_granularity = reports.libfunc_granularity;
+    let libfunc_weight = reports.libfunc_weight;
+    let skipped_classes_csv_out = reports.skipped_classes_csv_out;
+    let skipped_classes_json_out = reports.skipped_classes_json_out;
+    let strict = reports.strict;
+    let coverage_out = reports.coverage_out;
+    let instructions_csv_out = reports.instructions_csv_out;
+    let instructions_json_out = reports.instructions_json_out;
+    let json_metadata = reports.json_metadata;
+
+    let range = format!(
+        "from block {} to block {}",
+        metadata.start_block, metadata.end_block
+    );
+    let total_visited_pcs: usize = visited_pcs.values().map(EntryPointHistograms::total).sum();
+    let metadata = if json_metadata {
+        let mut metadata = metadata
+            .with_setting("libfunc_granularity", &libfunc_granularity)
+            .with_setting("libfunc_weight", &libfunc_weight)
+            .with_setting("time_series_bucket", &time_series_bucket)
+            .with_setting("strict", &strict);
+        metadata.query_chain_id(storage)?;
+        Some(metadata)
+    } else {
+        None
+    };
+
+    if txt_out.is_some()
+        || json_out.is_some()
+        || svg_path.is_some()
+        || contracts_csv_out.is_some()
+        || contracts_json_out.is_some()
+        || entry_points_csv_out.is_some()
+        || entry_points_json_out.is_some()
+        || versions_csv_out.is_some()
+        || versions_json_out.is_some()
+        || skipped_classes_csv_out.is_some()
+        || skipped_classes_json_out.is_some()
+        || strict
+        || results_db.is_some()
+    {
+        let entry_point_stats =
+            extract_libfuncs_weight_by_entry_point(visited_pcs, storage, class_cache)
+                .with_weight(libfunc_weight)?
+                .with_granularity(libfunc_granularity)?;
+
+        let skipped_classes = &entry_point_stats.skipped_classes;
+        if let Some(filename) = skipped_classes_csv_out {
+            write_report(&filename, skipped_classes.to_csv_bytes()?)?;
+        }
+
+        if let Some(filename) = skipped_classes_json_out {
+            write_json_report(&filename, &skipped_classes.to_json()?, metadata.as_ref())?;
+        }
+
+        check_skipped_classes(skipped_classes, total_visited_pcs, strict)?;
+        let contract_stats = entry_point_stats.by_contract();
+        let libfunc_stats = contract_stats.total();
+
+        if let Some(filename) = entry_points_csv_out {
+            write_report(&filename, entry_point_stats.to_csv_bytes()?)?;
+        }
+
+        if let Some(filename) = entry_points_json_out {
+            write_json_report(&filename, &entry_point_stats.to_json()?, metadata.as_ref())?;
+        }
+
+        if versions_csv_out.is_some() || versions_json_out.is_some() {
+            let version_stats = contract_stats.by_version();
+            if let Some(filename) = versions_csv_out {
+                write_report(&filename, version_stats.to_csv_bytes()?)?;
+            }
+            if let Some(filename) = versions_json_out {
+                write_json_report(&filename, &version_stats.to_json()?, metadata.as_ref())?;
+            }
+        }
+
+        if let Some(filename) = contracts_csv_out {
+            write_report(&filename, contract_stats.to_csv_bytes()?)?;
+        }
+
+        if let Some(filename) = contracts_json_out {
+            write_json_report(&filename, &contract_stats.to_json()?, metadata.as_ref())?;
+        }
+
+        if let Some((filename, records)) = results_db {
+            write_results_database(&filename, &records, &contract_stats)?;
+        }
 
         if let Some(filename) = txt_out {
             write_to_file(&filename, &libfunc_stats)?;
         }
 
+        if let Some(filename) = json_out {
+            write_json_report(&filename, &libfunc_stats.to_json()?, metadata.as_ref())?;
+        }
+
         if let Some(filename) = svg_path {
-            let title =
-                format!("Filtered libfuncs usage from block {start_block} to block {end_block}");
-            let libfunc_stats = libfunc_stats.filter_most_frequent();
-            export_histogram(&filename, title.as_str(), &libfunc_stats)?;
+            let title = format!("Filtered libfuncs usage {range}");
+            export_histogram(
+                &filename,
+                title.as_str(),
+                &libfunc_stats,
+                &histogram_options,
+            )?;
+        }
+    }
+
+    if let Some(call_trace_reports) = call_trace_reports {
+        let call_traces = call_trace_reports.call_traces;
+        let total_call_trace_pcs: usize = call_traces
+            .iter()
+            .flat_map(CallTrace::calls)
+            .map(|call_trace| call_trace.pcs.len())
+            .sum();
+
+        if call_trace_reports.addresses_csv_out.is_some()
+            || call_trace_reports.addresses_json_out.is_some()
+        {
+            let (address_stats, skipped_classes) =
+                extract_libfuncs_weight_by_address(call_traces, storage, class_cache);
+            check_skipped_classes(&skipped_classes, total_call_trace_pcs, strict)?;
+            let address_stats = address_stats
+                .with_weight(libfunc_weight)?
+                .with_granularity(libfunc_granularity)?;
+
+            if let Some(filename) = call_trace_reports.addresses_csv_out {
+                write_report(&filename, address_stats.to_csv_bytes()?)?;
+            }
+
+            if let Some(filename) = call_trace_reports.addresses_json_out {
+                write_json_report(&filename, &address_stats.to_json()?, metadata.as_ref())?;
+            }
         }
+
+        if call_trace_reports.legacy_functions_csv_out.is_some()
+            || call_trace_reports.legacy_functions_json_out.is_some()
+        {
+            let (legacy_stats, skipped_classes) =
+                extract_legacy_function_steps(call_traces, storage);
+            check_skipped_classes(&skipped_classes, total_call_trace_pcs, strict)?;
+
+            if let Some(filename) = call_trace_reports.legacy_functions_csv_out {
+                write_report(&filename, legacy_stats.to_csv_bytes()?)?;
+            }
+
+            if let Some(filename) = call_trace_reports.legacy_functions_json_out {
+                write_json_report(&filename, &legacy_stats.to_json()?, metadata.as_ref())?;
+            }
+        }
+    }
+
+    if time_series_csv_out.is_some() || time_series_svg_out.is_some() {
+        let (time_series_stats, skipped_classes) = extract_libfuncs_weight_by_bucket(
+            visited_pcs,
+            storage,
+            class_cache,
+            time_series_bucket,
+        )?;
+        check_skipped_classes(&skipped_classes, total_visited_pcs, strict)?;
+        let time_series_stats = time_series_stats
+            .with_weight(libfunc_weight)?
+            .with_granularity(libfunc_granularity)?;
+
+        if let Some(filename) = time_series_csv_out {
+            write_report(&filename, time_series_stats.to_csv_bytes()?)?;
+        }
+
+        if let Some(filename) = time_series_svg_out {
+            let title = format!("Libfuncs usage over time {range}");
+            export_time_series(&filename, title.as_str(), &time_series_stats)?;
+        }
+    }
+
+    if let Some(filename) = coverage_out {
+        let (coverage, skipped_classes) =
+            extract_statement_coverage(visited_pcs, storage, class_cache);
+        check_skipped_classes(&skipped_classes, total_visited_pcs, strict)?;
+        write_coverage_lcov(&filename, &coverage)?;
+    }
+
+    if instructions_csv_out.is_some() || instructions_json_out.is_some() {
+        let (instruction_statistics, skipped_classes) =
+            extract_instruction_statistics(visited_pcs, storage, class_cache);
+        check_skipped_classes(&skipped_classes, total_visited_pcs, strict)?;
+        if let Some(filename) = instructions_csv_out {
+            write_report(&filename, instruction_statistics.to_csv_bytes()?)?;
+        }
+        if let Some(filename) = instructions_json_out {
+            write_json_report(
+                &filename,
+                &instruction_statistics.to_json()?,
+                metadata.as_ref(),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Take the command line input arguments of the command `diff` and compare the
+/// libfunc usage statistics of two replays.
+///
+/// # Arguments
+///
+/// - `args`: The list of command line input arguments.
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - Any of the output files exists already and can't be overwritten.
+/// - Any of the input files can't be read or parsed.
+/// - Any of the output files can't be written.
+fn run_diff(args: DiffArgs) -> anyhow::Result<()> {
+    let before = args.before;
+    let after = args.after;
+    let csv_out = args.csv_out;
+    let markdown_out = args.markdown_out;
+    let svg_out = args.svg_out;
+    let overwrite = args.overwrite;
+
+    check_file(csv_out.as_ref(), overwrite)?;
+    check_file(markdown_out.as_ref(), overwrite)?;
+    check_file(svg_out.as_ref(), overwrite)?;
+
+    let statistics_diff = StatisticsDiff::new(read_from_file(&before)?, read_from_file(&after)?)?;
+
+    if csv_out.is_none() && markdown_out.is_none() && svg_out.is_none() {
+        let markdown = statistics_diff.to_markdown_bytes()?;
+        println!("{}", String::from_utf8_lossy(&markdown));
+    }
+
+    if let Some(filename) = csv_out {
+        write_report(&filename, statistics_diff.to_csv_bytes()?)?;
+    }
+
+    if let Some(filename) = markdown_out {
+        write_report(&filename, statistics_diff.to_markdown_bytes()?)?;
+    }
+
+    if let Some(filename) = svg_out {
+        let title = format!(
+            "Libfuncs share change from {} to {}",
+            before.display(),
+            after.display()
+        );
+        export_diff(&filename, title.as_str(), &statistics_diff)?;
+    }
+
+    Ok(())
+}
+
+/// Take the command line input arguments of the command `merge` and merge the
+/// libfunc usage statistics of several replays.
+///
+/// # Arguments
+///
+/// - `args`: The list of command line input arguments.
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - Any of the output files exists already and can't be overwritten.
+/// - Any of the input files can't be read or parsed.
+/// - Any of the output files can't be written.
+fn run_merge(args: MergeArgs) -> anyhow::Result<()> {
+    let inputs = args.inputs;
+    let txt_out = args.txt_out;
+    let json_out = args.json_out;
+    let svg_path = args.svg_out;
+    let histogram = args.histogram;
+    let overwrite = args.overwrite;
+
+    check_file(txt_out.as_ref(), overwrite)?;
+    check_file(json_out.as_ref(), overwrite)?;
+    check_file(svg_path.as_ref(), overwrite)?;
+
+    let libfunc_stats = read_and_merge_files(&inputs)?;
+    tracing::info!("Merged {} files", inputs.len());
+
+    if let Some(filename) = txt_out {
+        write_to_file(&filename, &libfunc_stats)?;
+    }
+
+    if let Some(filename) = json_out {
+        write_json_report(&filename, &libfunc_stats.to_json()?, None)?;
+    }
+
+    if let Some(filename) = svg_path {
+        let title = format!("Filtered libfuncs usage merged from {} files", inputs.len());
+        let histogram_options = histogram_options(histogram)?;
+        export_histogram(
+            &filename,
+            title.as_str(),
+            &libfunc_stats,
+            &histogram_options,
+        )?;
     }
 
     Ok(())
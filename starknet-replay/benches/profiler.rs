@@ -0,0 +1,61 @@
+//! Benchmark of the extraction of Sierra statement frequencies from the
+//! histogram of visited program counters.
+//!
+//! The indexed lookup of [`SierraProfiler::collect_profiling_info`] is compared
+//! with a linear scan of the list of compiled statements for each program
+//! counter.
+
+use std::collections::HashMap;
+use std::fs;
+
+use cairo_lang_sierra::program::{Program, StatementIdx};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use starknet_replay::profiler::SierraProfiler;
+use starknet_replay::runner::pc_histogram::PcHistogram;
+
+fn read_sierra_program() -> Program {
+    let filename = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sierra_program.json");
+    let sierra_program_json =
+        fs::read_to_string(filename).unwrap_or_else(|_| panic!("Unable to read file {filename}"));
+    serde_json::from_str(&sierra_program_json)
+        .unwrap_or_else(|_| panic!("Unable to parse {filename} to Program"))
+}
+
+/// Returns the frequency of Sierra statements scanning all the compiled
+/// statements for each program counter.
+fn collect_profiling_info_linear_scan(
+    profiler: &SierraProfiler,
+    pcs: &PcHistogram,
+) -> HashMap<StatementIdx, usize> {
+    let mut sierra_statement_weights = HashMap::default();
+    for (pc, frequency) in pcs {
+        for statement in profiler.commands.iter().filter(|c| c.pc() == *pc) {
+            let statement_idx = StatementIdx(statement.statement_idx());
+            *sierra_statement_weights.entry(statement_idx).or_insert(0) += frequency;
+        }
+    }
+    sierra_statement_weights
+}
+
+fn bench_profiler(c: &mut Criterion) {
+    let sierra_program = read_sierra_program();
+    let profiler = SierraProfiler::new(sierra_program.clone(), None).unwrap();
+    // All the program counters of the contract are visited.
+    let pcs: PcHistogram = profiler.commands.iter().map(|c| c.pc()).collect();
+
+    let mut group = c.benchmark_group("collect_profiling_info");
+    group.bench_function("indexed", |b| {
+        b.iter(|| profiler.collect_profiling_info(black_box(&pcs)));
+    });
+    group.bench_function("linear_scan", |b| {
+        b.iter(|| collect_profiling_info_linear_scan(&profiler, black_box(&pcs)));
+    });
+    group.finish();
+
+    c.bench_function("sierra_profiler_new", |b| {
+        b.iter(|| SierraProfiler::new(black_box(sierra_program.clone()), None).unwrap());
+    });
+}
+
+criterion_group!(benches, bench_profiler);
+criterion_main!(benches);
@@ -24,6 +24,11 @@ pub enum Error {
     #[error("The list of `libfuncs` called is empty. Can't create histogram.")]
     Empty,
 
+    /// `InvalidPattern` variant is returned when the pattern to filter the
+    /// libfuncs isn't a valid regular expression.
+    #[error(transparent)]
+    InvalidPattern(#[from] regex::Error),
+
     /// The `Unknown` variant is for any other uncategorised error.
     #[error("Unknown Error generating libfunc histogram: {0:?}")]
     Unknown(String),
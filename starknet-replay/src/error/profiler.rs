@@ -41,6 +41,32 @@ pub enum Error {
     #[error("The list of visited program counters is empty. Can't continue profiling.")]
     EmptyProgramCounterList,
 
+    /// `BytecodeMismatch` is returned in function
+    /// [`crate::profiler::SierraProfiler#method.check_bytecode`] when the
+    /// program compiled by the profiler doesn't match the bytecode executed
+    /// by [`blockifier`].
+    #[error("The compiled program doesn't match the executed bytecode at PC {0}.")]
+    BytecodeMismatch(usize),
+
+    /// `MissingCircuitInfo` is returned when the Sierra gas cost of a libfunc
+    /// invocation requires the info of a circuit type which isn't declared in
+    /// the Sierra program.
+    #[error("The info of circuit type {0} is missing.")]
+    MissingCircuitInfo(String),
+
+    /// `InvalidStatistics` is returned in function
+    /// [`crate::profiler::replay_statistics::ReplayStatistics#method.
+    /// from_csv_bytes`] when a line of the libfunc statistics can't be
+    /// parsed.
+    #[error("Failed parsing libfunc statistics at line {0}: {1}")]
+    InvalidStatistics(usize, String),
+
+    /// `IncompatibleStatistics` is returned when libfunc statistics with
+    /// different settings are merged or compared, or when a setting can't be
+    /// applied to libfunc statistics.
+    #[error("Incompatible libfunc statistics: {0}.")]
+    IncompatibleStatistics(String),
+
     /// `Save` variant is for errors reported when saving the result of libfuncs
     /// statistics to file.
     #[error(transparent)]
@@ -50,6 +76,11 @@ pub enum Error {
     #[error(transparent)]
     Serde(#[from] serde_json::Error),
 
+    /// `Database` variant is for errors reported by the crate [`rusqlite`]
+    /// when writing the results database.
+    #[error(transparent)]
+    Database(#[from] rusqlite::Error),
+
     /// The `Unknown` variant is for any other uncategorised error.
     #[error("Unknown Error during in profiler: {0:?}")]
     Unknown(String),
@@ -37,4 +37,14 @@ pub enum Error {
     /// of bytes into a [`String`].
     #[error(transparent)]
     DecodeBytes(#[from] Utf8Error),
+
+    /// `Request` variant is used for errors generated by JSON-RPC requests not
+    /// supported by [`starknet_providers`].
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+
+    /// `Serde` variant is used for errors generated when deserialising the
+    /// result of JSON-RPC requests not supported by [`starknet_providers`].
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
 }
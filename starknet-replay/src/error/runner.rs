@@ -71,6 +71,12 @@ pub enum Error {
     #[error(transparent)]
     State(#[from] StateError),
 
+    /// `InvalidVisitedPcsFile` variant is for errors reported when reading a
+    /// file of visited program counters which is truncated or has not been
+    /// saved by [`crate::runner::transaction_visited_pcs::write_visited_pcs`].
+    #[error("Invalid file of visited program counters: {0}")]
+    InvalidVisitedPcsFile(String),
+
     /// The `Unknown` variant is for any other uncategorised error.
     #[error("Unknown Error during block replay: {0:?}")]
     Unknown(String),
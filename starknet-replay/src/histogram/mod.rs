@@ -3,21 +3,183 @@
 //!
 //! The file `mod.rs` contains the public interface. The main entry function to
 //! use this module is by calling the function [`export`] to render and save the
-//! SVG image.
+//! SVG image. The function [`export_time_series`] renders and saves the line
+//! chart of the libfunc usage over time and the function [`export_diff`]
+//! renders and saves the bar chart of the change in share of each libfunc
+//! between two replays.
+//!
+//! The libfuncs plotted in the histogram are filtered, sorted and scaled as
+//! set in [`HistogramOptions`].
 
+use std::fmt::{self, Display, Formatter};
 use std::fs;
 use std::ops::{Add, Div};
 use std::path::PathBuf;
+use std::str::FromStr;
+
+use regex::Regex;
 
 use super::profiler::replay_statistics::ReplayStatistics;
+use super::profiler::statistics_diff::{LibfuncChange, StatisticsDiff, BIGGEST_MOVERS};
+use super::profiler::time_series_statistics::TimeSeriesStatistics;
 use crate::error::HistogramError;
-use crate::histogram::plot::render;
+use crate::histogram::plot::{render, render_diff, render_time_series};
 
 mod plot;
 
 /// Histogram dimensions are set in pixels using a `u32` type.
 type PixelCount = u32;
 
+/// The number of libfuncs plotted in the line chart of the libfunc usage over
+/// time.
+const TIME_SERIES_LIBFUNCS: usize = 10;
+
+/// The minimum width in pixels of the line chart of the libfunc usage over
+/// time.
+const TIME_SERIES_MIN_WIDTH: PixelCount = 1200;
+
+/// The height in pixels of the plotting area of the line chart of the libfunc
+/// usage over time.
+const TIME_SERIES_HEIGHT: PixelCount = 1000;
+
+/// The height in pixels of the plotting area of the bar chart of the change in
+/// share of each libfunc.
+const DIFF_HEIGHT: PixelCount = 800;
+
+/// The height in pixels of the plotting area of the histogram with
+/// logarithmic y axis.
+const LOG_SCALE_HEIGHT: PixelCount = 1000;
+
+/// The order of the libfuncs on the x axis of the histogram.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum SortOrder {
+    /// Libfuncs are sorted from the highest weight. Libfuncs with the same
+    /// weight are sorted by name.
+    #[default]
+    Value,
+
+    /// Libfuncs are sorted by name.
+    Name,
+}
+impl Display for SortOrder {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let sort_order = match self {
+            SortOrder::Value => "value",
+            SortOrder::Name => "name",
+        };
+        write!(f, "{sort_order}")
+    }
+}
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "value" => Ok(SortOrder::Value),
+            "name" => Ok(SortOrder::Name),
+            _ => Err(format!(
+                "Invalid sort order `{s}`. Expected `value` or `name`."
+            )),
+        }
+    }
+}
+
+/// This struct contains the options to filter, sort and scale the libfuncs
+/// plotted in the histogram.
+///
+/// Filters are applied in order: first `include` and `exclude`, then
+/// `cumulative_share` and finally `top`.
+#[derive(Debug, Clone, Default)]
+pub struct HistogramOptions {
+    /// If set, only the most called libfuncs which together account for this
+    /// percentage of the libfunc calls are plotted.
+    pub cumulative_share: Option<usize>,
+
+    /// If set, only this number of the most called libfuncs is plotted.
+    pub top: Option<usize>,
+
+    /// If set, only the libfuncs whose name matches this pattern are plotted.
+    pub include: Option<Regex>,
+
+    /// If set, the libfuncs whose name matches this pattern aren't plotted.
+    pub exclude: Option<Regex>,
+
+    /// Set to plot the y axis in logarithmic scale.
+    pub log_scale: bool,
+
+    /// The order of the libfuncs on the x axis.
+    pub sort: SortOrder,
+}
+impl HistogramOptions {
+    /// Returns the options with the pattern `include` of the libfuncs to plot.
+    ///
+    /// # Arguments
+    ///
+    /// - `include`: The regular expression matching the names of the libfuncs
+    ///   to plot.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if `include` isn't a valid regular expression.
+    pub fn with_include(mut self, include: &str) -> Result<Self, HistogramError> {
+        self.include = Some(Regex::new(include)?);
+        Ok(self)
+    }
+
+    /// Returns the options with the pattern `exclude` of the libfuncs not to
+    /// plot.
+    ///
+    /// # Arguments
+    ///
+    /// - `exclude`: The regular expression matching the names of the libfuncs
+    ///   not to plot.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if `exclude` isn't a valid regular expression.
+    pub fn with_exclude(mut self, exclude: &str) -> Result<Self, HistogramError> {
+        self.exclude = Some(Regex::new(exclude)?);
+        Ok(self)
+    }
+
+    /// Returns the libfuncs of `libfunc_stats` which pass the filters.
+    ///
+    /// # Arguments
+    ///
+    /// - `libfunc_stats`: The libfunc statistics to filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use starknet_replay::histogram::HistogramOptions;
+    /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+    /// let mut replay_statistics = ReplayStatistics::default();
+    /// replay_statistics.update(&"store_temp".to_string(), 367);
+    /// replay_statistics.update(&"enum_match".to_string(), 895);
+    /// replay_statistics.update(&"u32_to_felt252".to_string(), 759);
+    /// let options = HistogramOptions {
+    ///     top: Some(1),
+    ///     ..HistogramOptions::default()
+    /// }
+    /// .with_exclude("^enum_")
+    /// .unwrap();
+    /// let filtered_statistics = options.filter(&replay_statistics);
+    /// assert_eq!(filtered_statistics.get_libfuncs(), vec!["u32_to_felt252"]);
+    /// ```
+    #[must_use]
+    pub fn filter(&self, libfunc_stats: &ReplayStatistics) -> ReplayStatistics {
+        let mut filtered_stats =
+            libfunc_stats.filter_names(self.include.as_ref(), self.exclude.as_ref());
+        if let Some(percentage) = self.cumulative_share {
+            filtered_stats = filtered_stats.filter_cumulative_share(percentage);
+        }
+        if let Some(count) = self.top {
+            filtered_stats = filtered_stats.filter_top(count);
+        }
+        filtered_stats
+    }
+}
+
 /// This struct contains the variable configuration parameters for rendering the
 /// histogram image.
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -33,6 +195,12 @@ pub struct Config {
 
     /// Number of pixels used below the x-axis for the labels.
     pub x_label_area: PixelCount,
+
+    /// Set to plot the y axis in logarithmic scale.
+    pub log_scale: bool,
+
+    /// The order of the libfuncs on the x axis of the histogram.
+    pub sort: SortOrder,
 }
 impl Config {
     /// Constructs a new [`Config`] object.
@@ -40,6 +208,7 @@ impl Config {
     /// # Arguments
     ///
     /// - `libfunc_stats`: the data to be plotted on the histogram.
+    /// - `options`: the scale of the y axis and the order of the libfuncs.
     ///
     /// # Errors
     ///
@@ -49,7 +218,10 @@ impl Config {
     ///
     /// - There is a math overflow when computing the [`Config`] parameters
     /// - There is a truncation when casting from `usize` to `u32`.
-    pub fn new(libfunc_stats: &ReplayStatistics) -> Result<Self, HistogramError> {
+    pub fn new(
+        libfunc_stats: &ReplayStatistics,
+        options: &HistogramOptions,
+    ) -> Result<Self, HistogramError> {
         let max_frequency = libfunc_stats
             .get_highest_frequency()
             .ok_or(HistogramError::Empty)?;
@@ -57,7 +229,14 @@ impl Config {
         let x_label_area = Self::calc_x_label_area(libfunc_stats);
         let width = Self::calc_width(number_of_buckets);
         let max_y_axis = Self::calc_max_y_axis(max_frequency);
-        let height = Self::calc_height(max_y_axis, x_label_area);
+        // The height of the logarithmic y axis doesn't depend on `max_y_axis`.
+        let height = if options.log_scale {
+            LOG_SCALE_HEIGHT
+                .checked_add(x_label_area)
+                .expect("Overflow in new")
+        } else {
+            Self::calc_height(max_y_axis, x_label_area)
+        };
 
         tracing::info!("Number of buckets {number_of_buckets}");
         tracing::info!("Max y axis {max_y_axis}");
@@ -67,6 +246,110 @@ impl Config {
             height,
             max_y_axis,
             x_label_area,
+            log_scale: options.log_scale,
+            sort: options.sort,
+        })
+    }
+
+    /// Constructs a new [`Config`] object for the line chart of the libfunc
+    /// usage over time.
+    ///
+    /// # Arguments
+    ///
+    /// - `time_series`: the data to be plotted on the line chart.
+    /// - `libfuncs`: the libfuncs plotted on the line chart.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there are no buckets or no libfuncs to plot on the
+    /// line chart.
+    ///
+    /// # Panics
+    ///
+    /// - There is a math overflow when computing the [`Config`] parameters
+    /// - There is a truncation when casting from `usize` to `u32`.
+    pub fn new_time_series(
+        time_series: &TimeSeriesStatistics,
+        libfuncs: &[&str],
+    ) -> Result<Self, HistogramError> {
+        let max_frequency = time_series
+            .buckets
+            .values()
+            .flat_map(|statistics| {
+                libfuncs
+                    .iter()
+                    .map(|libfunc| statistics.get_libfunc_frequency(libfunc))
+            })
+            .max()
+            .ok_or(HistogramError::Empty)?;
+        let labels = time_series.labels();
+        let chars_longest_label = labels.iter().map(String::len).max().unwrap_or_default();
+        let x_label_area = Self::calc_label_area(chars_longest_label);
+        let width = Self::calc_width(labels.len()).max(TIME_SERIES_MIN_WIDTH);
+        let max_y_axis = Self::calc_max_y_axis(max_frequency);
+        let height = TIME_SERIES_HEIGHT
+            .checked_add(x_label_area)
+            .expect("Overflow in new_time_series");
+
+        tracing::info!("Number of time buckets {}", labels.len());
+        tracing::info!("Max y axis {max_y_axis}");
+
+        Ok(Config {
+            width,
+            height,
+            max_y_axis,
+            x_label_area,
+            log_scale: false,
+            sort: SortOrder::default(),
+        })
+    }
+
+    /// Constructs a new [`Config`] object for the bar chart of the change in
+    /// share of each libfunc between two replays.
+    ///
+    /// The field `max_y_axis` is the biggest absolute change in share in
+    /// percentage points, rounded up.
+    ///
+    /// # Arguments
+    ///
+    /// - `changes`: the changes to be plotted on the bar chart.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there are no libfuncs to plot on the bar chart.
+    ///
+    /// # Panics
+    ///
+    /// - There is a math overflow when computing the [`Config`] parameters
+    /// - There is a truncation when casting from `usize` to `u32`.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Added because the share change is at most 100.
+    pub fn new_diff(changes: &[LibfuncChange]) -> Result<Self, HistogramError> {
+        if changes.is_empty() {
+            return Err(HistogramError::Empty);
+        }
+        let max_share_change = changes
+            .iter()
+            .map(|change| (change.share_change() * 100.0).abs())
+            .fold(0.0, f64::max);
+        let chars_longest_name = changes
+            .iter()
+            .map(|change| change.name.len())
+            .max()
+            .unwrap_or_default();
+        let x_label_area = Self::calc_label_area(chars_longest_name);
+        let width = Self::calc_width(changes.len());
+        let max_y_axis = (max_share_change.ceil() as usize).max(1);
+        let height = DIFF_HEIGHT
+            .checked_add(x_label_area)
+            .expect("Overflow in new_diff");
+
+        Ok(Config {
+            width,
+            height,
+            max_y_axis,
+            x_label_area,
+            log_scale: false,
+            sort: SortOrder::default(),
         })
     }
 
@@ -88,8 +371,23 @@ impl Config {
             .max_by_key(|p| p.len())
             .unwrap_or(&"")
             .len();
+        Self::calc_label_area(chars_longest_name)
+    }
+
+    /// Calculate the space required to render labels rotated by 90 degrees.
+    ///
+    /// # Arguments
+    ///
+    /// - `chars_longest_label`: The number of characters of the longest label.
+    ///
+    /// # Panics
+    ///
+    /// Panics if:
+    /// - There is a math overflow when computing the number of pixels.
+    /// - There is a truncation when casting from `usize` to `u32`.
+    fn calc_label_area(chars_longest_label: usize) -> PixelCount {
         let pixels_per_char: usize = 15;
-        let x_label_area_size: usize = chars_longest_name
+        let x_label_area_size: usize = chars_longest_label
             .checked_mul(pixels_per_char)
             .expect("Overflow in calc_x_label_area");
         let x_label_area_size: PixelCount =
@@ -196,16 +494,20 @@ fn save(filename: &PathBuf, content: &impl ToString) -> Result<(), HistogramErro
 
 /// This function generates and saves the libfunc frequency histogram.
 ///
+/// The libfuncs are filtered, sorted and scaled as set in `options`.
+///
 /// # Arguments
 ///
 /// - `filename`: The filename to output the SVG.
 /// - `title`: The title of the histogram.
 /// - `libfunc_stats`: The object containing libfunc statistics.
+/// - `options`: The options to filter, sort and scale the libfuncs.
 ///
 /// # Errors
 ///
 /// Returns [`Err`] if:
 ///
+/// - No libfunc passes the filters of `options`.
 /// - The `filename` can't be written to.
 /// - There is any error rendering the data.
 ///
@@ -216,7 +518,7 @@ fn save(filename: &PathBuf, content: &impl ToString) -> Result<(), HistogramErro
 /// # Examples
 ///
 /// ```
-/// # use starknet_replay::histogram::export;
+/// # use starknet_replay::histogram::{export, HistogramOptions, SortOrder};
 /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
 /// let mut replay_statistics = ReplayStatistics::default();
 /// replay_statistics.update(&"store_temp".to_string(), 367);
@@ -225,16 +527,132 @@ fn save(filename: &PathBuf, content: &impl ToString) -> Result<(), HistogramErro
 /// replay_statistics.update(&"const_as_immediate".to_string(), 264);
 /// let filename = "doctest.svg";
 /// let title = "Doctest histogram";
-/// export(&filename.into(), title, &replay_statistics).unwrap();
+/// let options = HistogramOptions {
+///     log_scale: true,
+///     sort: SortOrder::Name,
+///     ..HistogramOptions::default()
+/// };
+/// export(&filename.into(), title, &replay_statistics, &options).unwrap();
 /// ```
 pub fn export(
     filename: &PathBuf,
     title: &str,
     libfunc_stats: &ReplayStatistics,
+    options: &HistogramOptions,
 ) -> Result<(), HistogramError> {
-    let config = Config::new(libfunc_stats)?;
+    let libfunc_stats = options.filter(libfunc_stats);
+    let config = Config::new(&libfunc_stats, options)?;
 
-    let content = render(title, &config, libfunc_stats)?;
+    let content = render(title, &config, &libfunc_stats)?;
+
+    save(filename, &content)
+}
+
+/// This function generates and saves the line chart of the libfunc usage over
+/// time.
+///
+/// Only the [`TIME_SERIES_LIBFUNCS`] libfuncs with the highest weight over all
+/// buckets are plotted.
+///
+/// # Arguments
+///
+/// - `filename`: The filename to output the SVG.
+/// - `title`: The title of the line chart.
+/// - `time_series`: The object containing libfunc statistics of each bucket.
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - There are no buckets or no libfuncs to plot.
+/// - The `filename` can't be written to.
+/// - There is any error rendering the data.
+///
+/// # Examples
+///
+/// ```
+/// # use starknet_replay::histogram::export_time_series;
+/// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+/// # use starknet_replay::profiler::time_series_statistics::{BucketSize, TimeSeriesStatistics};
+/// let mut time_series = TimeSeriesStatistics::new(BucketSize::Blocks(100));
+/// for (bucket, frequency) in [(632_000, 367), (632_100, 895), (632_200, 759)] {
+///     let mut replay_statistics = ReplayStatistics::default();
+///     replay_statistics.update(&"store_temp".to_string(), frequency);
+///     replay_statistics.update(&"enum_match".to_string(), frequency / 2);
+///     time_series.add(bucket, &replay_statistics);
+/// }
+/// let filename = "doctest_time_series.svg";
+/// let title = "Doctest line chart";
+/// export_time_series(&filename.into(), title, &time_series).unwrap();
+/// ```
+pub fn export_time_series(
+    filename: &PathBuf,
+    title: &str,
+    time_series: &TimeSeriesStatistics,
+) -> Result<(), HistogramError> {
+    let libfuncs = time_series.top_libfuncs(TIME_SERIES_LIBFUNCS);
+    let config = Config::new_time_series(time_series, &libfuncs)?;
+
+    let content = render_time_series(title, &config, time_series, &libfuncs)?;
+
+    save(filename, &content)
+}
+
+/// This function generates and saves the bar chart of the change in share of
+/// the libfuncs between two replays.
+///
+/// Only the [`BIGGEST_MOVERS`] libfuncs with the biggest absolute change in
+/// share are plotted.
+///
+/// # Arguments
+///
+/// - `filename`: The filename to output the SVG.
+/// - `title`: The title of the bar chart.
+/// - `statistics_diff`: The object comparing the libfunc statistics of two
+///   replays.
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - There are no libfuncs to plot.
+/// - The `filename` can't be written to.
+/// - There is any error rendering the data.
+///
+/// # Examples
+///
+/// ```
+/// # use starknet_replay::histogram::export_diff;
+/// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+/// # use starknet_replay::profiler::statistics_diff::StatisticsDiff;
+/// let mut before = ReplayStatistics::default();
+/// before.update(&"store_temp".to_string(), 367);
+/// before.update(&"enum_match".to_string(), 895);
+/// let mut after = ReplayStatistics::default();
+/// after.update(&"store_temp".to_string(), 759);
+/// after.update(&"u32_to_felt252".to_string(), 264);
+/// let filename = "doctest_diff.svg";
+/// let title = "Doctest diff";
+/// export_diff(
+///     &filename.into(),
+///     title,
+///     &StatisticsDiff::new(before, after).unwrap(),
+/// )
+/// .unwrap();
+/// ```
+pub fn export_diff(
+    filename: &PathBuf,
+    title: &str,
+    statistics_diff: &StatisticsDiff,
+) -> Result<(), HistogramError> {
+    let changes: Vec<LibfuncChange> = statistics_diff
+        .changes()
+        .into_iter()
+        .take(BIGGEST_MOVERS)
+        .collect();
+    let config = Config::new_diff(&changes)?;
+
+    let content = render_diff(title, &config, &changes)?;
 
     save(filename, &content)
 }
@@ -318,6 +736,45 @@ mod tests {
             generate_dummy_replay_statistics(string_len, number_libfuncs, max_frequency);
         let filename = "test_generate_histogram.svg";
         let title = "Running test_generate_histogram";
-        export(&filename.into(), title, &replay_statistics).unwrap();
+        export(
+            &filename.into(),
+            title,
+            &replay_statistics,
+            &HistogramOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_histogram_options_filter() {
+        let mut replay_statistics = ReplayStatistics::default();
+        replay_statistics.update(&"store_temp", 500);
+        replay_statistics.update(&"enum_match", 300);
+        replay_statistics.update(&"u32_to_felt252", 150);
+        replay_statistics.update(&"u32_overflowing_add", 50);
+
+        let options = HistogramOptions::default();
+        assert_eq!(options.filter(&replay_statistics), replay_statistics);
+
+        let options = HistogramOptions {
+            cumulative_share: Some(80),
+            ..HistogramOptions::default()
+        };
+        let filtered_statistics = options.filter(&replay_statistics);
+        assert_eq!(filtered_statistics.get_number_of_libfuncs(), 2);
+        assert_eq!(filtered_statistics.get_libfunc_frequency("enum_match"), 300);
+
+        let options = HistogramOptions {
+            top: Some(1),
+            ..HistogramOptions::default()
+        }
+        .with_include("^u32_")
+        .unwrap();
+        let filtered_statistics = options.filter(&replay_statistics);
+        assert_eq!(filtered_statistics.get_libfuncs(), vec!["u32_to_felt252"]);
+
+        assert!(HistogramOptions::default().with_exclude("(").is_err());
+        assert_eq!("name".parse::<SortOrder>(), Ok(SortOrder::Name));
+        assert!("random".parse::<SortOrder>().is_err());
     }
 }
@@ -1,20 +1,30 @@
 //! This module uses the library [`plotters`] to render and export the
-//! histogram.
+//! histogram, the line chart of the libfunc usage over time and the bar chart
+//! of the change in share of each libfunc between two replays.
 //!
-//! To use another plotting library, it's sufficient to rewrite the function
-//! [`crate::histogram::render`].
+//! To use another plotting library, it's sufficient to rewrite the functions
+//! [`crate::histogram::render`], [`crate::histogram::render_time_series`] and
+//! [`crate::histogram::render_diff`].
 
+use std::ops::AddAssign;
+
+use itertools::Itertools;
 use plotters::backend::SVGBackend;
-use plotters::chart::ChartBuilder;
-use plotters::coord::ranged1d::{IntoSegmentedCoord, SegmentValue};
-use plotters::drawing::IntoDrawingArea;
-use plotters::series::Histogram;
-use plotters::style::full_palette::{RED, WHITE};
+use plotters::chart::{ChartBuilder, SeriesLabelPosition};
+use plotters::coord::combinators::IntoLogRange;
+use plotters::coord::ranged1d::{AsRangedCoord, IntoSegmentedCoord, SegmentValue, ValueFormatter};
+use plotters::coord::Shift;
+use plotters::drawing::{DrawingArea, IntoDrawingArea};
+use plotters::element::PathElement;
+use plotters::series::{Histogram, LineSeries};
+use plotters::style::full_palette::{BLACK, GREEN, RED, WHITE};
 use plotters::style::text_anchor::{HPos, Pos, VPos};
-use plotters::style::{Color, FontTransform, IntoFont, TextStyle};
+use plotters::style::{Color, FontTransform, IntoFont, Palette, Palette99, TextStyle};
 
-use crate::histogram::{Config, HistogramError};
+use crate::histogram::{Config, HistogramError, SortOrder};
 use crate::profiler::replay_statistics::ReplayStatistics;
+use crate::profiler::statistics_diff::LibfuncChange;
+use crate::profiler::time_series_statistics::TimeSeriesStatistics;
 
 /// Create and export the histogram as SVG file.
 ///
@@ -41,6 +51,28 @@ pub fn render(
     Ok(buffer.clone())
 }
 
+/// Returns the libfuncs of `libfunc_stats` in the order `sort`.
+///
+/// # Arguments
+///
+/// - `libfunc_stats`: The input data to be plotted.
+/// - `sort`: The order of the libfuncs.
+fn sorted_libfuncs(libfunc_stats: &ReplayStatistics, sort: SortOrder) -> Vec<&str> {
+    let libfuncs = libfunc_stats.get_libfuncs().into_iter();
+    match sort {
+        SortOrder::Value => libfuncs
+            .sorted_by(|a, b| {
+                Ord::cmp(
+                    &libfunc_stats.get_libfunc_frequency(b),
+                    &libfunc_stats.get_libfunc_frequency(a),
+                )
+                .then(Ord::cmp(a, b))
+            })
+            .collect(),
+        SortOrder::Name => libfuncs.sorted().collect(),
+    }
+}
+
 /// Internal function to call [`plotters`] rendering backend.
 ///
 /// # Arguments
@@ -55,26 +87,81 @@ fn render_with_plotter(
     libfunc_stats: &ReplayStatistics,
     buffer: &mut String,
 ) -> Result<(), HistogramError> {
-    let list_of_libfuncs = libfunc_stats.get_libfuncs();
+    let list_of_libfuncs = sorted_libfuncs(libfunc_stats, config.sort);
     let root = SVGBackend::with_string(buffer, (config.width, config.height)).into_drawing_area();
 
     root.fill(&WHITE)?;
 
-    let mut chart = ChartBuilder::on(&root)
+    if config.log_scale {
+        // The logarithmic y axis starts from 1 because the logarithm of 0 isn't
+        // defined. Libfuncs with frequency 0 are plotted as frequency 1.
+        #[allow(clippy::cast_precision_loss)]
+        // Added because the position of the bars doesn't need to be exact.
+        let to_log_value = |frequency: usize| frequency.max(1) as f64;
+        draw_histogram(
+            &root,
+            title,
+            config,
+            libfunc_stats,
+            &list_of_libfuncs,
+            (1.0..to_log_value(config.max_y_axis)).log_scale(),
+            1.0,
+            to_log_value,
+        )
+    } else {
+        draw_histogram(
+            &root,
+            title,
+            config,
+            libfunc_stats,
+            &list_of_libfuncs,
+            0..config.max_y_axis,
+            0,
+            |frequency| frequency,
+        )
+    }
+}
+
+/// Draws the histogram with the y axis `y_axis` on `root`.
+///
+/// # Arguments
+///
+/// - `root`: The drawing area of the SVG image.
+/// - `title`: The title of the histogram.
+/// - `config`: The configuration object of the histogram.
+/// - `libfunc_stats`: The input data to be plotted.
+/// - `list_of_libfuncs`: The libfuncs to be plotted, in order.
+/// - `y_axis`: The range of the y axis.
+/// - `baseline`: The value on the y axis where the bars start.
+/// - `to_value`: The function converting a frequency to a value on the y axis.
+#[allow(clippy::too_many_arguments)] // Added because the arguments are the inputs of `render_with_plotter`.
+fn draw_histogram<Y>(
+    root: &DrawingArea<SVGBackend, Shift>,
+    title: &str,
+    config: &Config,
+    libfunc_stats: &ReplayStatistics,
+    list_of_libfuncs: &[&str],
+    y_axis: Y,
+    baseline: Y::Value,
+    to_value: impl Fn(usize) -> Y::Value,
+) -> Result<(), HistogramError>
+where
+    Y: AsRangedCoord,
+    Y::CoordDescType: ValueFormatter<Y::Value>,
+    Y::Value: AddAssign + Default + Clone,
+{
+    let mut chart = ChartBuilder::on(root)
         .x_label_area_size(config.x_label_area)
         .y_label_area_size(150)
         .margin(30)
         .caption(title, ("sans-serif", 50.0))
-        .build_cartesian_2d(
-            list_of_libfuncs.as_slice().into_segmented(),
-            0..config.max_y_axis,
-        )?;
+        .build_cartesian_2d(list_of_libfuncs.into_segmented(), y_axis)?;
 
     // The use of `x_label_formatter` ensures labels aren't printed with quotes
     // around them.
     chart
         .configure_mesh()
-        .x_labels(libfunc_stats.get_number_of_libfuncs())
+        .x_labels(list_of_libfuncs.len())
         .x_label_formatter(&|pos| match pos {
             SegmentValue::Exact(t) | SegmentValue::CenterOf(t) => (**t).to_string(),
             SegmentValue::Last => String::new(),
@@ -98,11 +185,219 @@ fn render_with_plotter(
     chart.draw_series(
         Histogram::vertical(&chart)
             .style(RED.mix(0.5).filled())
+            .baseline(baseline)
             .data(list_of_libfuncs.iter().map(|libfunc_name| {
                 let frequency = libfunc_stats.get_libfunc_frequency(libfunc_name);
-                (libfunc_name, frequency)
+                (libfunc_name, to_value(frequency))
             })),
     )?;
 
     Ok(())
 }
+
+/// Create and export the line chart of the libfunc usage over time as SVG
+/// file.
+///
+/// # Arguments
+///
+/// - `title`: The title of the line chart.
+/// - `config`: The configuration object of the line chart.
+/// - `time_series`: The input data to be plotted.
+/// - `libfuncs`: The libfuncs to be plotted, one line each.
+///
+/// # Errors
+///
+/// Returns [`Err`] if there is an error rendering the line chart.
+pub fn render_time_series(
+    title: &str,
+    config: &Config,
+    time_series: &TimeSeriesStatistics,
+    libfuncs: &[&str],
+) -> Result<String, HistogramError> {
+    let mut buffer: String = String::default();
+    render_time_series_with_plotter(title, config, time_series, libfuncs, &mut buffer)?;
+    Ok(buffer.clone())
+}
+
+/// Internal function to call [`plotters`] rendering backend for the line
+/// chart.
+///
+/// The x axis is the index of the bucket, labelled with the label of the
+/// bucket.
+///
+/// # Arguments
+///
+/// - `title`: The title of the line chart.
+/// - `config`: The configuration object of the line chart.
+/// - `time_series`: The input data to be plotted.
+/// - `libfuncs`: The libfuncs to be plotted, one line each.
+/// - `buffer`: The buffer that contains the SVG image.
+fn render_time_series_with_plotter(
+    title: &str,
+    config: &Config,
+    time_series: &TimeSeriesStatistics,
+    libfuncs: &[&str],
+    buffer: &mut String,
+) -> Result<(), HistogramError> {
+    let labels = time_series.labels();
+    let root = SVGBackend::with_string(buffer, (config.width, config.height)).into_drawing_area();
+
+    root.fill(&WHITE)?;
+
+    // The x axis needs at least two points to be rendered.
+    let last_bucket = labels.len().saturating_sub(1).max(1);
+    let mut chart = ChartBuilder::on(&root)
+        .x_label_area_size(config.x_label_area)
+        .y_label_area_size(150)
+        .margin(30)
+        .caption(title, ("sans-serif", 50.0))
+        .build_cartesian_2d(0..last_bucket, 0..config.max_y_axis)?;
+
+    chart
+        .configure_mesh()
+        .x_labels(labels.len())
+        .x_label_formatter(&|bucket: &usize| labels.get(*bucket).cloned().unwrap_or_default())
+        .max_light_lines(1)
+        .bold_line_style(BLACK.mix(0.1))
+        .y_desc("Weight")
+        .x_desc("Bucket")
+        .x_label_style(
+            // When rotating 90 deg, `HPos` controls the vertical position.
+            // `VPos` controls the horizontal position.
+            TextStyle::from(("sans-serif", 20).into_font())
+                .transform(FontTransform::Rotate90)
+                .pos(Pos::new(HPos::Left, VPos::Center)),
+        )
+        .axis_desc_style(("sans-serif", 35))
+        .draw()?;
+
+    for (idx, libfunc) in libfuncs.iter().enumerate() {
+        let color = Palette99::pick(idx).to_rgba();
+        chart
+            .draw_series(LineSeries::new(
+                time_series
+                    .buckets
+                    .values()
+                    .enumerate()
+                    .map(|(bucket, statistics)| {
+                        (bucket, statistics.get_libfunc_frequency(libfunc))
+                    }),
+                color.stroke_width(3),
+            ))?
+            .label(*libfunc)
+            .legend(move |(x, y)| {
+                PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(3))
+            });
+    }
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperRight)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .label_font(("sans-serif", 20))
+        .draw()?;
+
+    Ok(())
+}
+
+/// Create and export the bar chart of the change in share of each libfunc as
+/// SVG file.
+///
+/// # Arguments
+///
+/// - `title`: The title of the bar chart.
+/// - `config`: The configuration object of the bar chart.
+/// - `changes`: The input data to be plotted.
+///
+/// # Errors
+///
+/// Returns [`Err`] if there is an error rendering the bar chart.
+pub fn render_diff(
+    title: &str,
+    config: &Config,
+    changes: &[LibfuncChange],
+) -> Result<String, HistogramError> {
+    let mut buffer: String = String::default();
+    render_diff_with_plotter(title, config, changes, &mut buffer)?;
+    Ok(buffer.clone())
+}
+
+/// Internal function to call [`plotters`] rendering backend for the bar chart
+/// of the change in share of each libfunc.
+///
+/// Increases are drawn in green above the x axis and decreases in red below
+/// it.
+///
+/// # Arguments
+///
+/// - `title`: The title of the bar chart.
+/// - `config`: The configuration object of the bar chart.
+/// - `changes`: The input data to be plotted.
+/// - `buffer`: The buffer that contains the SVG image.
+#[allow(clippy::cast_precision_loss)] // Added because `max_y_axis` is at most 100.
+fn render_diff_with_plotter(
+    title: &str,
+    config: &Config,
+    changes: &[LibfuncChange],
+    buffer: &mut String,
+) -> Result<(), HistogramError> {
+    let list_of_libfuncs: Vec<&str> = changes.iter().map(|change| change.name.as_str()).collect();
+    let max_y_axis = config.max_y_axis as f64;
+    let root = SVGBackend::with_string(buffer, (config.width, config.height)).into_drawing_area();
+
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .x_label_area_size(config.x_label_area)
+        .y_label_area_size(150)
+        .margin(30)
+        .caption(title, ("sans-serif", 50.0))
+        .build_cartesian_2d(
+            list_of_libfuncs.as_slice().into_segmented(),
+            -max_y_axis..max_y_axis,
+        )?;
+
+    // The use of `x_label_formatter` ensures labels aren't printed with quotes
+    // around them.
+    chart
+        .configure_mesh()
+        .x_labels(list_of_libfuncs.len())
+        .x_label_formatter(&|pos| match pos {
+            SegmentValue::Exact(t) | SegmentValue::CenterOf(t) => (**t).to_string(),
+            SegmentValue::Last => String::new(),
+        })
+        .max_light_lines(1)
+        .disable_x_mesh()
+        .bold_line_style(WHITE.mix(0.3))
+        .y_desc("Share change (percentage points)")
+        .x_desc("Libfunc name")
+        .x_label_style(
+            // When rotating 90 deg, `HPos` controls the vertical position.
+            // `VPos` controls the horizontal position.
+            TextStyle::from(("sans-serif", 20).into_font())
+                .transform(FontTransform::Rotate90)
+                .pos(Pos::new(HPos::Left, VPos::Center)),
+        )
+        .axis_desc_style(("sans-serif", 35))
+        .draw()?;
+
+    chart.draw_series(
+        Histogram::vertical(&chart)
+            .style_func(|_, share_change: &f64| {
+                if *share_change >= 0.0 {
+                    GREEN.mix(0.5).filled()
+                } else {
+                    RED.mix(0.5).filled()
+                }
+            })
+            .data(
+                list_of_libfuncs
+                    .iter()
+                    .zip(changes)
+                    .map(|(libfunc_name, change)| (libfunc_name, change.share_change() * 100.0)),
+            ),
+    )?;
+
+    Ok(())
+}
@@ -7,8 +7,8 @@
 //! collect more kinds of data during replay.
 //!
 //! The simplest interaction with this library is to call the function
-//! [`crate::runner::run_replay`] which returns the usage statistics of
-//! libfuncs.
+//! [`crate::runner::run_replay`] which returns the visited program counters
+//! and the other data selected with [`runner::replay_options::ReplayOptions`].
 //!
 //! The key structs of the library are as follows:
 //!
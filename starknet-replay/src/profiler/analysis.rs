@@ -1,19 +1,92 @@
 //! This file contains the code to process a transaction trace and update the
 //! hashmap which keeps the statistics of the number of calls for each libfunc.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
+use cairo_lang_sierra::extensions::gas::CostTokenType;
 use cairo_lang_sierra::program::Program;
+use cairo_lang_sierra_to_casm::metadata::MetadataComputationConfig;
+use cairo_lang_starknet_classes::casm_contract_class::ENTRY_POINT_COST;
 use cairo_lang_starknet_classes::contract_class::ContractClass as CairoContractClass;
+use cairo_lang_starknet_classes::felt252_serde::sierra_from_felt252s;
 use itertools::Itertools;
-use starknet_core::types::ContractClass;
-
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use starknet_api::core::{ClassHash, ContractAddress};
+use starknet_core::types::{ContractClass, Felt, FlattenedSierraClass};
+use starknet_core::utils::get_selector_from_name;
+
+use crate::block_number::BlockNumber;
+use crate::profiler::class_cache::{ClassCache, CompiledClass};
+use crate::profiler::contract_statistics::{AddressStatistics, ContractStatistics};
+use crate::profiler::coverage::ClassCoverage;
+use crate::profiler::entry_point_statistics::{EntryPointStatistics, UNKNOWN_ENTRY_POINT};
+use crate::profiler::flamegraph::{ClassFrames, FoldedStacks};
+use crate::profiler::instruction_statistics::InstructionStatistics;
+use crate::profiler::legacy::LegacyProfiler;
+use crate::profiler::legacy_statistics::LegacyStatistics;
 use crate::profiler::replace_ids::replace_sierra_ids_in_program;
 use crate::profiler::replay_statistics::ReplayStatistics;
+use crate::profiler::skipped_classes::{SkipReason, SkippedClass, SkippedClasses};
+use crate::profiler::time_series_statistics::{BucketSize, TimeSeriesStatistics};
+use crate::profiler::version_statistics::{ClassVersion, VersionStatistics};
 use crate::profiler::{ProfilerError, SierraProfiler};
-use crate::runner::replay_class_hash::VisitedPcs;
+use crate::runner::call_trace::CallTrace;
+use crate::runner::pc_histogram::{EntryPointHistograms, PcHistogram};
+use crate::runner::replay_class_hash::{ReplayClassHash, VisitedPcs};
 use crate::storage::Storage;
 
+/// Converts a [`starknet_core::types::FlattenedSierraClass`] into a
+/// [`cairo_lang_starknet_classes::contract_class::ContractClass`] without ABI.
+///
+/// # Arguments
+///
+/// - `ctx`: The input [`starknet_core::types::FlattenedSierraClass`]
+///
+/// # Errors
+///
+/// Returns [`Err`] if there is a serde deserialisation issue.
+fn get_cairo_contract_class(
+    ctx: &FlattenedSierraClass,
+) -> Result<CairoContractClass, ProfilerError> {
+    let mut json = serde_json::to_value(ctx)?;
+    json.as_object_mut()
+        .ok_or(ProfilerError::Unknown(
+            "Failed serialising `ContractClass`.".to_string(),
+        ))?
+        .remove("abi");
+    let contract_class: CairoContractClass = serde_json::from_value::<CairoContractClass>(json)?;
+    Ok(contract_class)
+}
+
+/// Returns the Sierra version and the compiler version stored in the header of
+/// the Sierra program of a contract class.
+///
+/// Returns [`None`] for legacy contract classes and if the header can't be
+/// decoded.
+///
+/// # Arguments
+///
+/// - `ctx`: The input [`starknet_core::types::ContractClass`]
+fn get_class_version_from_class_definition(ctx: &ContractClass) -> Option<ClassVersion> {
+    let ContractClass::Sierra(ctx) = ctx else {
+        return None;
+    };
+    let contract_class = get_cairo_contract_class(ctx).ok()?;
+    let (sierra_version, compiler_version, _) =
+        sierra_from_felt252s(&contract_class.sierra_program).ok()?;
+    Some(ClassVersion {
+        sierra_version: format!(
+            "{}.{}.{}",
+            sierra_version.major, sierra_version.minor, sierra_version.patch
+        ),
+        compiler_version: format!(
+            "{}.{}.{}",
+            compiler_version.major, compiler_version.minor, compiler_version.patch
+        ),
+    })
+}
+
 /// Converts transforms a [`starknet_core::types::ContractClass`] in Sierra
 /// [`cairo_lang_sierra::program::Program`].
 ///
@@ -26,54 +99,213 @@ use crate::storage::Storage;
 /// Returns [`Err`] if there is a serde deserialisation issue.
 fn get_sierra_program_from_class_definition(ctx: &ContractClass) -> Result<Program, ProfilerError> {
     match ctx {
-        ContractClass::Sierra(ctx) => {
-            let mut json = serde_json::to_value(ctx)?;
-            json.as_object_mut()
-                .ok_or(ProfilerError::Unknown(
-                    "Failed serialising `ContractClass`.".to_string(),
-                ))?
-                .remove("abi");
-            let contract_class: CairoContractClass =
-                serde_json::from_value::<CairoContractClass>(json)?;
-            // TODO: `extract_sierra_program` returns an error of type `Felt252SerdeError`
-            // which is private. For ease of integration with `thiserror`, it needs to be
-            // made public. Issue #20
-            let sierra_program = contract_class.extract_sierra_program().map_err(|_| {
-                ProfilerError::Unknown("Error extracting sierra program".to_string().to_string())
-            })?;
-            let sierra_program = replace_sierra_ids_in_program(&sierra_program);
-            Ok(sierra_program)
-        }
+        ContractClass::Sierra(ctx) => get_sierra_program(&get_cairo_contract_class(ctx)?),
         ContractClass::Legacy(_) => {
             Err(ProfilerError::Unknown("Not a Sierra contract.".to_string()))
         }
     }
 }
 
-/// Returns the frequency of libfuncs for a given Sierra contract.
+/// Extracts the Sierra [`cairo_lang_sierra::program::Program`] of a
+/// [`cairo_lang_starknet_classes::contract_class::ContractClass`].
+///
+/// # Arguments
+///
+/// - `contract_class`: The input
+///   [`cairo_lang_starknet_classes::contract_class::ContractClass`]
+///
+/// # Errors
+///
+/// Returns [`Err`] if the Sierra program can't be decoded.
+fn get_sierra_program(contract_class: &CairoContractClass) -> Result<Program, ProfilerError> {
+    // TODO: `extract_sierra_program` returns an error of type `Felt252SerdeError`
+    // which is private. For ease of integration with `thiserror`, it needs to be
+    // made public. Issue #20
+    let sierra_program = contract_class.extract_sierra_program().map_err(|_| {
+        ProfilerError::Unknown("Error extracting sierra program".to_string().to_string())
+    })?;
+    Ok(replace_sierra_ids_in_program(&sierra_program))
+}
+
+/// Returns the frequency and the cost of concrete and generic libfuncs for a
+/// given Sierra contract.
 ///
 /// # Arguments
 ///
 /// - `runner`: The Sierra profiler object.
-/// - `pcs`: The vector of program counters from each execution of the Sierra
+/// - `pcs`: The histogram of program counters from each execution of the Sierra
 ///   contract in `runner`.
 fn internal_extract_libfuncs_weight(
     runner: &SierraProfiler,
-    pcs: &Vec<usize>,
-) -> HashMap<String, usize> {
-    let raw_profiling_info = runner.collect_profiling_info(pcs.as_slice());
-    runner.unpack_profiling_info(&raw_profiling_info)
+    pcs: &PcHistogram,
+) -> ReplayStatistics {
+    let raw_profiling_info = runner.collect_profiling_info(pcs);
+    let statement_steps = runner.collect_statement_steps(pcs);
+    let (concrete_costs, generic_costs) =
+        runner.unpack_cost_info(&raw_profiling_info, &statement_steps);
+    ReplayStatistics::new()
+        .add_statistics(&runner.unpack_profiling_info(&raw_profiling_info))
+        .add_generic_statistics(&runner.unpack_generic_profiling_info(&raw_profiling_info))
+        .add_cost_statistics(&concrete_costs, &generic_costs)
 }
 
-/// Extracts the frequency of libfuncs from visited program counters.
+/// Collects the names of the functions declared in the ABI of a contract
+/// class.
 ///
-/// The process to extract the frequency of libfuncs called is:
-/// 1- Get the vector of visited program counters
-/// 2- Query the RPC endpoint to extract the Starknet contract from the class
-/// hash and block number.
-/// 3- Run the profiler over the list of visited program
-/// counters to determine which lines of the Sierra code have been executed and
-/// collect the results.
+/// Functions declared inside interfaces are collected as well.
+///
+/// # Arguments
+///
+/// - `abi`: The ABI of the contract class or a part of it.
+/// - `names`: The list where the function names are appended.
+fn collect_abi_function_names(abi: &serde_json::Value, names: &mut Vec<String>) {
+    match abi {
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_abi_function_names(item, names);
+            }
+        }
+        serde_json::Value::Object(item) => {
+            let is_function = matches!(
+                item.get("type").and_then(serde_json::Value::as_str),
+                Some("function" | "l1_handler" | "constructor")
+            );
+            if let (true, Some(name)) = (
+                is_function,
+                item.get("name").and_then(serde_json::Value::as_str),
+            ) {
+                names.push(name.to_string());
+            }
+            if let Some(items) = item.get("items") {
+                collect_abi_function_names(items, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the entry points of a contract class as pairs of Sierra function
+/// index and entry point name.
+///
+/// The name of an entry point is the name of the function in the ABI with the
+/// same selector. If the ABI doesn't contain the selector, the name is the
+/// selector itself. Legacy contract classes have no Sierra entry points.
+///
+/// # Arguments
+///
+/// - `ctx`: The input [`starknet_core::types::ContractClass`]
+fn get_entry_points_from_class_definition(ctx: &ContractClass) -> Vec<(usize, String)> {
+    let ContractClass::Sierra(ctx) = ctx else {
+        return Vec::new();
+    };
+
+    let abi: serde_json::Value = serde_json::from_str(&ctx.abi).unwrap_or_default();
+    let mut function_names = Vec::new();
+    collect_abi_function_names(&abi, &mut function_names);
+    let function_names: HashMap<Felt, String> = function_names
+        .into_iter()
+        .filter_map(|name| Some((get_selector_from_name(&name).ok()?, name)))
+        .collect();
+
+    let entry_points_by_type = &ctx.entry_points_by_type;
+    entry_points_by_type
+        .constructor
+        .iter()
+        .chain(&entry_points_by_type.external)
+        .chain(&entry_points_by_type.l1_handler)
+        .filter_map(|entry_point| {
+            let function_idx = usize::try_from(entry_point.function_idx).ok()?;
+            let name = function_names
+                .get(&entry_point.selector)
+                .cloned()
+                .unwrap_or_else(|| format!("{:#x}", entry_point.selector));
+            Some((function_idx, name))
+        })
+        .collect()
+}
+
+/// Returns the map between the program counter of each entry point and its
+/// name.
+///
+/// # Arguments
+///
+/// - `runner`: The Sierra profiler object.
+/// - `entry_points`: The list of pairs of Sierra function index and entry point
+///   name.
+fn entry_point_names_by_pc(
+    runner: &SierraProfiler,
+    entry_points: Vec<(usize, String)>,
+) -> HashMap<usize, String> {
+    entry_points
+        .into_iter()
+        .filter_map(|(function_idx, name)| {
+            let function = runner.sierra_program.funcs.get(function_idx)?;
+            let pc = runner.statement_pc(function.entry_point)?;
+            Some((pc, name))
+        })
+        .collect()
+}
+
+/// Groups the visited program counters by class hash, merging the histograms
+/// of the same class across different blocks.
+///
+/// The [`ReplayClassHash`] returned for each class is the one with the lowest
+/// block number. It is used to query the contract class from the storage.
+///
+/// # Arguments
+///
+/// - `visited_pcs`: The object that contains the list of visited program
+///   counters for each transaction replayed.
+fn group_by_class_hash(visited_pcs: &VisitedPcs) -> Vec<(ReplayClassHash, EntryPointHistograms)> {
+    let mut classes: HashMap<ClassHash, (ReplayClassHash, EntryPointHistograms)> = HashMap::new();
+    for (replay_class_hash, pcs) in visited_pcs {
+        let (first_replay_class_hash, class_pcs) = classes
+            .entry(replay_class_hash.class_hash)
+            .or_insert_with(|| (*replay_class_hash, EntryPointHistograms::new()));
+        if replay_class_hash.block_number < first_replay_class_hash.block_number {
+            *first_replay_class_hash = *replay_class_hash;
+        }
+        class_pcs.merge(pcs);
+    }
+    classes.into_values().collect()
+}
+
+/// Groups the visited program counters by class hash and by bucket of
+/// blocks, merging the histograms of the same class in the same bucket.
+///
+/// The [`ReplayClassHash`] returned for each class is the one with the lowest
+/// block number. It is used to query the contract class from the storage.
+///
+/// # Arguments
+///
+/// - `visited_pcs`: The object that contains the list of visited program
+///   counters for each transaction replayed.
+/// - `buckets`: The bucket of each block in `visited_pcs`.
+fn group_by_class_hash_and_bucket(
+    visited_pcs: &VisitedPcs,
+    buckets: &HashMap<BlockNumber, u64>,
+) -> Vec<(ReplayClassHash, BTreeMap<u64, EntryPointHistograms>)> {
+    let mut classes: HashMap<ClassHash, (ReplayClassHash, BTreeMap<u64, EntryPointHistograms>)> =
+        HashMap::new();
+    for (replay_class_hash, pcs) in visited_pcs {
+        let Some(bucket) = buckets.get(&replay_class_hash.block_number) else {
+            continue;
+        };
+        let (first_replay_class_hash, class_buckets) = classes
+            .entry(replay_class_hash.class_hash)
+            .or_insert_with(|| (*replay_class_hash, BTreeMap::new()));
+        if replay_class_hash.block_number < first_replay_class_hash.block_number {
+            *first_replay_class_hash = *replay_class_hash;
+        }
+        class_buckets.entry(*bucket).or_default().merge(pcs);
+    }
+    classes.into_values().collect()
+}
+
+/// Runs `profile` over the visited program counters of each contract class.
+///
+/// The visited program counters are grouped by class hash with
+/// [`group_by_class_hash`] and passed to [`profile_grouped_classes`].
 ///
 /// # Arguments
 ///
@@ -81,33 +313,794 @@ fn internal_extract_libfuncs_weight(
 ///   counters for each transaction replayed.
 /// - `storage`: The object to query the starknet blockchain using the RPC
 ///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+/// - `profile`: The function which extracts the result of a contract class from
+///   the contract class definition, the profiler and the histograms of visited
+///   program counters.
+fn profile_classes<T: Send>(
+    visited_pcs: &VisitedPcs,
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+    profile: impl Fn(&ReplayClassHash, &ContractClass, &Arc<SierraProfiler>, &EntryPointHistograms) -> T
+        + Sync,
+) -> (Vec<T>, SkippedClasses) {
+    profile_grouped_classes(
+        &group_by_class_hash(visited_pcs),
+        storage,
+        class_cache,
+        EntryPointHistograms::total,
+        profile,
+    )
+}
+
+/// Runs `profile` over the data of each contract class in `classes`.
+///
+/// The process is:
+/// 1- Query the RPC endpoint to extract the Starknet contract from the class
+/// hash and block number.
+/// 2- Compile the Sierra program of the contract and call `profile` with the
+/// [`SierraProfiler`] of the contract.
+///
+/// Each contract class is compiled only once per [`ClassCache`] and different
+/// classes are processed in parallel. Classes that can't be compiled are
+/// skipped and returned together with the number of visited program counters
+/// lost.
+///
+/// # Arguments
+///
+/// - `classes`: The data of each contract class, keyed by the class hash and
+///   block number used to query the contract class.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+/// - `count_visited_pcs`: The function which returns the number of visited
+///   program counters in the data of a contract class.
+/// - `profile`: The function which extracts the result of a contract class from
+///   the contract class definition, the profiler and the data of the class.
+fn profile_grouped_classes<P: Sync, T: Send>(
+    classes: &[(ReplayClassHash, P)],
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+    count_visited_pcs: impl Fn(&P) -> usize + Sync,
+    profile: impl Fn(&ReplayClassHash, &ContractClass, &Arc<SierraProfiler>, &P) -> T + Sync,
+) -> (Vec<T>, SkippedClasses) {
+    let results: Vec<Result<T, SkippedClass>> = classes
+        .par_iter()
+        .map(|(replay_class_hash, class_data)| {
+            let compiled_class = class_cache.get_or_compile(replay_class_hash.class_hash, || {
+                compile_class(replay_class_hash, storage)
+            });
+            match compiled_class {
+                CompiledClass::Sierra(contract_class, runner) => Ok(profile(
+                    replay_class_hash,
+                    &contract_class,
+                    &runner,
+                    class_data,
+                )),
+                CompiledClass::Skipped(reason, message) => Err(SkippedClass {
+                    class_hash: replay_class_hash.class_hash,
+                    block_number: replay_class_hash.block_number,
+                    reason,
+                    message,
+                    visited_pcs: count_visited_pcs(class_data),
+                }),
+            }
+        })
+        .collect();
+
+    let mut profiles = Vec::with_capacity(results.len());
+    let mut skipped_classes = SkippedClasses::new();
+    for result in results {
+        match result {
+            Ok(profile) => profiles.push(profile),
+            Err(skipped_class) => skipped_classes.add(&skipped_class),
+        }
+    }
+    (profiles, skipped_classes)
+}
+
+/// Returns the metadata configuration used to compile the Sierra program of a
+/// contract class into the CASM contract class executed by [`blockifier`].
+///
+/// It mirrors
+/// [`cairo_lang_starknet_classes::casm_contract_class::CasmContractClass::from_contract_class`]:
+/// the cost of each
+/// entry point function is set to [`ENTRY_POINT_COST`] and the linear solvers
+/// are used from Sierra version 1.4.0.
+///
+/// # Arguments
+///
+/// - `contract_class`: The contract class.
+/// - `sierra_program`: The Sierra program of the contract class.
 ///
 /// # Errors
 ///
-/// Returns [`Err`] if the constructor of `SierraCasmRunnerLight` fails.
-pub fn extract_libfuncs_weight(
-    visited_pcs: &VisitedPcs,
+/// Returns [`Err`] if the Sierra version can't be read from the header of the
+/// Sierra program.
+fn contract_metadata_config(
+    contract_class: &CairoContractClass,
+    sierra_program: &Program,
+) -> Result<MetadataComputationConfig, ProfilerError> {
+    let (sierra_version, _, _) = sierra_from_felt252s(&contract_class.sierra_program)
+        .map_err(|_| ProfilerError::Unknown("Error reading the Sierra version".to_string()))?;
+    let entry_points = &contract_class.entry_points_by_type;
+    let function_set_costs = entry_points
+        .constructor
+        .iter()
+        .chain(&entry_points.external)
+        .chain(&entry_points.l1_handler)
+        .filter_map(|entry_point| sierra_program.funcs.get(entry_point.function_idx))
+        .map(|function| {
+            (
+                function.id.clone(),
+                [(CostTokenType::Const, ENTRY_POINT_COST)].into(),
+            )
+        })
+        .collect();
+    let linear_solver = sierra_version.minor >= 4;
+    Ok(MetadataComputationConfig {
+        function_set_costs,
+        linear_gas_solver: linear_solver,
+        linear_ap_change_solver: linear_solver,
+        ..MetadataComputationConfig::default()
+    })
+}
+
+/// Queries the contract class of `replay_class_hash` and compiles its Sierra
+/// program.
+///
+/// The Sierra program is compiled with the same metadata configuration of the
+/// contract class executed, see [`contract_metadata_config`].
+///
+/// Returns [`CompiledClass::Skipped`] if the class can't be queried, if it's a
+/// legacy class, if its Sierra program can't be extracted or compiled or if
+/// the program compiled by the profiler doesn't match the bytecode compiled by
+/// the node, see [`check_executed_bytecode`]. Skipped classes are logged
+/// because their visited program counters can't be mapped to Sierra statements.
+/// Legacy classes are profiled by [`extract_legacy_function_steps`] instead.
+///
+/// # Arguments
+///
+/// - `replay_class_hash`: The class hash and block number used to query the
+///   contract class.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+fn compile_class(replay_class_hash: &ReplayClassHash, storage: &impl Storage) -> CompiledClass {
+    tracing::info!("Processing pcs from {replay_class_hash:?}.");
+    let skip = |reason: SkipReason, message: String| -> CompiledClass {
+        if reason == SkipReason::Legacy {
+            tracing::info!("Skipping {replay_class_hash:?}: {message}");
+        } else {
+            tracing::warn!("Skipping {replay_class_hash:?}: {message}");
+        }
+        CompiledClass::Skipped(reason, message)
+    };
+    let contract_class = match storage.get_contract_class_at_block(replay_class_hash) {
+        Ok(contract_class) => contract_class,
+        Err(err) => return skip(SkipReason::QueryFailed, err.to_string()),
+    };
+    let ContractClass::Sierra(sierra_class) = &contract_class else {
+        return skip(
+            SkipReason::Legacy,
+            "Not a Sierra contract, see the legacy function reports.".to_string(),
+        );
+    };
+    let compilation_input = get_cairo_contract_class(sierra_class).and_then(|cairo_class| {
+        let sierra_program = get_sierra_program(&cairo_class)?;
+        let metadata_config = contract_metadata_config(&cairo_class, &sierra_program)?;
+        Ok((sierra_program, metadata_config))
+    });
+    let (sierra_program, metadata_config) = match compilation_input {
+        Ok(compilation_input) => compilation_input,
+        Err(err) => return skip(SkipReason::InvalidSierraProgram, err.to_string()),
+    };
+    let runner = match SierraProfiler::with_metadata_config(sierra_program, None, metadata_config) {
+        Ok(runner) => runner,
+        Err(err) => return skip(SkipReason::CompilationFailed, err.to_string()),
+    };
+    if let Err(err) = check_executed_bytecode(replay_class_hash, &runner, storage) {
+        return skip(SkipReason::BytecodeMismatch, err.to_string());
+    }
+    CompiledClass::Sierra(Arc::new(contract_class), Arc::new(runner))
+}
+
+/// Checks that the program compiled by `runner` matches the CASM bytecode
+/// compiled by the node for the contract class of `replay_class_hash`.
+///
+/// The replay executes the bytecode compiled by the node when it's available,
+/// which is the bytecode executed on chain and may come from a different
+/// compiler version. If the node doesn't return the compiled class, the replay
+/// executes the class compiled locally with the same compiler and settings of
+/// `runner`, therefore the check is skipped.
+///
+/// # Arguments
+///
+/// - `replay_class_hash`: The class hash and block number of the contract
+///   class.
+/// - `runner`: The Sierra profiler of the contract.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the program compiled by `runner` doesn't match the
+/// bytecode compiled by the node.
+fn check_executed_bytecode(
+    replay_class_hash: &ReplayClassHash,
+    runner: &SierraProfiler,
     storage: &impl Storage,
-) -> Result<ReplayStatistics, ProfilerError> {
-    let mut local_cumulative_libfuncs_weight = ReplayStatistics::new();
+) -> Result<(), ProfilerError> {
+    match storage.get_compiled_casm_bytecode(&replay_class_hash.class_hash) {
+        Some(bytecode) => runner.check_bytecode(&bytecode),
+        None => {
+            tracing::debug!(
+                "Compiled CASM of {replay_class_hash:?} not available from the node. Skipping the \
+                 bytecode check."
+            );
+            Ok(())
+        }
+    }
+}
 
-    for (replay_class_hash, all_pcs) in visited_pcs {
-        tracing::info!("Processing pcs from {replay_class_hash:?}.");
-        let Ok(contract_class) = storage.get_contract_class_at_block(replay_class_hash) else {
+/// Extracts the frequency of libfuncs from visited program counters for each
+/// entry point of each contract class.
+///
+/// The first program counter of each entry point execution is matched with
+/// the entry points of the contract and the function names in its ABI. Then
+/// the profiler is run over the histogram of visited program counters of each
+/// entry point to determine which lines of the Sierra code have been executed.
+///
+/// Entry point executions which can't be matched with an entry point of the
+/// contract are reported as [`UNKNOWN_ENTRY_POINT`].
+///
+/// # Arguments
+///
+/// - `visited_pcs`: The object that contains the list of visited program
+///   counters for each transaction replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+pub fn extract_libfuncs_weight_by_entry_point(
+    visited_pcs: &VisitedPcs,
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+) -> EntryPointStatistics {
+    let (classes_statistics, skipped_classes) = profile_classes(
+        visited_pcs,
+        storage,
+        class_cache,
+        |replay_class_hash, contract_class, runner, all_pcs| {
+            let mut entry_point_statistics = EntryPointStatistics::new();
+            if let Some(version) = get_class_version_from_class_definition(contract_class) {
+                entry_point_statistics.set_class_version(replay_class_hash.class_hash, &version);
+            }
+            let entry_point_names = entry_point_names_by_pc(
+                runner,
+                get_entry_points_from_class_definition(contract_class),
+            );
+
+            for (entry_point_pc, pcs) in all_pcs {
+                let entry_point = entry_point_names
+                    .get(entry_point_pc)
+                    .map_or(UNKNOWN_ENTRY_POINT, String::as_str);
+                entry_point_statistics.add(
+                    replay_class_hash.class_hash,
+                    entry_point,
+                    &internal_extract_libfuncs_weight(runner, pcs),
+                );
+            }
+            entry_point_statistics
+        },
+    );
+
+    let mut cumulative_statistics = EntryPointStatistics::new();
+    for statistics in &classes_statistics {
+        cumulative_statistics.merge(statistics);
+    }
+    cumulative_statistics.skipped_classes = skipped_classes;
+    cumulative_statistics
+}
+
+/// Extracts the coverage of the Sierra statements of each contract class from
+/// visited program counters.
+///
+/// The classes are sorted by class hash. The contract classes which can't be
+/// compiled are returned as [`SkippedClasses`].
+///
+/// # Arguments
+///
+/// - `visited_pcs`: The object that contains the list of visited program
+///   counters for each transaction replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+pub fn extract_statement_coverage(
+    visited_pcs: &VisitedPcs,
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+) -> (Vec<ClassCoverage>, SkippedClasses) {
+    let (mut coverage, skipped_classes) = profile_classes(
+        visited_pcs,
+        storage,
+        class_cache,
+        |replay_class_hash, _, runner, all_pcs| {
+            ClassCoverage::new(replay_class_hash.class_hash, runner, &all_pcs.histogram())
+        },
+    );
+    coverage.sort_by_key(|class_coverage| class_coverage.class_hash);
+    (coverage, skipped_classes)
+}
+
+/// Extracts the frequency of CASM instruction kinds, hint types and
+/// memory-access patterns from visited program counters.
+///
+/// This is the sum over all contract classes. The contract classes which can't
+/// be compiled are returned as [`SkippedClasses`].
+///
+/// # Arguments
+///
+/// - `visited_pcs`: The object that contains the list of visited program
+///   counters for each transaction replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+pub fn extract_instruction_statistics(
+    visited_pcs: &VisitedPcs,
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+) -> (InstructionStatistics, SkippedClasses) {
+    let (classes_statistics, skipped_classes) = profile_classes(
+        visited_pcs,
+        storage,
+        class_cache,
+        |_, _, runner, all_pcs| {
+            let mut instruction_statistics = InstructionStatistics::new();
+            instruction_statistics.add_pcs(runner, &all_pcs.histogram());
+            instruction_statistics
+        },
+    );
+
+    let mut cumulative_statistics = InstructionStatistics::new();
+    for statistics in &classes_statistics {
+        cumulative_statistics.merge(statistics);
+    }
+    (cumulative_statistics, skipped_classes)
+}
+
+/// Compiles the contract classes of the calls in `call_traces` and collects the
+/// data required to name their frames.
+///
+/// The contract classes are compiled once and in parallel. The contract
+/// classes which can't be compiled are returned as [`SkippedClasses`], together
+/// with the number of program counters visited by their calls.
+///
+/// # Arguments
+///
+/// - `call_traces`: The call traces of the transactions replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+fn compile_call_trace_classes(
+    call_traces: &[CallTrace],
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+) -> (HashMap<ClassHash, ClassFrames>, SkippedClasses) {
+    let mut classes: HashMap<ClassHash, (ReplayClassHash, usize)> = HashMap::new();
+    for call_trace in call_traces.iter().flat_map(CallTrace::calls) {
+        let Some(replay_class_hash) = call_trace.class_hash else {
             continue;
         };
+        let (first_replay_class_hash, class_visited_pcs) = classes
+            .entry(replay_class_hash.class_hash)
+            .or_insert((replay_class_hash, 0));
+        if replay_class_hash.block_number < first_replay_class_hash.block_number {
+            *first_replay_class_hash = replay_class_hash;
+        }
+        *class_visited_pcs += call_trace.pcs.len();
+    }
+    let classes: Vec<(ReplayClassHash, usize)> = classes.into_values().collect();
+
+    let (class_frames, skipped_classes) = profile_grouped_classes(
+        &classes,
+        storage,
+        class_cache,
+        |class_visited_pcs| *class_visited_pcs,
+        |replay_class_hash, contract_class, runner, _| {
+            let entry_point_names = entry_point_names_by_pc(
+                runner,
+                get_entry_points_from_class_definition(contract_class),
+            );
+            (
+                replay_class_hash.class_hash,
+                ClassFrames::new(Arc::clone(runner), entry_point_names),
+            )
+        },
+    );
+    (class_frames.into_iter().collect(), skipped_classes)
+}
 
-        let Ok(sierra_program) = get_sierra_program_from_class_definition(&contract_class) else {
+/// Folds the call traces of the replayed transactions into the samples
+/// collected with each call stack.
+///
+/// The call stacks of inner calls are nested below the call stack of their
+/// caller. The contract classes which can't be compiled don't contribute
+/// samples and are returned as [`SkippedClasses`].
+///
+/// # Arguments
+///
+/// - `call_traces`: The call traces of the transactions replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+pub fn extract_folded_stacks(
+    call_traces: &[CallTrace],
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+) -> (FoldedStacks, SkippedClasses) {
+    let (classes, skipped_classes) = compile_call_trace_classes(call_traces, storage, class_cache);
+
+    let folded_stacks = call_traces
+        .par_iter()
+        .map(|call_trace| {
+            let mut folded_stacks = FoldedStacks::new();
+            folded_stacks.add_call_trace(call_trace, None, &classes);
+            folded_stacks
+        })
+        .reduce(FoldedStacks::new, |mut cumulative_stacks, folded_stacks| {
+            cumulative_stacks.merge(&folded_stacks);
+            cumulative_stacks
+        });
+    (folded_stacks, skipped_classes)
+}
+
+/// Folds the call traces of the replayed transactions into the samples
+/// collected with each call stack, separately for each contract class.
+///
+/// The call stacks of each class start at the entry point called. The steps of
+/// the calls to other contracts are reported in the class called. The contract
+/// classes which can't be compiled are returned as [`SkippedClasses`].
+///
+/// # Arguments
+///
+/// - `call_traces`: The call traces of the transactions replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+pub fn extract_folded_stacks_by_class(
+    call_traces: &[CallTrace],
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+) -> (BTreeMap<ClassHash, FoldedStacks>, SkippedClasses) {
+    let (classes, skipped_classes) = compile_call_trace_classes(call_traces, storage, class_cache);
+
+    let mut folded_stacks: BTreeMap<ClassHash, FoldedStacks> = BTreeMap::new();
+    for call_trace in call_traces.iter().flat_map(CallTrace::calls) {
+        let Some(replay_class_hash) = call_trace.class_hash else {
             continue;
         };
+        if classes.contains_key(&replay_class_hash.class_hash) {
+            folded_stacks
+                .entry(replay_class_hash.class_hash)
+                .or_default()
+                .add_call(call_trace, None, &classes);
+        }
+    }
+    (folded_stacks, skipped_classes)
+}
 
-        let runner = SierraProfiler::new(sierra_program, None)?;
+/// Extracts the frequency of libfuncs from the call traces of the replayed
+/// transactions for each contract address.
+///
+/// Each call is attributed to the address of the contract called and the class
+/// executed. The contract classes which can't be compiled are returned as
+/// [`SkippedClasses`].
+///
+/// # Arguments
+///
+/// - `call_traces`: The call traces of the transactions replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+pub fn extract_libfuncs_weight_by_address(
+    call_traces: &[CallTrace],
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+) -> (AddressStatistics, SkippedClasses) {
+    let mut classes: HashMap<ClassHash, (ReplayClassHash, HashMap<ContractAddress, PcHistogram>)> =
+        HashMap::new();
+    for call_trace in call_traces.iter().flat_map(CallTrace::calls) {
+        let Some(replay_class_hash) = call_trace.class_hash else {
+            continue;
+        };
+        let (first_replay_class_hash, addresses) = classes
+            .entry(replay_class_hash.class_hash)
+            .or_insert_with(|| (replay_class_hash, HashMap::new()));
+        if replay_class_hash.block_number < first_replay_class_hash.block_number {
+            *first_replay_class_hash = replay_class_hash;
+        }
+        addresses
+            .entry(call_trace.contract_address)
+            .or_default()
+            .extend(call_trace.pcs.iter().copied());
+    }
+    let classes: Vec<(ReplayClassHash, HashMap<ContractAddress, PcHistogram>)> =
+        classes.into_values().collect();
+
+    let (classes_statistics, skipped_classes) = profile_grouped_classes(
+        &classes,
+        storage,
+        class_cache,
+        |addresses| addresses.values().map(PcHistogram::total).sum(),
+        |replay_class_hash, _, runner, addresses| {
+            let mut address_statistics = AddressStatistics::new();
+            for (contract_address, pcs) in addresses {
+                address_statistics.add(
+                    *contract_address,
+                    replay_class_hash.class_hash,
+                    &internal_extract_libfuncs_weight(runner, pcs),
+                );
+            }
+            address_statistics
+        },
+    );
+
+    let mut cumulative_statistics = AddressStatistics::new();
+    for statistics in &classes_statistics {
+        cumulative_statistics.merge(statistics);
+    }
+    (cumulative_statistics, skipped_classes)
+}
 
-        let concrete_libfunc_weights = internal_extract_libfuncs_weight(&runner, all_pcs);
+/// Extracts the number of calls, CASM steps and hints executed in each function
+/// of each legacy Cairo 0 contract class from the call traces of the replayed
+/// transactions.
+///
+/// The calls are analysed with [`LegacyProfiler::collect_call_steps`]. Calls
+/// of Sierra classes are ignored. The legacy classes which can't be queried or
+/// whose program can't be decompressed are returned as [`SkippedClasses`].
+///
+/// # Arguments
+///
+/// - `call_traces`: The call traces of the transactions replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+pub fn extract_legacy_function_steps(
+    call_traces: &[CallTrace],
+    storage: &(impl Storage + Sync),
+) -> (LegacyStatistics, SkippedClasses) {
+    let mut classes: HashMap<ClassHash, (ReplayClassHash, Vec<&CallTrace>)> = HashMap::new();
+    for call_trace in call_traces.iter().flat_map(CallTrace::calls) {
+        let Some(replay_class_hash) = call_trace.class_hash else {
+            continue;
+        };
+        let (first_replay_class_hash, class_calls) = classes
+            .entry(replay_class_hash.class_hash)
+            .or_insert_with(|| (replay_class_hash, Vec::new()));
+        if replay_class_hash.block_number < first_replay_class_hash.block_number {
+            *first_replay_class_hash = replay_class_hash;
+        }
+        class_calls.push(call_trace);
+    }
+    let classes: Vec<(ReplayClassHash, Vec<&CallTrace>)> = classes.into_values().collect();
+
+    let results: Vec<Result<LegacyStatistics, SkippedClass>> = classes
+        .par_iter()
+        .filter_map(|(replay_class_hash, class_calls)| {
+            let skip = |reason: SkipReason, message: String| {
+                tracing::warn!("Skipping legacy {replay_class_hash:?}: {message}");
+                SkippedClass {
+                    class_hash: replay_class_hash.class_hash,
+                    block_number: replay_class_hash.block_number,
+                    reason,
+                    message,
+                    visited_pcs: class_calls.iter().map(|call| call.pcs.len()).sum(),
+                }
+            };
+            let contract_class = match storage.get_contract_class_at_block(replay_class_hash) {
+                Ok(contract_class) => contract_class,
+                Err(err) => return Some(Err(skip(SkipReason::QueryFailed, err.to_string()))),
+            };
+            let ContractClass::Legacy(contract_class) = contract_class else {
+                return None;
+            };
+            tracing::info!("Processing calls of legacy {replay_class_hash:?}.");
+            let profiler = match LegacyProfiler::new(&contract_class) {
+                Ok(profiler) => profiler,
+                Err(err) => {
+                    return Some(Err(skip(SkipReason::InvalidLegacyProgram, err.to_string())))
+                }
+            };
+            let mut legacy_statistics = LegacyStatistics::new();
+            for call_trace in class_calls {
+                for (function, steps) in profiler.collect_call_steps(call_trace) {
+                    legacy_statistics.add(replay_class_hash.class_hash, &function, &steps);
+                }
+            }
+            Some(Ok(legacy_statistics))
+        })
+        .collect();
+
+    let mut cumulative_statistics = LegacyStatistics::new();
+    let mut skipped_classes = SkippedClasses::new();
+    for result in results {
+        match result {
+            Ok(statistics) => cumulative_statistics.merge(&statistics),
+            Err(skipped_class) => skipped_classes.add(&skipped_class),
+        }
+    }
+    (cumulative_statistics, skipped_classes)
+}
 
-        local_cumulative_libfuncs_weight =
-            local_cumulative_libfuncs_weight.add_statistics(&concrete_libfunc_weights);
+/// Extracts the frequency of libfuncs from visited program counters for each
+/// contract class.
+///
+/// This is the sum over all entry points of each contract class of the result
+/// of [`extract_libfuncs_weight_by_entry_point`].
+///
+/// # Arguments
+///
+/// - `visited_pcs`: The object that contains the list of visited program
+///   counters for each transaction replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+pub fn extract_libfuncs_weight_by_contract(
+    visited_pcs: &VisitedPcs,
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+) -> ContractStatistics {
+    extract_libfuncs_weight_by_entry_point(visited_pcs, storage, class_cache).by_contract()
+}
+
+/// Extracts the frequency of libfuncs from visited program counters for each
+/// pair of Sierra version and compiler version of the contract classes.
+///
+/// This is the result of [`extract_libfuncs_weight_by_contract`] grouped by
+/// the versions of each contract class.
+///
+/// # Arguments
+///
+/// - `visited_pcs`: The object that contains the list of visited program
+///   counters for each transaction replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+pub fn extract_libfuncs_weight_by_version(
+    visited_pcs: &VisitedPcs,
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+) -> VersionStatistics {
+    extract_libfuncs_weight_by_contract(visited_pcs, storage, class_cache).by_version()
+}
+
+/// Returns the bucket of each block in `visited_pcs`.
+///
+/// The block header is queried only if the bucket depends on the block
+/// timestamp.
+///
+/// # Arguments
+///
+/// - `visited_pcs`: The object that contains the list of visited program
+///   counters for each transaction replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `bucket_size`: The size of the buckets.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the header of a block can't be queried.
+fn buckets_by_block(
+    visited_pcs: &VisitedPcs,
+    storage: &impl Storage,
+    bucket_size: BucketSize,
+) -> Result<HashMap<BlockNumber, u64>, ProfilerError> {
+    let mut buckets = HashMap::new();
+    for block_number in visited_pcs
+        .keys()
+        .map(|replay_class_hash| replay_class_hash.block_number)
+    {
+        if buckets.contains_key(&block_number) {
+            continue;
+        }
+        let timestamp = if bucket_size.needs_timestamp() {
+            storage
+                .get_block_header(block_number)
+                .map_err(|err| {
+                    ProfilerError::Unknown(format!(
+                        "Failed querying the header of block {block_number}: {err}"
+                    ))
+                })?
+                .timestamp
+                .0
+        } else {
+            0
+        };
+        buckets.insert(
+            block_number,
+            bucket_size.bucket(block_number.get(), timestamp),
+        );
+    }
+    Ok(buckets)
+}
+
+/// Extracts the frequency of libfuncs from visited program counters for each
+/// bucket of blocks.
+///
+/// Each contract class is compiled only once and the profiler is run over the
+/// histogram of visited program counters of each bucket. The contract classes
+/// which can't be compiled are returned as [`SkippedClasses`].
+///
+/// # Arguments
+///
+/// - `visited_pcs`: The object that contains the list of visited program
+///   counters for each transaction replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+/// - `bucket_size`: The size of the buckets.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the header of a block can't be queried.
+pub fn extract_libfuncs_weight_by_bucket(
+    visited_pcs: &VisitedPcs,
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+    bucket_size: BucketSize,
+) -> Result<(TimeSeriesStatistics, SkippedClasses), ProfilerError> {
+    let buckets = buckets_by_block(visited_pcs, storage, bucket_size)?;
+    let classes = group_by_class_hash_and_bucket(visited_pcs, &buckets);
+
+    let (classes_statistics, skipped_classes) = profile_grouped_classes(
+        &classes,
+        storage,
+        class_cache,
+        |class_buckets| {
+            class_buckets
+                .values()
+                .map(EntryPointHistograms::total)
+                .sum()
+        },
+        |_, _, runner, class_buckets| {
+            let mut time_series_statistics = TimeSeriesStatistics::new(bucket_size);
+            for (bucket, pcs) in class_buckets {
+                time_series_statistics.add(
+                    *bucket,
+                    &internal_extract_libfuncs_weight(runner, &pcs.histogram()),
+                );
+            }
+            time_series_statistics
+        },
+    );
+
+    let mut cumulative_statistics = TimeSeriesStatistics::new(bucket_size);
+    for statistics in &classes_statistics {
+        cumulative_statistics.merge(statistics);
     }
+    Ok((cumulative_statistics, skipped_classes))
+}
+
+/// Extracts the frequency of libfuncs from visited program counters.
+///
+/// This is the sum over all contract classes of the result of
+/// [`extract_libfuncs_weight_by_contract`]. The contract classes which can't be
+/// compiled are returned as [`SkippedClasses`].
+///
+/// # Arguments
+///
+/// - `visited_pcs`: The object that contains the list of visited program
+///   counters for each transaction replayed.
+/// - `storage`: The object to query the starknet blockchain using the RPC
+///   protocol.
+/// - `class_cache`: The contract classes already compiled.
+pub fn extract_libfuncs_weight(
+    visited_pcs: &VisitedPcs,
+    storage: &(impl Storage + Sync),
+    class_cache: &ClassCache,
+) -> (ReplayStatistics, SkippedClasses) {
+    let contract_statistics =
+        extract_libfuncs_weight_by_contract(visited_pcs, storage, class_cache);
+    let local_cumulative_libfuncs_weight = contract_statistics.total();
 
     for (concrete_name, weight) in local_cumulative_libfuncs_weight
         .concrete_libfunc
@@ -117,7 +1110,10 @@ pub fn extract_libfuncs_weight(
         tracing::info!("  libfunc {concrete_name}: {weight}");
     }
 
-    Ok(local_cumulative_libfuncs_weight)
+    (
+        local_cumulative_libfuncs_weight,
+        contract_statistics.skipped_classes,
+    )
 }
 
 #[cfg(test)]
@@ -137,6 +1133,7 @@ mod tests {
     use itertools::Itertools;
 
     use super::*;
+    use crate::block_number::BlockNumber;
 
     fn read_file_to_string(filename: &str) -> io::Result<String> {
         let out_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
@@ -364,6 +1361,109 @@ mod tests {
         visited_pcs
     }
 
+    #[test]
+    fn test_group_by_class_hash() {
+        let class_hash = ClassHash(Felt::from(1_u64));
+        let other_class_hash = ClassHash(Felt::from(2_u64));
+        let replay_class_hash = |block_number: u64, class_hash: ClassHash| ReplayClassHash {
+            block_number: BlockNumber::new(block_number),
+            class_hash,
+        };
+        let mut visited_pcs = VisitedPcs::new();
+        visited_pcs.insert(
+            replay_class_hash(11, class_hash),
+            [1, 2].into_iter().collect(),
+        );
+        visited_pcs.insert(
+            replay_class_hash(10, class_hash),
+            [2, 3].into_iter().collect(),
+        );
+        visited_pcs.insert(
+            replay_class_hash(12, other_class_hash),
+            [5].into_iter().collect(),
+        );
+
+        let mut classes = group_by_class_hash(&visited_pcs);
+        classes.sort_by_key(|(replay_class_hash, _)| replay_class_hash.class_hash);
+
+        assert_eq!(classes.len(), 2);
+        assert_eq!(classes[0].0, replay_class_hash(10, class_hash));
+        assert_eq!(classes[0].1.count(2), 2);
+        assert_eq!(classes[0].1.total(), 4);
+        assert_eq!(classes[1].0, replay_class_hash(12, other_class_hash));
+        assert_eq!(classes[1].1.total(), 1);
+    }
+
+    #[test]
+    fn test_group_by_class_hash_and_bucket() {
+        let class_hash = ClassHash(Felt::from(1_u64));
+        let replay_class_hash = |block_number: u64| ReplayClassHash {
+            block_number: BlockNumber::new(block_number),
+            class_hash,
+        };
+        let mut visited_pcs = VisitedPcs::new();
+        for (block_number, pcs) in [(9, vec![1, 2]), (11, vec![2, 3]), (12, vec![4])] {
+            visited_pcs.insert(replay_class_hash(block_number), pcs.into_iter().collect());
+        }
+        let bucket_size = BucketSize::Blocks(10);
+        let buckets = [9, 11, 12]
+            .into_iter()
+            .map(|block_number| {
+                (
+                    BlockNumber::new(block_number),
+                    bucket_size.bucket(block_number, 0),
+                )
+            })
+            .collect();
+
+        let classes = group_by_class_hash_and_bucket(&visited_pcs, &buckets);
+
+        assert_eq!(classes.len(), 1);
+        let (first_replay_class_hash, class_buckets) = &classes[0];
+        assert_eq!(*first_replay_class_hash, replay_class_hash(9));
+        assert_eq!(class_buckets.keys().copied().collect_vec(), vec![0, 10]);
+        assert_eq!(class_buckets[&0].total(), 2);
+        assert_eq!(class_buckets[&10].total(), 3);
+    }
+
+    #[test]
+    fn test_get_entry_points_from_class_definition() {
+        let filename = "/test_data/sierra_felt.json";
+        let mut contract_class_json: serde_json::Value =
+            serde_json::from_str(&read_file_to_string(filename).unwrap()).unwrap();
+        let abi = serde_json::json!([{
+            "type": "interface",
+            "name": "hello_starknet::HelloStarknetTrait",
+            "items": [{
+                "type": "function",
+                "name": "increase_balance",
+                "inputs": [],
+                "outputs": [],
+                "state_mutability": "external"
+            }]
+        }]);
+        contract_class_json["abi"] = abi.to_string().into();
+        let contract_class: ContractClass = serde_json::from_value(contract_class_json).unwrap();
+
+        let entry_points = get_entry_points_from_class_definition(&contract_class);
+        assert_eq!(
+            entry_points,
+            vec![
+                (0, "increase_balance".to_string()),
+                (
+                    1,
+                    "0x39e11d48192e4333233c7eb19d10ad67c362bb28580c604d67884c85da39695".to_string()
+                )
+            ]
+        );
+
+        let sierra_program = get_sierra_program_from_class_definition(&contract_class).unwrap();
+        let runner = SierraProfiler::new(sierra_program, None).unwrap();
+        let entry_point_names = entry_point_names_by_pc(&runner, entry_points);
+        assert_eq!(entry_point_names.len(), 2);
+        assert_eq!(entry_point_names[&0], "increase_balance");
+    }
+
     #[test]
     fn test_get_sierra_program_from_class_definition() {
         let sierra_program_json_file = "/test_data/sierra_felt.json";
@@ -375,6 +1475,17 @@ mod tests {
         assert_eq!(sierra_program_test, sierra_program);
     }
 
+    #[test]
+    fn test_get_class_version_from_class_definition() {
+        let filename = "/test_data/sierra_felt.json";
+        let contract_class_json = read_file_to_string(filename).unwrap();
+        let contract_class: ContractClass = serde_json::from_str(&contract_class_json).unwrap();
+
+        let version = get_class_version_from_class_definition(&contract_class).unwrap();
+        assert_eq!(version.sierra_version, "1.5.0");
+        assert_eq!(version.compiler_version, "2.6.0");
+    }
+
     #[test]
     fn test_extract_libfuncs_program() {
         // This is the original CAIRO code.
@@ -394,8 +1505,9 @@ mod tests {
         // The offset of the program is 4 because the header contains 3 instructions.
         let sierra_profiler = SierraProfiler::new(sierra_program.clone(), Some(4)).unwrap();
 
-        let concrete_libfunc_weights =
-            internal_extract_libfuncs_weight(&sierra_profiler, &visited_pcs);
+        let libfunc_weights =
+            internal_extract_libfuncs_weight(&sierra_profiler, &visited_pcs.into_iter().collect());
+        let concrete_libfunc_weights = libfunc_weights.concrete_libfunc;
 
         assert_eq!(concrete_libfunc_weights.len(), 4);
         assert_libfunc_frequency(&concrete_libfunc_weights, "store_temp<felt252>", 2);
@@ -411,6 +1523,22 @@ mod tests {
         );
         assert_libfunc_frequency(&concrete_libfunc_weights, "felt252_add", 1);
 
+        let generic_libfunc_weights = libfunc_weights.generic_libfunc;
+        assert_eq!(generic_libfunc_weights.len(), 3);
+        assert_libfunc_frequency(&generic_libfunc_weights, "store_temp", 2);
+        assert_libfunc_frequency(&generic_libfunc_weights, "const_as_immediate", 2);
+        assert_libfunc_frequency(&generic_libfunc_weights, "felt252_add", 1);
+
+        // `felt252_add` is executed by the CASM instruction of the following
+        // `store_temp<felt252>`, therefore its cost is zero steps and zero gas.
+        let store_temp_cost = libfunc_weights.concrete_libfunc_cost["store_temp<felt252>"];
+        assert_eq!(store_temp_cost.steps, 2);
+        assert_eq!(store_temp_cost.gas, 200);
+        let felt252_add_cost = libfunc_weights.concrete_libfunc_cost["felt252_add"];
+        assert_eq!(felt252_add_cost.steps, 0);
+        assert_eq!(felt252_add_cost.gas, 0);
+        assert_eq!(libfunc_weights.generic_libfunc_cost["store_temp"].steps, 2);
+
         let libfuncs = sierra_profiler.get_libfuncs_at_pc(4);
         assert_eq!(libfuncs.len(), 3);
         assert!(libfuncs.contains(&"const_as_immediate<Const<felt252, 2>>".to_string()));
@@ -423,6 +1551,111 @@ mod tests {
         assert!(libfuncs.contains(&"store_temp<felt252>".to_string()));
     }
 
+    #[test]
+    fn test_class_coverage() {
+        let visited_pcs: PcHistogram = [1, 4, 6, 8, 3].into_iter().collect();
+
+        let cairo_file = "/test_data/sierra_add_program.cairo";
+        let sierra_program = compile_cairo_program(cairo_file, true);
+        let sierra_profiler = SierraProfiler::new(sierra_program.clone(), Some(4)).unwrap();
+
+        let coverage =
+            ClassCoverage::new(ClassHash(Felt::from(1_u64)), &sierra_profiler, &visited_pcs);
+
+        assert_eq!(
+            coverage.statement_hits.len(),
+            sierra_program.statements.len()
+        );
+        assert_eq!(coverage.statements.len(), sierra_program.statements.len());
+        assert_eq!(coverage.functions.len(), sierra_program.funcs.len());
+        assert_eq!(
+            coverage
+                .functions
+                .iter()
+                .map(|function| function.statements)
+                .sum::<usize>(),
+            sierra_program.statements.len()
+        );
+        assert_eq!(coverage.functions[0].entry_point, 0);
+        assert_eq!(coverage.functions[0].hits, 1);
+        assert!(coverage.statements_hit() > 0);
+    }
+
+    #[test]
+    fn test_extract_folded_stacks_program() {
+        let cairo_file = "/test_data/sierra_add_program.cairo";
+        let sierra_program = compile_cairo_program(cairo_file, true);
+        let main = sierra_program.funcs[0].id.to_string();
+        let sierra_profiler = SierraProfiler::new(sierra_program, Some(4)).unwrap();
+
+        let class_hash = ClassHash(Felt::from(1_u64));
+        let entry_point_names = HashMap::from([(4, "main".to_string())]);
+        let classes = HashMap::from([(
+            class_hash,
+            ClassFrames::new(Arc::new(sierra_profiler), entry_point_names),
+        )]);
+        let call_trace = CallTrace {
+            class_hash: Some(ReplayClassHash {
+                block_number: BlockNumber::new(632_917),
+                class_hash,
+            }),
+            pcs: vec![4, 6, 8],
+            ..CallTrace::default()
+        };
+
+        let mut folded_stacks = FoldedStacks::new();
+        folded_stacks.add_call_trace(&call_trace, None, &classes);
+
+        // The number of libfunc calls matches `internal_extract_libfuncs_weight`.
+        let total = folded_stacks.total();
+        assert_eq!(total.steps, 3);
+        assert_eq!(total.libfunc_calls, 5);
+        let store_temp = &folded_stacks.stacks[&format!("0x1::main;{main};store_temp<felt252>")];
+        assert_eq!(store_temp.steps, 2);
+        assert_eq!(store_temp.libfunc_calls, 2);
+        let felt252_add = &folded_stacks.stacks[&format!("0x1::main;{main};felt252_add")];
+        assert_eq!(felt252_add.steps, 0);
+        assert_eq!(felt252_add.libfunc_calls, 1);
+        assert_eq!(
+            folded_stacks.stacks[&format!("0x1::main;{main};return")].steps,
+            1
+        );
+    }
+
+    #[test]
+    fn test_extract_instruction_statistics_program() {
+        let visited_pcs: PcHistogram = [1, 4, 6, 8, 3].into_iter().collect();
+
+        let cairo_file = "/test_data/sierra_add_program.cairo";
+        let sierra_program = compile_cairo_program(cairo_file, true);
+        let sierra_profiler = SierraProfiler::new(sierra_program, Some(4)).unwrap();
+
+        let mut instruction_statistics = InstructionStatistics::new();
+        instruction_statistics.add_pcs(&sierra_profiler, &visited_pcs);
+
+        // PCs 1 and 3 belong to the header of the program.
+        assert_eq!(instruction_statistics.steps(), 3);
+        assert_eq!(instruction_statistics.instructions["assert_eq"], 2);
+        assert_eq!(instruction_statistics.instructions["ret"], 1);
+        assert!(instruction_statistics.hints.is_empty());
+        assert_eq!(
+            instruction_statistics
+                .memory_accesses
+                .values()
+                .sum::<usize>(),
+            3
+        );
+        assert_eq!(
+            instruction_statistics
+                .memory_accesses
+                .iter()
+                .filter(|(pattern, _)| pattern.starts_with("dst=[ap]"))
+                .map(|(_, frequency)| frequency)
+                .sum::<usize>(),
+            2
+        );
+    }
+
     #[test]
     fn test_extract_libfuncs_secp_program() {
         let visited_pcs: Vec<usize> = vec![
@@ -436,7 +1669,8 @@ mod tests {
         let sierra_profiler = SierraProfiler::new(sierra_program.clone(), Some(8)).unwrap();
 
         let concrete_libfunc_weights =
-            internal_extract_libfuncs_weight(&sierra_profiler, &visited_pcs);
+            internal_extract_libfuncs_weight(&sierra_profiler, &visited_pcs.into_iter().collect())
+                .concrete_libfunc;
 
         assert_eq!(concrete_libfunc_weights.len(), 16);
         assert_libfunc_frequency(&concrete_libfunc_weights, "secp256r1_new_syscall", 1);
@@ -452,6 +1686,41 @@ mod tests {
         assert!(libfuncs.contains(&"secp256r1_mul_syscall".to_string()));
     }
 
+    #[test]
+    fn test_check_bytecode_contract() {
+        let cairo_file = "/test_data/sierra_add_contract.cairo";
+        let contract_class = compile_cairo_contract(cairo_file, true);
+        let sierra_program = get_sierra_program(&contract_class).unwrap();
+        let metadata_config = contract_metadata_config(&contract_class, &sierra_program).unwrap();
+        let mut casm_class =
+            CasmContractClass::from_contract_class(contract_class, false, usize::MAX).unwrap();
+
+        let sierra_profiler =
+            SierraProfiler::with_metadata_config(sierra_program, None, metadata_config).unwrap();
+        assert!(sierra_profiler.check_bytecode(&casm_class.bytecode).is_ok());
+
+        let bytecode_length = casm_class.bytecode.len();
+        let mut longer_bytecode = casm_class.bytecode.clone();
+        longer_bytecode.push(casm_class.bytecode[0].clone());
+        assert!(matches!(
+            sierra_profiler.check_bytecode(&longer_bytecode),
+            Err(ProfilerError::BytecodeMismatch(pc)) if pc == bytecode_length
+        ));
+
+        let mut changed_bytecode = casm_class.bytecode.clone();
+        changed_bytecode[bytecode_length - 1].value += 1_u32;
+        assert!(matches!(
+            sierra_profiler.check_bytecode(&changed_bytecode),
+            Err(ProfilerError::BytecodeMismatch(pc)) if pc == bytecode_length - 1
+        ));
+
+        casm_class.bytecode.clear();
+        assert!(matches!(
+            sierra_profiler.check_bytecode(&casm_class.bytecode),
+            Err(ProfilerError::BytecodeMismatch(0))
+        ));
+    }
+
     #[test]
     fn test_extract_libfuncs_contract() {
         // This is the original CAIRO code.
@@ -488,7 +1757,8 @@ mod tests {
         let sierra_profiler = SierraProfiler::new(sierra_program.clone(), Some(1)).unwrap();
 
         let concrete_libfunc_weights =
-            internal_extract_libfuncs_weight(&sierra_profiler, &visited_pcs);
+            internal_extract_libfuncs_weight(&sierra_profiler, &visited_pcs.into_iter().collect())
+                .concrete_libfunc;
 
         assert_eq!(concrete_libfunc_weights.len(), 30);
         assert_libfunc_frequency(
@@ -574,7 +1844,8 @@ mod tests {
         let sierra_profiler = SierraProfiler::new(sierra_program.clone(), Some(1)).unwrap();
 
         let concrete_libfunc_weights =
-            internal_extract_libfuncs_weight(&sierra_profiler, &visited_pcs);
+            internal_extract_libfuncs_weight(&sierra_profiler, &visited_pcs.into_iter().collect())
+                .concrete_libfunc;
 
         assert_eq!(concrete_libfunc_weights.len(), 45);
         assert_libfunc_frequency(
@@ -619,7 +1890,8 @@ mod tests {
         let sierra_profiler = SierraProfiler::new(sierra_program.clone(), None).unwrap();
 
         let concrete_libfunc_weights =
-            internal_extract_libfuncs_weight(&sierra_profiler, &visited_pcs);
+            internal_extract_libfuncs_weight(&sierra_profiler, &visited_pcs.into_iter().collect())
+                .concrete_libfunc;
 
         assert_eq!(concrete_libfunc_weights.len(), 30);
         assert_libfunc_frequency(&concrete_libfunc_weights, "secp256r1_new_syscall", 1);
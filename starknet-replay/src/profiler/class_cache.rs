@@ -0,0 +1,123 @@
+//! The module which keeps the contract classes compiled by the profiler.
+//!
+//! Compiling the Sierra program of a contract class is the most expensive step
+//! of each analysis. The compiled classes are kept in a [`ClassCache`] shared
+//! by all the analyses of a run so that each class is compiled at most once.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `ClassCache`
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use starknet_api::core::ClassHash;
+use starknet_core::types::ContractClass;
+
+use crate::profiler::skipped_classes::SkipReason;
+use crate::profiler::SierraProfiler;
+
+/// The outcome of the compilation of a contract class.
+#[derive(Clone)]
+pub(crate) enum CompiledClass {
+    /// The contract class and the profiler of its Sierra program.
+    Sierra(Arc<ContractClass>, Arc<SierraProfiler>),
+
+    /// The contract class can't be profiled for the reason given, together
+    /// with the description of the error.
+    Skipped(SkipReason, String),
+}
+
+/// The contract classes compiled by the profiler, keyed by class hash.
+///
+/// The definition of a contract class doesn't depend on the block, therefore
+/// each class is compiled only the first time it's requested.
+#[derive(Default)]
+pub struct ClassCache {
+    /// The outcome of the compilation of each contract class.
+    classes: RwLock<HashMap<ClassHash, CompiledClass>>,
+}
+impl ClassCache {
+    /// Initialisation of an empty [`ClassCache`].
+    #[must_use]
+    pub fn new() -> Self {
+        ClassCache::default()
+    }
+
+    /// Returns the number of contract classes compiled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [`std::sync::RwLock`] is poisoned. It's best to panic
+    /// instead of handling the error because it implies data is corrupted.
+    #[allow(clippy::unwrap_used)]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.classes.read().unwrap().len()
+    }
+
+    /// Returns `true` if no contract class has been compiled.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the compiled contract class `class_hash`, calling `compile`
+    /// only if the class hasn't been compiled yet.
+    ///
+    /// # Arguments
+    ///
+    /// - `class_hash`: The class hash of the contract class.
+    /// - `compile`: The function which compiles the contract class.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [`std::sync::RwLock`] is poisoned. It's best to panic
+    /// instead of handling the error because it implies data is corrupted.
+    #[allow(clippy::unwrap_used)]
+    pub(crate) fn get_or_compile(
+        &self,
+        class_hash: ClassHash,
+        compile: impl FnOnce() -> CompiledClass,
+    ) -> CompiledClass {
+        if let Some(compiled_class) = self.classes.read().unwrap().get(&class_hash) {
+            return compiled_class.clone();
+        }
+        // The lock isn't held during the compilation to compile different
+        // classes in parallel.
+        let compiled_class = compile();
+        self.classes
+            .write()
+            .unwrap()
+            .entry(class_hash)
+            .or_insert(compiled_class)
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use starknet_api::felt;
+
+    use super::*;
+
+    #[test]
+    fn test_class_cache() {
+        let class_cache = ClassCache::new();
+        let compilations = AtomicUsize::new(0);
+        let compile = || {
+            compilations.fetch_add(1, Ordering::Relaxed);
+            CompiledClass::Skipped(SkipReason::Legacy, "Not a Sierra contract.".to_string())
+        };
+
+        for _ in 0..2 {
+            let compiled_class = class_cache.get_or_compile(ClassHash(felt!("0x1")), compile);
+            assert!(matches!(
+                compiled_class,
+                CompiledClass::Skipped(SkipReason::Legacy, _)
+            ));
+        }
+        assert_eq!(compilations.load(Ordering::Relaxed), 1);
+        assert_eq!(class_cache.len(), 1);
+    }
+}
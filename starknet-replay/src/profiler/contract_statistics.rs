@@ -0,0 +1,654 @@
+//! The module which provides the breakdown of libfunc usage statistics by
+//! contract class and by contract address.
+//!
+//! Visited program counters are recorded by [`blockifier`] for each class hash,
+//! therefore [`ContractStatistics`] reports contracts deployed with the same
+//! class together. [`AddressStatistics`] splits them by contract address using
+//! the call traces of the transactions.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `ContractStatistics`
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use itertools::Itertools;
+use serde::Serialize;
+use starknet_api::core::{ClassHash, ContractAddress};
+
+use crate::error::ProfilerError;
+use crate::profiler::replay_statistics::{
+    share,
+    LibfuncGranularity,
+    LibfuncWeight,
+    ReplayStatistics,
+};
+use crate::profiler::skipped_classes::SkippedClasses;
+use crate::profiler::version_statistics::{ClassVersion, VersionStatistics};
+
+/// The struct to hold the libfunc usage statistics of each contract class.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct ContractStatistics {
+    /// This field contains the association between class hash (key) and the
+    /// libfunc usage statistics of the class (value).
+    pub classes: BTreeMap<ClassHash, ReplayStatistics>,
+
+    /// This field contains the association between class hash (key) and the
+    /// Sierra version and compiler version of the class (value). Classes
+    /// whose versions can't be read are not included.
+    pub class_versions: BTreeMap<ClassHash, ClassVersion>,
+
+    /// This field contains the contract classes skipped by the analysis,
+    /// which are missing from the statistics.
+    pub skipped_classes: SkippedClasses,
+}
+
+/// The struct to hold the libfunc usage statistics of each contract address.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct AddressStatistics {
+    /// This field contains the association between the contract address and
+    /// the class hash executed (key) and the libfunc usage statistics of the
+    /// contract (value). A contract is reported once for each class it
+    /// executed, e.g. before and after a class upgrade.
+    pub contracts: BTreeMap<(ContractAddress, ClassHash), ReplayStatistics>,
+}
+
+/// The key of a contract class in the JSON report.
+#[derive(Debug, Serialize)]
+struct ClassKey {
+    /// The class hash of the contract.
+    class_hash: ClassHash,
+}
+
+/// The key of a contract address in the JSON report.
+#[derive(Debug, Serialize)]
+struct AddressKey {
+    /// The address of the contract.
+    contract_address: ContractAddress,
+
+    /// The class hash executed by the contract.
+    class_hash: ClassHash,
+}
+
+/// The libfunc usage of a contract in the JSON report.
+#[derive(Debug, Serialize)]
+struct ContractReport<'a, K> {
+    /// The key of the contract.
+    #[serde(flatten)]
+    key: K,
+
+    /// The number of libfunc calls of the contract.
+    total_calls: usize,
+
+    /// The ratio between the libfunc calls of the contract and the libfunc
+    /// calls of all contracts.
+    share_of_total_calls: f64,
+
+    /// The usage of each libfunc called by the contract.
+    libfuncs: BTreeMap<&'a str, LibfuncReport>,
+}
+
+/// The usage of a libfunc by a contract class in the JSON report.
+#[derive(Debug, Serialize)]
+struct LibfuncReport {
+    /// The number of calls of the libfunc by the contract.
+    weight: usize,
+
+    /// The ratio between the calls of the libfunc by the contract and the
+    /// calls of the libfunc by all contracts.
+    share_of_libfunc_calls: f64,
+}
+
+/// Returns the libfunc usage statistics of all contracts combined.
+///
+/// # Arguments
+///
+/// - `contracts`: The libfunc usage statistics of each contract.
+fn total_statistics<K>(contracts: &BTreeMap<K, ReplayStatistics>) -> ReplayStatistics {
+    let mut total = ReplayStatistics::new();
+    for statistics in contracts.values() {
+        total.merge(statistics);
+    }
+    total
+}
+
+/// Applies `select` to the libfunc usage statistics of each contract.
+///
+/// # Arguments
+///
+/// - `contracts`: The libfunc usage statistics of each contract.
+/// - `select`: The function which returns the new statistics of a contract.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `select` fails for any contract.
+fn map_statistics<K: Ord + Copy>(
+    contracts: &BTreeMap<K, ReplayStatistics>,
+    select: impl Fn(&ReplayStatistics) -> Result<ReplayStatistics, ProfilerError>,
+) -> Result<BTreeMap<K, ReplayStatistics>, ProfilerError> {
+    contracts
+        .iter()
+        .map(|(key, statistics)| Ok((*key, select(statistics)?)))
+        .collect()
+}
+
+/// Returns the contracts sorted by descending number of libfunc calls
+/// together with their number of calls.
+///
+/// # Arguments
+///
+/// - `contracts`: The libfunc usage statistics of each contract.
+fn contracts_by_calls<K: Ord>(
+    contracts: &BTreeMap<K, ReplayStatistics>,
+) -> Vec<(&K, &ReplayStatistics, usize)> {
+    contracts
+        .iter()
+        .map(|(key, statistics)| {
+            let calls = statistics.concrete_libfunc.values().sum();
+            (key, statistics, calls)
+        })
+        .sorted_by(|a, b| Ord::cmp(&b.2, &a.2).then(Ord::cmp(a.0, b.0)))
+        .collect()
+}
+
+/// Serialises the libfunc usage statistics of each contract to CSV format.
+///
+/// Contracts are reported in descending order of libfunc calls. For each
+/// contract, libfuncs are reported in descending order of weight.
+///
+/// # Arguments
+///
+/// - `contracts`: The libfunc usage statistics of each contract.
+/// - `key_header`: The header of the columns identifying the contract.
+/// - `key_columns`: The function which returns the columns identifying a
+///   contract.
+///
+/// # Errors
+///
+/// Returns [`Err`] if there is an IO error writing to the buffer.
+fn contracts_to_csv_bytes<K: Ord>(
+    contracts: &BTreeMap<K, ReplayStatistics>,
+    key_header: &str,
+    key_columns: impl Fn(&K) -> String,
+) -> Result<Vec<u8>, std::io::Error> {
+    let total = total_statistics(contracts);
+    let total_calls: usize = total.concrete_libfunc.values().sum();
+
+    let mut f = Vec::new();
+    writeln!(
+        f,
+        "{key_header},Contract Share,Function Name,Weight,Libfunc Share"
+    )?;
+    for (key, statistics, calls) in contracts_by_calls(contracts) {
+        let key_columns = key_columns(key);
+        let contract_share = share(calls, total_calls);
+        for (concrete_name, weight) in statistics
+            .concrete_libfunc
+            .iter()
+            .sorted_by(|a, b| Ord::cmp(&b.1, &a.1).then(Ord::cmp(a.0, b.0)))
+        {
+            let libfunc_share = share(*weight, total.get_libfunc_frequency(concrete_name));
+            writeln!(
+                f,
+                "{key_columns},{contract_share:.6},\"{concrete_name}\",{weight},{libfunc_share:.6}"
+            )?;
+        }
+    }
+    Ok(f)
+}
+
+/// Serialises the libfunc usage statistics of each contract to JSON format.
+///
+/// Contracts are reported in descending order of libfunc calls.
+///
+/// # Arguments
+///
+/// - `contracts`: The libfunc usage statistics of each contract.
+/// - `report_key`: The function which returns the fields identifying a contract
+///   in the report.
+///
+/// # Errors
+///
+/// Returns [`Err`] if there is an error serialising the object.
+fn contracts_to_json<K: Ord, R: Serialize>(
+    contracts: &BTreeMap<K, ReplayStatistics>,
+    report_key: impl Fn(&K) -> R,
+) -> Result<String, serde_json::Error> {
+    let total = total_statistics(contracts);
+    let total_calls: usize = total.concrete_libfunc.values().sum();
+
+    let report: Vec<ContractReport<R>> = contracts_by_calls(contracts)
+        .into_iter()
+        .map(|(key, statistics, calls)| {
+            let libfuncs = statistics
+                .concrete_libfunc
+                .iter()
+                .map(|(concrete_name, weight)| {
+                    let libfunc_report = LibfuncReport {
+                        weight: *weight,
+                        share_of_libfunc_calls: share(
+                            *weight,
+                            total.get_libfunc_frequency(concrete_name),
+                        ),
+                    };
+                    (concrete_name.as_str(), libfunc_report)
+                })
+                .collect();
+            ContractReport {
+                key: report_key(key),
+                total_calls: calls,
+                share_of_total_calls: share(calls, total_calls),
+                libfuncs,
+            }
+        })
+        .collect();
+    serde_json::to_string_pretty(&report)
+}
+
+impl ContractStatistics {
+    /// Initialisation of [`ContractStatistics`].
+    ///
+    /// The struct is initialised with all fields empty.
+    #[must_use]
+    pub fn new() -> Self {
+        ContractStatistics {
+            classes: BTreeMap::default(),
+            class_versions: BTreeMap::default(),
+            skipped_classes: SkippedClasses::new(),
+        }
+    }
+
+    /// Adds the libfunc usage statistics of a contract class.
+    ///
+    /// If `class_hash` already exists, the statistics are merged.
+    ///
+    /// # Arguments
+    ///
+    /// - `class_hash`: The class hash of the contract.
+    /// - `statistics`: The libfunc usage statistics of the contract.
+    pub fn add(&mut self, class_hash: ClassHash, statistics: &ReplayStatistics) {
+        self.classes
+            .entry(class_hash)
+            .or_default()
+            .merge(statistics);
+    }
+
+    /// Sets the Sierra version and compiler version of a contract class.
+    ///
+    /// # Arguments
+    ///
+    /// - `class_hash`: The class hash of the contract.
+    /// - `version`: The versions of the contract class.
+    pub fn set_class_version(&mut self, class_hash: ClassHash, version: &ClassVersion) {
+        self.class_versions.insert(class_hash, version.clone());
+    }
+
+    /// Update the object with data in `from`.
+    ///
+    /// # Arguments
+    ///
+    /// - `from`: Input `ContractStatistics` to get data from.
+    pub fn merge(&mut self, from: &ContractStatistics) {
+        for (class_hash, statistics) in &from.classes {
+            self.add(*class_hash, statistics);
+        }
+        for (class_hash, version) in &from.class_versions {
+            self.set_class_version(*class_hash, version);
+        }
+        self.skipped_classes.merge(&from.skipped_classes);
+    }
+
+    /// Returns the libfunc usage statistics of all contract classes combined.
+    #[must_use]
+    pub fn total(&self) -> ReplayStatistics {
+        total_statistics(&self.classes)
+    }
+
+    /// Returns the libfunc usage statistics of each pair of Sierra version and
+    /// compiler version.
+    ///
+    /// Classes whose versions are unknown are reported with empty versions.
+    #[must_use]
+    pub fn by_version(&self) -> VersionStatistics {
+        let mut version_statistics = VersionStatistics::new();
+        for (class_hash, statistics) in &self.classes {
+            let version = self
+                .class_versions
+                .get(class_hash)
+                .cloned()
+                .unwrap_or_default();
+            version_statistics.add_class(&version, statistics);
+        }
+        version_statistics
+    }
+
+    /// Returns the statistics of each contract class with the libfunc names of
+    /// the chosen granularity.
+    ///
+    /// # Arguments
+    ///
+    /// - `granularity`: The granularity of the libfunc names.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the granularity can't be selected for the statistics
+    /// of each contract class, see [`ReplayStatistics::with_granularity`].
+    pub fn with_granularity(
+        &self,
+        granularity: LibfuncGranularity,
+    ) -> Result<ContractStatistics, ProfilerError> {
+        Ok(ContractStatistics {
+            classes: map_statistics(&self.classes, |statistics| {
+                statistics.with_granularity(granularity)
+            })?,
+            class_versions: self.class_versions.clone(),
+            skipped_classes: self.skipped_classes.clone(),
+        })
+    }
+
+    /// Returns the statistics of each contract class with the libfuncs weighted
+    /// by the chosen weight.
+    ///
+    /// # Arguments
+    ///
+    /// - `weight`: The weight of the libfuncs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the weight can't be selected for the statistics of
+    /// each contract class, see [`ReplayStatistics::with_weight`].
+    pub fn with_weight(&self, weight: LibfuncWeight) -> Result<ContractStatistics, ProfilerError> {
+        Ok(ContractStatistics {
+            classes: map_statistics(&self.classes, |statistics| statistics.with_weight(weight))?,
+            class_versions: self.class_versions.clone(),
+            skipped_classes: self.skipped_classes.clone(),
+        })
+    }
+
+    /// Serialises [`ContractStatistics`] to CSV format.
+    ///
+    /// Contracts are reported in descending order of libfunc calls. For each
+    /// contract, libfuncs are reported in descending order of weight.
+    ///
+    /// - `Contract Share` is the ratio between the libfunc calls of the
+    ///   contract and the libfunc calls of all contracts.
+    /// - `Libfunc Share` is the ratio between the calls of the libfunc by the
+    ///   contract and the calls of the libfunc by all contracts.
+    ///
+    /// The function name is quoted because it may contain commas.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str;
+    /// # use indoc::indoc;
+    /// # use starknet_api::core::{ClassHash, ContractAddress};
+    /// # use starknet_core::types::Felt;
+    /// # use starknet_replay::profiler::contract_statistics::ContractStatistics;
+    /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+    /// let mut first = ReplayStatistics::default();
+    /// first.update(&"u256_safe_divmod".to_string(), 3);
+    /// first.update(&"felt252_add".to_string(), 5);
+    /// let mut second = ReplayStatistics::default();
+    /// second.update(&"u256_safe_divmod".to_string(), 1);
+    /// let mut contract_statistics = ContractStatistics::new();
+    /// contract_statistics.add(ClassHash(Felt::from(1_u64)), &first);
+    /// contract_statistics.add(ClassHash(Felt::from(2_u64)), &second);
+    /// let expected_string = indoc! {r#"
+    ///     Class Hash,Contract Share,Function Name,Weight,Libfunc Share
+    ///     0x1,0.888889,"felt252_add",5,1.000000
+    ///     0x1,0.888889,"u256_safe_divmod",3,0.750000
+    ///     0x2,0.111111,"u256_safe_divmod",1,0.250000
+    /// "#};
+    /// let csv_output = contract_statistics.to_csv_bytes().unwrap();
+    /// assert_eq!(
+    ///     str::from_utf8(csv_output.as_slice()).unwrap(),
+    ///     expected_string
+    /// );
+    /// ```
+    pub fn to_csv_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        contracts_to_csv_bytes(&self.classes, "Class Hash", |class_hash| {
+            format!("{:#x}", class_hash.0)
+        })
+    }
+
+    /// Serialises [`ContractStatistics`] to JSON format.
+    ///
+    /// Contracts are reported in descending order of libfunc calls.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an error serialising the object.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        contracts_to_json(&self.classes, |class_hash| ClassKey {
+            class_hash: *class_hash,
+        })
+    }
+}
+
+impl AddressStatistics {
+    /// Initialisation of [`AddressStatistics`].
+    ///
+    /// The struct is initialised with all fields empty.
+    #[must_use]
+    pub fn new() -> Self {
+        AddressStatistics {
+            contracts: BTreeMap::default(),
+        }
+    }
+
+    /// Adds the libfunc usage statistics of a contract address.
+    ///
+    /// If the pair of `contract_address` and `class_hash` already exists, the
+    /// statistics are merged.
+    ///
+    /// # Arguments
+    ///
+    /// - `contract_address`: The address of the contract.
+    /// - `class_hash`: The class hash executed by the contract.
+    /// - `statistics`: The libfunc usage statistics of the contract.
+    pub fn add(
+        &mut self,
+        contract_address: ContractAddress,
+        class_hash: ClassHash,
+        statistics: &ReplayStatistics,
+    ) {
+        self.contracts
+            .entry((contract_address, class_hash))
+            .or_default()
+            .merge(statistics);
+    }
+
+    /// Update the object with data in `from`.
+    ///
+    /// # Arguments
+    ///
+    /// - `from`: Input `AddressStatistics` to get data from.
+    pub fn merge(&mut self, from: &AddressStatistics) {
+        for ((contract_address, class_hash), statistics) in &from.contracts {
+            self.add(*contract_address, *class_hash, statistics);
+        }
+    }
+
+    /// Returns the libfunc usage statistics of all contract addresses
+    /// combined.
+    #[must_use]
+    pub fn total(&self) -> ReplayStatistics {
+        total_statistics(&self.contracts)
+    }
+
+    /// Returns the statistics of each contract address with the libfunc names
+    /// of the chosen granularity.
+    ///
+    /// # Arguments
+    ///
+    /// - `granularity`: The granularity of the libfunc names.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the granularity can't be selected for the statistics
+    /// of each contract address, see [`ReplayStatistics::with_granularity`].
+    pub fn with_granularity(
+        &self,
+        granularity: LibfuncGranularity,
+    ) -> Result<AddressStatistics, ProfilerError> {
+        Ok(AddressStatistics {
+            contracts: map_statistics(&self.contracts, |statistics| {
+                statistics.with_granularity(granularity)
+            })?,
+        })
+    }
+
+    /// Returns the statistics of each contract address with the libfuncs
+    /// weighted by the chosen weight.
+    ///
+    /// # Arguments
+    ///
+    /// - `weight`: The weight of the libfuncs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the weight can't be selected for the statistics of
+    /// each contract address, see [`ReplayStatistics::with_weight`].
+    pub fn with_weight(&self, weight: LibfuncWeight) -> Result<AddressStatistics, ProfilerError> {
+        Ok(AddressStatistics {
+            contracts: map_statistics(&self.contracts, |statistics| {
+                statistics.with_weight(weight)
+            })?,
+        })
+    }
+
+    /// Serialises [`AddressStatistics`] to CSV format.
+    ///
+    /// The columns are the same of [`ContractStatistics::to_csv_bytes`], with
+    /// the contract address before the class hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    pub fn to_csv_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        contracts_to_csv_bytes(
+            &self.contracts,
+            "Contract Address,Class Hash",
+            |(contract_address, class_hash)| {
+                format!("{:#x},{:#x}", contract_address.0.key(), class_hash.0)
+            },
+        )
+    }
+
+    /// Serialises [`AddressStatistics`] to JSON format.
+    ///
+    /// Contracts are reported in descending order of libfunc calls.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an error serialising the object.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        contracts_to_json(&self.contracts, |(contract_address, class_hash)| {
+            AddressKey {
+                contract_address: *contract_address,
+                class_hash: *class_hash,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str;
+
+    use indoc::indoc;
+    use starknet_core::types::Felt;
+
+    use super::*;
+
+    #[test]
+    fn test_contract_statistics() {
+        let class_hash = ClassHash(Felt::from(1_u64));
+        let other_class_hash = ClassHash(Felt::from(2_u64));
+
+        let mut first = ReplayStatistics::new();
+        first.update(&"u256_safe_divmod".to_string(), 3);
+        let mut second = ReplayStatistics::new();
+        second.update(&"u256_safe_divmod".to_string(), 1);
+        second.update(&"felt252_add".to_string(), 2);
+
+        let mut contract_statistics = ContractStatistics::new();
+        contract_statistics.add(class_hash, &first);
+        contract_statistics.add(other_class_hash, &second);
+        contract_statistics.add(class_hash, &first);
+
+        assert_eq!(
+            contract_statistics.classes[&class_hash].get_libfunc_frequency("u256_safe_divmod"),
+            6
+        );
+        let total = contract_statistics.total();
+        assert_eq!(total.get_libfunc_frequency("u256_safe_divmod"), 7);
+        assert_eq!(total.get_libfunc_frequency("felt252_add"), 2);
+
+        let version = ClassVersion {
+            sierra_version: "1.6.0".to_string(),
+            compiler_version: "2.7.0".to_string(),
+        };
+        contract_statistics.set_class_version(class_hash, &version);
+        let version_statistics = contract_statistics.by_version();
+        assert_eq!(version_statistics.versions[&version].classes, 1);
+        assert_eq!(
+            version_statistics.versions[&ClassVersion::default()]
+                .statistics
+                .get_libfunc_frequency("felt252_add"),
+            2
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&contract_statistics.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["total_calls"], 6);
+        assert_eq!(json[1]["total_calls"], 3);
+        assert_eq!(json[1]["libfuncs"]["felt252_add"]["weight"], 2);
+        assert_eq!(
+            json[1]["libfuncs"]["felt252_add"]["share_of_libfunc_calls"],
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_address_statistics() {
+        let class_hash = ClassHash(Felt::from(1_u64));
+        let first_address = ContractAddress::try_from(Felt::from(10_u64)).unwrap();
+        let second_address = ContractAddress::try_from(Felt::from(11_u64)).unwrap();
+
+        let mut first = ReplayStatistics::new();
+        first.update(&"u256_safe_divmod".to_string(), 1);
+        let mut second = ReplayStatistics::new();
+        second.update(&"u256_safe_divmod".to_string(), 3);
+
+        // Contracts deployed with the same class are reported separately.
+        let mut address_statistics = AddressStatistics::new();
+        address_statistics.add(first_address, class_hash, &first);
+        address_statistics.add(second_address, class_hash, &second);
+        assert_eq!(address_statistics.contracts.len(), 2);
+        assert_eq!(
+            address_statistics
+                .total()
+                .get_libfunc_frequency("u256_safe_divmod"),
+            4
+        );
+
+        let expected_string = indoc! {r#"
+            Contract Address,Class Hash,Contract Share,Function Name,Weight,Libfunc Share
+            0xb,0x1,0.750000,"u256_safe_divmod",3,0.750000
+            0xa,0x1,0.250000,"u256_safe_divmod",1,0.250000
+        "#};
+        let csv_output = address_statistics.to_csv_bytes().unwrap();
+        assert_eq!(str::from_utf8(&csv_output).unwrap(), expected_string);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&address_statistics.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["class_hash"], "0x1");
+        assert_eq!(json[0]["total_calls"], 3);
+        assert_eq!(json[1]["share_of_total_calls"], 0.25);
+    }
+}
@@ -0,0 +1,281 @@
+//! The module which computes the coverage of the Sierra statements of a
+//! contract class from the visited program counters and exports it in lcov
+//! format.
+//!
+//! The Sierra program is used as source file: each line of the source file is a
+//! Sierra statement, the line number is the statement index plus 1. Each user
+//! function of the Sierra program starts at the line of its entry point. The
+//! source file of each contract class is written next to the lcov file.
+
+use std::io::Write;
+use std::path::Path;
+
+use cairo_lang_sierra::program::StatementIdx;
+use starknet_api::core::ClassHash;
+
+use crate::profiler::SierraProfiler;
+use crate::runner::pc_histogram::PcHistogram;
+
+/// The coverage of a user function of the Sierra program.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FunctionCoverage {
+    /// The name of the function. It's the numeric id of the function if the
+    /// Sierra program has no debug information.
+    pub name: String,
+
+    /// The index of the first statement of the function.
+    pub entry_point: usize,
+
+    /// The number of calls of the function.
+    pub hits: usize,
+
+    /// The number of statements of the function.
+    pub statements: usize,
+
+    /// The number of statements of the function executed at least once.
+    pub statements_hit: usize,
+}
+
+/// The coverage of the Sierra statements of a contract class.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ClassCoverage {
+    /// The class hash of the contract.
+    pub class_hash: ClassHash,
+
+    /// The number of executions of each Sierra statement, indexed by statement
+    /// index.
+    pub statement_hits: Vec<usize>,
+
+    /// The text of each Sierra statement, indexed by statement index.
+    pub statements: Vec<String>,
+
+    /// The coverage of each user function sorted by entry point.
+    pub functions: Vec<FunctionCoverage>,
+}
+impl ClassCoverage {
+    /// Computes the coverage of the Sierra program in `runner`.
+    ///
+    /// Statements never visited are reported with 0 hits.
+    ///
+    /// # Arguments
+    ///
+    /// - `class_hash`: The class hash of the contract.
+    /// - `runner`: The Sierra profiler of the contract.
+    /// - `pcs`: The histogram of program counters visited in the contract.
+    #[must_use]
+    pub fn new(class_hash: ClassHash, runner: &SierraProfiler, pcs: &PcHistogram) -> Self {
+        let profiling_info = runner.collect_profiling_info(pcs);
+        let number_of_statements = runner.sierra_program.statements.len();
+        let statement_hits: Vec<usize> = (0..number_of_statements)
+            .map(|statement_idx| {
+                profiling_info
+                    .get(&StatementIdx(statement_idx))
+                    .copied()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let statements = runner
+            .sierra_program
+            .statements
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        let mut entry_points: Vec<(usize, String)> = runner
+            .sierra_program
+            .funcs
+            .iter()
+            .map(|function| (function.entry_point.0, function.id.to_string()))
+            .collect();
+        entry_points.sort_unstable();
+
+        let ends = entry_points
+            .iter()
+            .skip(1)
+            .map(|(entry_point, _)| *entry_point)
+            .chain(std::iter::once(number_of_statements));
+        let functions = entry_points
+            .iter()
+            .zip(ends)
+            .map(|((entry_point, name), end)| {
+                let function_hits = statement_hits.get(*entry_point..end).unwrap_or_default();
+                FunctionCoverage {
+                    name: name.clone(),
+                    entry_point: *entry_point,
+                    hits: function_hits.first().copied().unwrap_or_default(),
+                    statements: function_hits.len(),
+                    statements_hit: function_hits.iter().filter(|hits| **hits > 0).count(),
+                }
+            })
+            .collect();
+
+        ClassCoverage {
+            class_hash,
+            statement_hits,
+            statements,
+            functions,
+        }
+    }
+
+    /// Returns the number of statements executed at least once.
+    #[must_use]
+    pub fn statements_hit(&self) -> usize {
+        self.statement_hits.iter().filter(|hits| **hits > 0).count()
+    }
+
+    /// Returns the number of functions called at least once.
+    #[must_use]
+    pub fn functions_hit(&self) -> usize {
+        self.functions
+            .iter()
+            .filter(|function| function.hits > 0)
+            .count()
+    }
+
+    /// Returns the name of the source file of the contract class, which is
+    /// named after the class hash.
+    #[must_use]
+    pub fn source_filename(&self) -> String {
+        format!("{:#x}.sierra", self.class_hash.0)
+    }
+
+    /// Serialises the Sierra program of the contract class as source file, one
+    /// statement per line.
+    #[must_use]
+    pub fn to_source_bytes(&self) -> Vec<u8> {
+        let mut source = self.statements.join("\n");
+        source.push('\n');
+        source.into_bytes()
+    }
+
+    /// Writes the coverage of the contract class as an lcov record.
+    ///
+    /// # Arguments
+    ///
+    /// - `f`: The buffer to write to.
+    /// - `source_directory`: The directory of the source file of the contract
+    ///   class.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    fn write_lcov(
+        &self,
+        f: &mut impl Write,
+        source_directory: &Path,
+    ) -> Result<(), std::io::Error> {
+        writeln!(f, "TN:")?;
+        writeln!(
+            f,
+            "SF:{}",
+            source_directory.join(self.source_filename()).display()
+        )?;
+        for function in &self.functions {
+            writeln!(f, "FN:{},{}", function.entry_point + 1, function.name)?;
+        }
+        for function in &self.functions {
+            writeln!(f, "FNDA:{},{}", function.hits, function.name)?;
+        }
+        writeln!(f, "FNF:{}", self.functions.len())?;
+        writeln!(f, "FNH:{}", self.functions_hit())?;
+        for (statement_idx, hits) in self.statement_hits.iter().enumerate() {
+            writeln!(f, "DA:{},{hits}", statement_idx + 1)?;
+        }
+        writeln!(f, "LF:{}", self.statement_hits.len())?;
+        writeln!(f, "LH:{}", self.statements_hit())?;
+        writeln!(f, "end_of_record")?;
+        Ok(())
+    }
+}
+
+/// Serialises the coverage of a list of contract classes to lcov format.
+///
+/// # Arguments
+///
+/// - `coverage`: The coverage of each contract class.
+/// - `source_directory`: The directory of the source files of the contract
+///   classes.
+///
+/// # Errors
+///
+/// Returns [`Err`] if there is an IO error writing to the buffer.
+pub fn to_lcov_bytes(
+    coverage: &[ClassCoverage],
+    source_directory: &Path,
+) -> Result<Vec<u8>, std::io::Error> {
+    let mut f = Vec::new();
+    for class_coverage in coverage {
+        class_coverage.write_lcov(&mut f, source_directory)?;
+    }
+    Ok(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str;
+
+    use indoc::indoc;
+    use starknet_core::types::Felt;
+
+    use super::*;
+
+    #[test]
+    fn test_to_lcov_bytes() {
+        let coverage = ClassCoverage {
+            class_hash: ClassHash(Felt::from(10_u64)),
+            statement_hits: vec![2, 2, 0, 1, 0],
+            statements: vec![
+                "felt252_add([0], [1]) -> ([2]);".to_string(),
+                "store_temp<felt252>([2]) -> ([2]);".to_string(),
+                "return([2]);".to_string(),
+                "drop<felt252>([0]) -> ();".to_string(),
+                "return();".to_string(),
+            ],
+            functions: vec![
+                FunctionCoverage {
+                    name: "[0]".to_string(),
+                    entry_point: 0,
+                    hits: 2,
+                    statements: 3,
+                    statements_hit: 2,
+                },
+                FunctionCoverage {
+                    name: "[1]".to_string(),
+                    entry_point: 3,
+                    hits: 1,
+                    statements: 2,
+                    statements_hit: 1,
+                },
+            ],
+        };
+        assert_eq!(coverage.statements_hit(), 3);
+        assert_eq!(coverage.functions_hit(), 2);
+
+        let expected_string = indoc! {"
+            TN:
+            SF:coverage/0xa.sierra
+            FN:1,[0]
+            FN:4,[1]
+            FNDA:2,[0]
+            FNDA:1,[1]
+            FNF:2
+            FNH:2
+            DA:1,2
+            DA:2,2
+            DA:3,0
+            DA:4,1
+            DA:5,0
+            LF:5
+            LH:3
+            end_of_record
+        "};
+        let lcov_output = to_lcov_bytes(&[coverage.clone()], Path::new("coverage")).unwrap();
+        assert_eq!(str::from_utf8(&lcov_output).unwrap(), expected_string);
+
+        assert_eq!(coverage.source_filename(), "0xa.sierra");
+        let source = coverage.to_source_bytes();
+        let source = str::from_utf8(&source).unwrap();
+        assert_eq!(source.lines().count(), 5);
+        assert_eq!(source.lines().nth(3), Some("drop<felt252>([0]) -> ();"));
+    }
+}
@@ -0,0 +1,335 @@
+//! The module which provides the breakdown of libfunc usage statistics by entry
+//! point of each contract class.
+//!
+//! The entry point executed is identified by the first program counter recorded
+//! by [`blockifier`] for each entry point call. The name of the entry point is
+//! the name of the function in the ABI of the contract class with the same
+//! selector.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `EntryPointStatistics`
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use itertools::Itertools;
+use serde::Serialize;
+use starknet_api::core::ClassHash;
+
+use crate::error::ProfilerError;
+use crate::profiler::contract_statistics::ContractStatistics;
+use crate::profiler::replay_statistics::{LibfuncGranularity, LibfuncWeight, ReplayStatistics};
+use crate::profiler::skipped_classes::SkippedClasses;
+use crate::profiler::version_statistics::ClassVersion;
+
+/// The name used for entry points which can't be matched with the entry points
+/// of the contract class.
+pub const UNKNOWN_ENTRY_POINT: &str = "unknown";
+
+/// The struct to hold the libfunc usage statistics of each entry point of each
+/// contract class.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct EntryPointStatistics {
+    /// This field contains the association between the pair of class hash and
+    /// entry point name (key) and the libfunc usage statistics of the entry
+    /// point (value).
+    pub entry_points: BTreeMap<(ClassHash, String), ReplayStatistics>,
+
+    /// This field contains the association between class hash (key) and the
+    /// Sierra version and compiler version of the class (value). Classes
+    /// whose versions can't be read are not included.
+    pub class_versions: BTreeMap<ClassHash, ClassVersion>,
+
+    /// This field contains the contract classes skipped by the analysis,
+    /// which are missing from the statistics.
+    pub skipped_classes: SkippedClasses,
+}
+
+/// The libfunc usage of an entry point in the JSON report.
+#[derive(Debug, Serialize)]
+struct EntryPointReport<'a> {
+    /// The class hash of the contract.
+    class_hash: ClassHash,
+
+    /// The name of the entry point.
+    entry_point: &'a str,
+
+    /// The number of libfunc calls of the entry point.
+    total_calls: usize,
+
+    /// The number of calls of each libfunc called by the entry point.
+    libfuncs: BTreeMap<&'a str, usize>,
+}
+
+impl EntryPointStatistics {
+    /// Initialisation of [`EntryPointStatistics`].
+    ///
+    /// The struct is initialised with all fields empty.
+    #[must_use]
+    pub fn new() -> Self {
+        EntryPointStatistics {
+            entry_points: BTreeMap::default(),
+            class_versions: BTreeMap::default(),
+            skipped_classes: SkippedClasses::new(),
+        }
+    }
+
+    /// Adds the libfunc usage statistics of an entry point.
+    ///
+    /// If the entry point already exists, the statistics are merged.
+    ///
+    /// # Arguments
+    ///
+    /// - `class_hash`: The class hash of the contract.
+    /// - `entry_point`: The name of the entry point.
+    /// - `statistics`: The libfunc usage statistics of the entry point.
+    pub fn add(&mut self, class_hash: ClassHash, entry_point: &str, statistics: &ReplayStatistics) {
+        self.entry_points
+            .entry((class_hash, entry_point.to_string()))
+            .or_default()
+            .merge(statistics);
+    }
+
+    /// Sets the Sierra version and compiler version of a contract class.
+    ///
+    /// # Arguments
+    ///
+    /// - `class_hash`: The class hash of the contract.
+    /// - `version`: The versions of the contract class.
+    pub fn set_class_version(&mut self, class_hash: ClassHash, version: &ClassVersion) {
+        self.class_versions.insert(class_hash, version.clone());
+    }
+
+    /// Update the object with data in `from`.
+    ///
+    /// # Arguments
+    ///
+    /// - `from`: Input `EntryPointStatistics` to get data from.
+    pub fn merge(&mut self, from: &EntryPointStatistics) {
+        for ((class_hash, entry_point), statistics) in &from.entry_points {
+            self.add(*class_hash, entry_point, statistics);
+        }
+        for (class_hash, version) in &from.class_versions {
+            self.set_class_version(*class_hash, version);
+        }
+        self.skipped_classes.merge(&from.skipped_classes);
+    }
+
+    /// Returns the libfunc usage statistics of each contract class, summing
+    /// the statistics of its entry points.
+    #[must_use]
+    pub fn by_contract(&self) -> ContractStatistics {
+        let mut contract_statistics = ContractStatistics::new();
+        for ((class_hash, _), statistics) in &self.entry_points {
+            contract_statistics.add(*class_hash, statistics);
+        }
+        for (class_hash, version) in &self.class_versions {
+            contract_statistics.set_class_version(*class_hash, version);
+        }
+        contract_statistics.skipped_classes = self.skipped_classes.clone();
+        contract_statistics
+    }
+
+    /// Returns the statistics of each entry point with the libfunc names of the
+    /// chosen granularity.
+    ///
+    /// # Arguments
+    ///
+    /// - `granularity`: The granularity of the libfunc names.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the granularity can't be selected for the statistics
+    /// of each entry point, see [`ReplayStatistics::with_granularity`].
+    pub fn with_granularity(
+        &self,
+        granularity: LibfuncGranularity,
+    ) -> Result<EntryPointStatistics, ProfilerError> {
+        Ok(EntryPointStatistics {
+            entry_points: self
+                .entry_points
+                .iter()
+                .map(|(key, statistics)| {
+                    Ok((key.clone(), statistics.with_granularity(granularity)?))
+                })
+                .collect::<Result<_, ProfilerError>>()?,
+            class_versions: self.class_versions.clone(),
+            skipped_classes: self.skipped_classes.clone(),
+        })
+    }
+
+    /// Returns the statistics of each entry point with the libfuncs weighted by
+    /// the chosen weight.
+    ///
+    /// # Arguments
+    ///
+    /// - `weight`: The weight of the libfuncs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the weight can't be selected for the statistics of
+    /// each entry point, see [`ReplayStatistics::with_weight`].
+    pub fn with_weight(
+        &self,
+        weight: LibfuncWeight,
+    ) -> Result<EntryPointStatistics, ProfilerError> {
+        Ok(EntryPointStatistics {
+            entry_points: self
+                .entry_points
+                .iter()
+                .map(|(key, statistics)| Ok((key.clone(), statistics.with_weight(weight)?)))
+                .collect::<Result<_, ProfilerError>>()?,
+            class_versions: self.class_versions.clone(),
+            skipped_classes: self.skipped_classes.clone(),
+        })
+    }
+
+    /// Returns the entry points sorted by descending number of libfunc calls
+    /// together with their number of calls.
+    fn entry_points_by_calls(&self) -> Vec<(&ClassHash, &str, &ReplayStatistics, usize)> {
+        self.entry_points
+            .iter()
+            .map(|((class_hash, entry_point), statistics)| {
+                let calls = statistics.concrete_libfunc.values().sum();
+                (class_hash, entry_point.as_str(), statistics, calls)
+            })
+            .sorted_by(|a, b| {
+                Ord::cmp(&b.3, &a.3)
+                    .then(Ord::cmp(a.0, b.0))
+                    .then(Ord::cmp(a.1, b.1))
+            })
+            .collect()
+    }
+
+    /// Serialises [`EntryPointStatistics`] to CSV format.
+    ///
+    /// Entry points are reported in descending order of libfunc calls. For
+    /// each entry point, libfuncs are reported in descending order of weight.
+    ///
+    /// The function name is quoted because it may contain commas.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str;
+    /// # use indoc::indoc;
+    /// # use starknet_api::core::ClassHash;
+    /// # use starknet_core::types::Felt;
+    /// # use starknet_replay::profiler::entry_point_statistics::EntryPointStatistics;
+    /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+    /// let mut transfer = ReplayStatistics::default();
+    /// transfer.update(&"u256_safe_divmod".to_string(), 3);
+    /// transfer.update(&"felt252_add".to_string(), 5);
+    /// let mut approve = ReplayStatistics::default();
+    /// approve.update(&"u256_safe_divmod".to_string(), 1);
+    /// let mut entry_point_statistics = EntryPointStatistics::new();
+    /// entry_point_statistics.add(ClassHash(Felt::from(1_u64)), "transfer", &transfer);
+    /// entry_point_statistics.add(ClassHash(Felt::from(1_u64)), "approve", &approve);
+    /// let expected_string = indoc! {r#"
+    ///     Class Hash,Entry Point,Function Name,Weight
+    ///     0x1,transfer,"felt252_add",5
+    ///     0x1,transfer,"u256_safe_divmod",3
+    ///     0x1,approve,"u256_safe_divmod",1
+    /// "#};
+    /// let csv_output = entry_point_statistics.to_csv_bytes().unwrap();
+    /// assert_eq!(
+    ///     str::from_utf8(csv_output.as_slice()).unwrap(),
+    ///     expected_string
+    /// );
+    /// ```
+    pub fn to_csv_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut f = Vec::new();
+        writeln!(f, "Class Hash,Entry Point,Function Name,Weight")?;
+        for (class_hash, entry_point, statistics, _) in self.entry_points_by_calls() {
+            for (concrete_name, weight) in statistics
+                .concrete_libfunc
+                .iter()
+                .sorted_by(|a, b| Ord::cmp(&b.1, &a.1).then(Ord::cmp(a.0, b.0)))
+            {
+                writeln!(
+                    f,
+                    "{:#x},{entry_point},\"{concrete_name}\",{weight}",
+                    class_hash.0
+                )?;
+            }
+        }
+        Ok(f)
+    }
+
+    /// Serialises [`EntryPointStatistics`] to JSON format.
+    ///
+    /// Entry points are reported in descending order of libfunc calls.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an error serialising the object.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        let report: Vec<EntryPointReport> = self
+            .entry_points_by_calls()
+            .into_iter()
+            .map(
+                |(class_hash, entry_point, statistics, calls)| EntryPointReport {
+                    class_hash: *class_hash,
+                    entry_point,
+                    total_calls: calls,
+                    libfuncs: statistics
+                        .concrete_libfunc
+                        .iter()
+                        .map(|(concrete_name, weight)| (concrete_name.as_str(), *weight))
+                        .collect(),
+                },
+            )
+            .collect();
+        serde_json::to_string_pretty(&report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_core::types::Felt;
+
+    use super::*;
+
+    #[test]
+    fn test_entry_point_statistics() {
+        let class_hash = ClassHash(Felt::from(1_u64));
+        let other_class_hash = ClassHash(Felt::from(2_u64));
+
+        let mut transfer = ReplayStatistics::new();
+        transfer.update(&"u256_safe_divmod".to_string(), 3);
+        let mut swap = ReplayStatistics::new();
+        swap.update(&"u256_safe_divmod".to_string(), 1);
+        swap.update(&"felt252_add".to_string(), 2);
+
+        let mut entry_point_statistics = EntryPointStatistics::new();
+        entry_point_statistics.add(class_hash, "transfer", &transfer);
+        entry_point_statistics.add(class_hash, "swap", &swap);
+        entry_point_statistics.add(other_class_hash, "swap", &swap);
+        entry_point_statistics.add(class_hash, "transfer", &transfer);
+
+        assert_eq!(
+            entry_point_statistics.entry_points[&(class_hash, "transfer".to_string())]
+                .get_libfunc_frequency("u256_safe_divmod"),
+            6
+        );
+
+        let contract_statistics = entry_point_statistics.by_contract();
+        assert_eq!(
+            contract_statistics.classes[&class_hash].get_libfunc_frequency("u256_safe_divmod"),
+            7
+        );
+        assert_eq!(
+            contract_statistics.classes[&other_class_hash].get_libfunc_frequency("felt252_add"),
+            2
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&entry_point_statistics.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["entry_point"], "transfer");
+        assert_eq!(json[0]["total_calls"], 6);
+        assert_eq!(json[1]["libfuncs"]["felt252_add"], 2);
+    }
+}
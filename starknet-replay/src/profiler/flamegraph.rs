@@ -0,0 +1,448 @@
+//! The module which folds the call traces of the replayed transactions into
+//! call stacks and exports them in the folded stacks format used by flamegraph
+//! tools (e.g. `inferno` or `flamegraph.pl`).
+//!
+//! Each line of the output is a call stack followed by the number of CASM steps
+//! executed with that call stack. The frames of a call stack are:
+//!
+//! - the contract call, as `<class hash>::<entry point>`;
+//! - the Sierra user functions called within the contract;
+//! - the libfunc executed, as leaf.
+//!
+//! Contract calls made through a syscall are nested below the call stack of the
+//! syscall.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `FoldedStacks`
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::sync::Arc;
+
+use cairo_lang_sierra::program::GenStatement;
+use itertools::Itertools;
+use starknet_api::core::ClassHash;
+
+use crate::profiler::entry_point_statistics::UNKNOWN_ENTRY_POINT;
+use crate::profiler::SierraProfiler;
+use crate::runner::call_trace::CallTrace;
+
+/// The generic libfuncs which call another contract. The contract calls are
+/// matched in order with the inner calls of the call trace.
+const CONTRACT_CALL_LIBFUNCS: [&str; 3] = [
+    "call_contract_syscall",
+    "library_call_syscall",
+    "deploy_syscall",
+];
+
+/// The generic libfunc used to call a user function.
+const FUNCTION_CALL_LIBFUNC: &str = "function_call";
+
+/// The name of the leaf frame of a `return` statement.
+const RETURN_FRAME: &str = "return";
+
+/// The data of a contract class required to name the frames of its calls.
+pub struct ClassFrames {
+    /// The Sierra profiler of the contract class.
+    runner: Arc<SierraProfiler>,
+
+    /// The map between the program counter of each entry point and its name.
+    entry_point_names: HashMap<usize, String>,
+
+    /// The map between the index of the first statement of each user function
+    /// and its name.
+    function_names: HashMap<usize, String>,
+}
+impl ClassFrames {
+    /// Initialisation of [`ClassFrames`].
+    ///
+    /// # Arguments
+    ///
+    /// - `runner`: The Sierra profiler of the contract class.
+    /// - `entry_point_names`: The map between the program counter of each entry
+    ///   point and its name.
+    #[must_use]
+    pub fn new(runner: Arc<SierraProfiler>, entry_point_names: HashMap<usize, String>) -> Self {
+        let function_names = runner
+            .sierra_program
+            .funcs
+            .iter()
+            .map(|function| (function.entry_point.0, function.id.to_string()))
+            .collect();
+        ClassFrames {
+            runner,
+            entry_point_names,
+            function_names,
+        }
+    }
+
+    /// Returns the name of the entry point starting at `pc`.
+    ///
+    /// # Arguments
+    ///
+    /// - `pc`: The first program counter of the entry point execution.
+    fn entry_point_name(&self, pc: usize) -> &str {
+        self.entry_point_names
+            .get(&pc)
+            .map_or(UNKNOWN_ENTRY_POINT, String::as_str)
+    }
+
+    /// Returns `true` if the statement `statement_idx` invokes a libfunc of
+    /// one of the generic libfuncs `generic_ids`.
+    ///
+    /// # Arguments
+    ///
+    /// - `statement_idx`: The index of the Sierra statement.
+    /// - `generic_ids`: The list of generic libfuncs.
+    fn invokes(&self, statement_idx: usize, generic_ids: &[&str]) -> bool {
+        let Some(GenStatement::Invocation(invocation)) =
+            self.runner.sierra_program.statements.get(statement_idx)
+        else {
+            return false;
+        };
+        self.runner
+            .generic_libfunc_id(&invocation.libfunc_id)
+            .is_some_and(|generic_id| generic_ids.contains(&generic_id.0.as_str()))
+    }
+
+    /// Returns the name of the leaf frame of the statement `statement_idx`.
+    ///
+    /// # Arguments
+    ///
+    /// - `statement_idx`: The index of the Sierra statement.
+    fn leaf_frame(&self, statement_idx: usize) -> String {
+        match self.runner.sierra_program.statements.get(statement_idx) {
+            Some(GenStatement::Invocation(invocation)) => invocation.libfunc_id.to_string(),
+            Some(GenStatement::Return(_)) => RETURN_FRAME.to_string(),
+            None => UNKNOWN_ENTRY_POINT.to_string(),
+        }
+    }
+}
+
+/// Returns the call stack made of `frame` followed by `functions`.
+///
+/// # Arguments
+///
+/// - `frame`: The call stack of the contract call.
+/// - `functions`: The user functions called within the contract call.
+fn join_frames(frame: &str, functions: &[&str]) -> String {
+    std::iter::once(frame)
+        .chain(functions.iter().copied())
+        .join(";")
+}
+
+/// The samples collected for a call stack.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub struct StackSamples {
+    /// The number of CASM steps executed.
+    pub steps: usize,
+
+    /// The number of libfunc calls.
+    pub libfunc_calls: usize,
+
+    /// The number of builtins passed to the libfuncs called.
+    pub builtin_calls: usize,
+}
+impl StackSamples {
+    /// Adds the samples in `other` to `self`.
+    ///
+    /// # Arguments
+    ///
+    /// - `other`: The samples to add.
+    pub fn merge(&mut self, other: &StackSamples) {
+        self.steps += other.steps;
+        self.libfunc_calls += other.libfunc_calls;
+        self.builtin_calls += other.builtin_calls;
+    }
+}
+
+/// The samples collected for each call stack.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct FoldedStacks {
+    /// This field contains the association between a call stack, with frames
+    /// separated by `;`, and the samples collected with it.
+    pub stacks: BTreeMap<String, StackSamples>,
+}
+impl FoldedStacks {
+    /// Initialisation of [`FoldedStacks`].
+    ///
+    /// The struct is initialised with field `stacks` empty.
+    #[must_use]
+    pub fn new() -> Self {
+        FoldedStacks {
+            stacks: BTreeMap::default(),
+        }
+    }
+
+    /// Adds `samples` to the samples of the call stack `stack`.
+    ///
+    /// # Arguments
+    ///
+    /// - `stack`: The call stack with frames separated by `;`.
+    /// - `samples`: The samples to add.
+    pub fn add(&mut self, stack: &str, samples: &StackSamples) {
+        if let Some(stack_samples) = self.stacks.get_mut(stack) {
+            stack_samples.merge(samples);
+        } else {
+            self.stacks.insert(stack.to_string(), *samples);
+        }
+    }
+
+    /// Update the object with data in `from`.
+    ///
+    /// # Arguments
+    ///
+    /// - `from`: Input `FoldedStacks` to get data from.
+    pub fn merge(&mut self, from: &FoldedStacks) {
+        for (stack, samples) in &from.stacks {
+            self.add(stack, samples);
+        }
+    }
+
+    /// Returns the sum of the samples of all call stacks.
+    #[must_use]
+    pub fn total(&self) -> StackSamples {
+        let mut total = StackSamples::default();
+        for samples in self.stacks.values() {
+            total.merge(samples);
+        }
+        total
+    }
+
+    /// Adds the call stacks of `call_trace` and of its inner calls.
+    ///
+    /// # Arguments
+    ///
+    /// - `call_trace`: The call trace to fold.
+    /// - `parent`: The call stack of the caller. It's `None` for the top level
+    ///   calls of a transaction.
+    /// - `classes`: The frame data of each contract class.
+    pub fn add_call_trace(
+        &mut self,
+        call_trace: &CallTrace,
+        parent: Option<&str>,
+        classes: &HashMap<ClassHash, ClassFrames>,
+    ) {
+        let inner_call_stacks = self.add_call(call_trace, parent, classes);
+        for (inner_call, inner_call_stack) in call_trace.inner_calls.iter().zip(inner_call_stacks) {
+            self.add_call_trace(inner_call, Some(&inner_call_stack), classes);
+        }
+    }
+
+    /// Adds the call stacks of `call_trace` without its inner calls and
+    /// returns the call stack of the caller of each inner call.
+    ///
+    /// Calls of classes missing from `classes` don't add any call stack.
+    ///
+    /// # Arguments
+    ///
+    /// - `call_trace`: The call trace to fold.
+    /// - `parent`: The call stack of the caller. It's `None` for the top level
+    ///   calls of a transaction.
+    /// - `classes`: The frame data of each contract class.
+    pub fn add_call(
+        &mut self,
+        call_trace: &CallTrace,
+        parent: Option<&str>,
+        classes: &HashMap<ClassHash, ClassFrames>,
+    ) -> Vec<String> {
+        let class = call_trace
+            .class_hash
+            .and_then(|replay_class_hash| classes.get(&replay_class_hash.class_hash));
+        let class_name = call_trace.class_hash.map_or_else(
+            || UNKNOWN_ENTRY_POINT.to_string(),
+            |replay_class_hash| format!("{:#x}", replay_class_hash.class_hash.0),
+        );
+        let entry_point = match (class, call_trace.pcs.first()) {
+            (Some(class), Some(pc)) => class.entry_point_name(*pc),
+            _ => UNKNOWN_ENTRY_POINT,
+        };
+        let contract_frame = format!("{class_name}::{entry_point}");
+        let frame = match parent {
+            Some(parent) => format!("{parent};{contract_frame}"),
+            None => contract_frame,
+        };
+
+        let contract_call_stacks = match class {
+            Some(class) => self.add_pcs(&call_trace.pcs, &frame, class),
+            None => Vec::new(),
+        };
+        // If the contract calls can't be matched with the inner calls, the
+        // inner calls are nested below the contract frame.
+        if contract_call_stacks.len() == call_trace.inner_calls.len() {
+            contract_call_stacks
+        } else {
+            vec![frame; call_trace.inner_calls.len()]
+        }
+    }
+
+    /// Adds one step for each program counter in `pcs` to the call stack
+    /// active when the program counter is executed and returns the call stacks
+    /// of the contract calls in order of execution.
+    ///
+    /// A program counter which doesn't start a Sierra statement belongs to the
+    /// last statement started. The user function called by a `function_call`
+    /// is pushed on the stack when its first statement is reached and it's
+    /// popped after a `return` statement.
+    ///
+    /// Each libfunc invoked is counted once, including libfuncs without CASM
+    /// instructions, which are reported with 0 steps.
+    ///
+    /// # Arguments
+    ///
+    /// - `pcs`: The program counters visited by a single contract call.
+    /// - `frame`: The call stack of the contract call.
+    /// - `class`: The frame data of the contract class.
+    fn add_pcs(&mut self, pcs: &[usize], frame: &str, class: &ClassFrames) -> Vec<String> {
+        let mut functions: Vec<&str> = Vec::new();
+        let mut stack = frame.to_string();
+        let mut statement: Option<usize> = None;
+        let mut contract_call_stacks = Vec::new();
+
+        for pc in pcs {
+            let statements: Vec<usize> = class
+                .runner
+                .commands_at_pc(*pc)
+                .map(|command| command.statement_idx())
+                .collect();
+            // Statements without CASM instructions start at the same program
+            // counter of the following statement, therefore the last statement
+            // is the one executed.
+            if let Some(next_statement) = statements.last() {
+                let is_call = statement.map_or(true, |statement_idx| {
+                    class.invokes(statement_idx, &[FUNCTION_CALL_LIBFUNC])
+                });
+                let is_return = statement.is_some_and(|statement_idx| {
+                    matches!(
+                        class.runner.sierra_program.statements.get(statement_idx),
+                        Some(GenStatement::Return(_))
+                    )
+                });
+                if is_call {
+                    if let Some(function) = statements
+                        .iter()
+                        .find_map(|statement_idx| class.function_names.get(statement_idx))
+                    {
+                        functions.push(function);
+                        stack = join_frames(frame, &functions);
+                    }
+                } else if is_return && functions.pop().is_some() {
+                    stack = join_frames(frame, &functions);
+                }
+
+                for statement_idx in &statements {
+                    if let Some(GenStatement::Invocation(invocation)) =
+                        class.runner.sierra_program.statements.get(*statement_idx)
+                    {
+                        let samples = StackSamples {
+                            steps: 0,
+                            libfunc_calls: 1,
+                            builtin_calls: class.runner.builtin_calls(&invocation.libfunc_id),
+                        };
+                        self.add(
+                            &format!("{stack};{}", class.leaf_frame(*statement_idx)),
+                            &samples,
+                        );
+                    }
+                }
+
+                statement = Some(*next_statement);
+                if class.invokes(*next_statement, &CONTRACT_CALL_LIBFUNCS) {
+                    contract_call_stacks.push(stack.clone());
+                }
+            }
+
+            let leaf = statement.map_or_else(
+                || UNKNOWN_ENTRY_POINT.to_string(),
+                |statement_idx| class.leaf_frame(statement_idx),
+            );
+            let samples = StackSamples {
+                steps: 1,
+                ..StackSamples::default()
+            };
+            self.add(&format!("{stack};{leaf}"), &samples);
+        }
+        contract_call_stacks
+    }
+
+    /// Serialises [`FoldedStacks`] to the folded stacks format.
+    ///
+    /// Each line contains a call stack followed by the number of steps. Call
+    /// stacks without steps are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut f = Vec::new();
+        for (stack, samples) in &self.stacks {
+            if samples.steps > 0 {
+                writeln!(f, "{stack} {}", samples.steps)?;
+            }
+        }
+        Ok(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str;
+
+    use indoc::indoc;
+    use starknet_core::types::Felt;
+
+    use super::*;
+    use crate::block_number::BlockNumber;
+    use crate::runner::replay_class_hash::ReplayClassHash;
+
+    fn steps(steps: usize) -> StackSamples {
+        StackSamples {
+            steps,
+            ..StackSamples::default()
+        }
+    }
+
+    #[test]
+    fn test_folded_stacks() {
+        let mut folded_stacks = FoldedStacks::new();
+        folded_stacks.add("0x1::transfer;[0];store_temp<felt252>", &steps(2));
+        folded_stacks.add("0x1::transfer;[0];return", &steps(1));
+        folded_stacks.add(
+            "0x1::transfer;[0];branch_align",
+            &StackSamples {
+                libfunc_calls: 1,
+                ..StackSamples::default()
+            },
+        );
+
+        let mut other = FoldedStacks::new();
+        other.add("0x1::transfer;[0];store_temp<felt252>", &steps(3));
+        folded_stacks.merge(&other);
+        assert_eq!(folded_stacks.total().steps, 6);
+        assert_eq!(folded_stacks.total().libfunc_calls, 1);
+
+        let expected_string = indoc! {"
+            0x1::transfer;[0];return 1
+            0x1::transfer;[0];store_temp<felt252> 5
+        "};
+        let output = folded_stacks.to_bytes().unwrap();
+        assert_eq!(str::from_utf8(&output).unwrap(), expected_string);
+    }
+
+    #[test]
+    fn test_add_call_trace_unknown_class() {
+        // Calls of classes which aren't profiled don't add call stacks.
+        let call_trace = CallTrace {
+            class_hash: Some(ReplayClassHash {
+                block_number: BlockNumber::new(632_917),
+                class_hash: ClassHash(Felt::from(1_u64)),
+            }),
+            pcs: vec![0, 1, 2],
+            inner_calls: vec![CallTrace::default()],
+            ..CallTrace::default()
+        };
+        let mut folded_stacks = FoldedStacks::new();
+        let inner_call_stacks = folded_stacks.add_call(&call_trace, None, &HashMap::new());
+        assert!(folded_stacks.stacks.is_empty());
+        assert_eq!(inner_call_stacks, vec!["0x1::unknown".to_string()]);
+    }
+}
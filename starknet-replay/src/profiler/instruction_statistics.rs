@@ -0,0 +1,332 @@
+//! The module which provides the breakdown of the CASM instructions executed
+//! during the replay by instruction kind, hint type and memory-access pattern.
+//!
+//! Each visited program counter is a CASM step, therefore the statistics are
+//! computed from the CASM instruction at each visited program counter rather
+//! than from the Sierra statements.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `InstructionStatistics`
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use cairo_lang_casm::assembler::{InstructionRepr, Op1Addr, Res};
+use cairo_lang_casm::hints::{CoreHint, CoreHintBase, DeprecatedHint, Hint, StarknetHint};
+use cairo_lang_casm::instructions::{Instruction, InstructionBody};
+use cairo_lang_casm::operand::Register;
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::profiler::SierraProfiler;
+use crate::runner::pc_histogram::PcHistogram;
+
+/// The struct to hold the frequency of CASM instruction kinds, hint types and
+/// memory-access patterns.
+#[derive(Debug, Clone, Eq, PartialEq, Default, Serialize)]
+pub struct InstructionStatistics {
+    /// The number of executions of each CASM instruction kind (e.g.
+    /// `assert_eq`, `jnz`).
+    pub instructions: BTreeMap<String, usize>,
+
+    /// The number of executions of each hint type (e.g. `TestLessThan`).
+    pub hints: BTreeMap<String, usize>,
+
+    /// The number of executions of each memory-access pattern (e.g.
+    /// `dst=[ap] op0=[fp] op1=imm res=op1`).
+    pub memory_accesses: BTreeMap<String, usize>,
+}
+
+/// Returns the kind of the CASM instruction `instruction`.
+///
+/// # Arguments
+///
+/// - `instruction`: The CASM instruction.
+fn instruction_kind(instruction: &Instruction) -> &'static str {
+    match instruction.body {
+        InstructionBody::AddAp(_) => "add_ap",
+        InstructionBody::AssertEq(_) => "assert_eq",
+        InstructionBody::Call(_) => "call",
+        InstructionBody::Jnz(_) => "jnz",
+        InstructionBody::Jump(_) => "jmp",
+        InstructionBody::Ret(_) => "ret",
+    }
+}
+
+/// Returns the type of the hint `hint`.
+///
+/// The type is the name of the variant of the core, deprecated or Starknet
+/// hint, e.g. `TestLessThan` for `Core(Core(TestLessThan { .. }))`.
+///
+/// # Arguments
+///
+/// - `hint`: The hint.
+fn hint_type(hint: &Hint) -> &'static str {
+    match hint {
+        Hint::Core(CoreHintBase::Core(hint)) => core_hint_type(hint),
+        Hint::Core(CoreHintBase::Deprecated(hint)) => deprecated_hint_type(hint),
+        Hint::Starknet(StarknetHint::SystemCall { .. }) => "SystemCall",
+        Hint::Starknet(StarknetHint::Cheatcode { .. }) => "Cheatcode",
+    }
+}
+
+/// Returns the name of the variant of the core hint `hint`.
+///
+/// # Arguments
+///
+/// - `hint`: The core hint.
+fn core_hint_type(hint: &CoreHint) -> &'static str {
+    match hint {
+        CoreHint::AllocSegment { .. } => "AllocSegment",
+        CoreHint::TestLessThan { .. } => "TestLessThan",
+        CoreHint::TestLessThanOrEqual { .. } => "TestLessThanOrEqual",
+        CoreHint::TestLessThanOrEqualAddress { .. } => "TestLessThanOrEqualAddress",
+        CoreHint::WideMul128 { .. } => "WideMul128",
+        CoreHint::DivMod { .. } => "DivMod",
+        CoreHint::Uint256DivMod { .. } => "Uint256DivMod",
+        CoreHint::Uint512DivModByUint256 { .. } => "Uint512DivModByUint256",
+        CoreHint::SquareRoot { .. } => "SquareRoot",
+        CoreHint::Uint256SquareRoot { .. } => "Uint256SquareRoot",
+        CoreHint::LinearSplit { .. } => "LinearSplit",
+        CoreHint::AllocFelt252Dict { .. } => "AllocFelt252Dict",
+        CoreHint::Felt252DictEntryInit { .. } => "Felt252DictEntryInit",
+        CoreHint::Felt252DictEntryUpdate { .. } => "Felt252DictEntryUpdate",
+        CoreHint::GetSegmentArenaIndex { .. } => "GetSegmentArenaIndex",
+        CoreHint::InitSquashData { .. } => "InitSquashData",
+        CoreHint::GetCurrentAccessIndex { .. } => "GetCurrentAccessIndex",
+        CoreHint::ShouldSkipSquashLoop { .. } => "ShouldSkipSquashLoop",
+        CoreHint::GetCurrentAccessDelta { .. } => "GetCurrentAccessDelta",
+        CoreHint::ShouldContinueSquashLoop { .. } => "ShouldContinueSquashLoop",
+        CoreHint::GetNextDictKey { .. } => "GetNextDictKey",
+        CoreHint::AssertLeFindSmallArcs { .. } => "AssertLeFindSmallArcs",
+        CoreHint::AssertLeIsFirstArcExcluded { .. } => "AssertLeIsFirstArcExcluded",
+        CoreHint::AssertLeIsSecondArcExcluded { .. } => "AssertLeIsSecondArcExcluded",
+        CoreHint::RandomEcPoint { .. } => "RandomEcPoint",
+        CoreHint::FieldSqrt { .. } => "FieldSqrt",
+        CoreHint::DebugPrint { .. } => "DebugPrint",
+        CoreHint::AllocConstantSize { .. } => "AllocConstantSize",
+        CoreHint::U256InvModN { .. } => "U256InvModN",
+        CoreHint::EvalCircuit { .. } => "EvalCircuit",
+    }
+}
+
+/// Returns the name of the variant of the deprecated hint `hint`.
+///
+/// # Arguments
+///
+/// - `hint`: The deprecated hint.
+fn deprecated_hint_type(hint: &DeprecatedHint) -> &'static str {
+    match hint {
+        DeprecatedHint::AssertCurrentAccessIndicesIsEmpty => "AssertCurrentAccessIndicesIsEmpty",
+        DeprecatedHint::AssertAllAccessesUsed { .. } => "AssertAllAccessesUsed",
+        DeprecatedHint::AssertAllKeysUsed => "AssertAllKeysUsed",
+        DeprecatedHint::AssertLeAssertThirdArcExcluded => "AssertLeAssertThirdArcExcluded",
+        DeprecatedHint::AssertLtAssertValidInput { .. } => "AssertLtAssertValidInput",
+        DeprecatedHint::Felt252DictRead { .. } => "Felt252DictRead",
+        DeprecatedHint::Felt252DictWrite { .. } => "Felt252DictWrite",
+    }
+}
+
+/// Returns the memory-access pattern of the encoded instruction `memory`.
+///
+/// The pattern reports the register used to address the destination and the
+/// first operand, the source of the second operand and how the result is
+/// computed.
+///
+/// # Arguments
+///
+/// - `memory`: The encoded CASM instruction.
+fn memory_access_pattern(memory: &InstructionRepr) -> String {
+    let register = |register: &Register| match register {
+        Register::AP => "[ap]",
+        Register::FP => "[fp]",
+    };
+    let op1 = match memory.op1_addr {
+        Op1Addr::Imm => "imm",
+        Op1Addr::AP => "[ap]",
+        Op1Addr::FP => "[fp]",
+        Op1Addr::Op0 => "[op0]",
+    };
+    let res = match memory.res {
+        Res::Op1 => "op1",
+        Res::Add => "add",
+        Res::Mul => "mul",
+        Res::Unconstrained => "unconstrained",
+    };
+    format!(
+        "dst={} op0={} op1={op1} res={res}",
+        register(&memory.dst_register),
+        register(&memory.op0_register)
+    )
+}
+
+impl InstructionStatistics {
+    /// Initialisation of [`InstructionStatistics`].
+    ///
+    /// The struct is initialised with all fields empty.
+    #[must_use]
+    pub fn new() -> Self {
+        InstructionStatistics {
+            instructions: BTreeMap::default(),
+            hints: BTreeMap::default(),
+            memory_accesses: BTreeMap::default(),
+        }
+    }
+
+    /// Adds the CASM instructions executed at the program counters in `pcs`.
+    ///
+    /// Program counters which aren't the beginning of an instruction of the
+    /// program in `runner` are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// - `runner`: The Sierra profiler of the contract.
+    /// - `pcs`: The histogram of program counters visited in the contract.
+    pub fn add_pcs(&mut self, runner: &SierraProfiler, pcs: &PcHistogram) {
+        for (pc, frequency) in pcs {
+            let Some(instruction) = runner.instruction_at_pc(*pc) else {
+                continue;
+            };
+            *self
+                .instructions
+                .entry(instruction_kind(instruction).to_string())
+                .or_default() += frequency;
+            for hint in &instruction.hints {
+                *self.hints.entry(hint_type(hint).to_string()).or_default() += frequency;
+            }
+            let pattern = memory_access_pattern(&instruction.assemble());
+            *self.memory_accesses.entry(pattern).or_default() += frequency;
+        }
+    }
+
+    /// Update the object with data in `from`.
+    ///
+    /// # Arguments
+    ///
+    /// - `from`: Input `InstructionStatistics` to get data from.
+    pub fn merge(&mut self, from: &InstructionStatistics) {
+        for (name, frequency) in &from.instructions {
+            *self.instructions.entry(name.clone()).or_default() += frequency;
+        }
+        for (name, frequency) in &from.hints {
+            *self.hints.entry(name.clone()).or_default() += frequency;
+        }
+        for (name, frequency) in &from.memory_accesses {
+            *self.memory_accesses.entry(name.clone()).or_default() += frequency;
+        }
+    }
+
+    /// Returns the number of CASM steps, which is the number of executed
+    /// instructions.
+    #[must_use]
+    pub fn steps(&self) -> usize {
+        self.instructions.values().sum()
+    }
+
+    /// Serialises [`InstructionStatistics`] to CSV format.
+    ///
+    /// Instruction kinds, hint types and memory-access patterns are reported in
+    /// this order. Each category is sorted in descending order of frequency.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str;
+    /// # use indoc::indoc;
+    /// # use starknet_replay::profiler::instruction_statistics::InstructionStatistics;
+    /// let mut instruction_statistics = InstructionStatistics::new();
+    /// instruction_statistics
+    ///     .instructions
+    ///     .insert("ret".to_string(), 1);
+    /// instruction_statistics
+    ///     .instructions
+    ///     .insert("assert_eq".to_string(), 2);
+    /// instruction_statistics
+    ///     .hints
+    ///     .insert("TestLessThan".to_string(), 1);
+    /// let expected_string = indoc! {r#"
+    ///     Category,Name,Frequency
+    ///     instruction,"assert_eq",2
+    ///     instruction,"ret",1
+    ///     hint,"TestLessThan",1
+    /// "#};
+    /// let csv_output = instruction_statistics.to_csv_bytes().unwrap();
+    /// assert_eq!(
+    ///     str::from_utf8(csv_output.as_slice()).unwrap(),
+    ///     expected_string
+    /// );
+    /// ```
+    pub fn to_csv_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut f = Vec::new();
+        writeln!(f, "Category,Name,Frequency")?;
+        for (category, frequencies) in [
+            ("instruction", &self.instructions),
+            ("hint", &self.hints),
+            ("memory_access", &self.memory_accesses),
+        ] {
+            for (name, frequency) in frequencies
+                .iter()
+                .sorted_by(|a, b| Ord::cmp(&b.1, &a.1).then(Ord::cmp(a.0, b.0)))
+            {
+                writeln!(f, "{category},\"{name}\",{frequency}")?;
+            }
+        }
+        Ok(f)
+    }
+
+    /// Serialises [`InstructionStatistics`] to JSON format.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an error serialising the object.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cairo_lang_casm::operand::{CellRef, ResOperand};
+
+    use super::*;
+
+    #[test]
+    fn test_hint_type() {
+        let dst = CellRef {
+            register: Register::AP,
+            offset: 0,
+        };
+        let alloc_segment = Hint::Core(CoreHintBase::Core(CoreHint::AllocSegment { dst }));
+        assert_eq!(hint_type(&alloc_segment), "AllocSegment");
+        let assert_all_keys_used =
+            Hint::Core(CoreHintBase::Deprecated(DeprecatedHint::AssertAllKeysUsed));
+        assert_eq!(hint_type(&assert_all_keys_used), "AssertAllKeysUsed");
+        let system_call = Hint::Starknet(StarknetHint::SystemCall {
+            system: ResOperand::Deref(dst),
+        });
+        assert_eq!(hint_type(&system_call), "SystemCall");
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut first = InstructionStatistics::new();
+        first.instructions.insert("assert_eq".to_string(), 2);
+        first.hints.insert("AllocSegment".to_string(), 1);
+        let mut second = InstructionStatistics::new();
+        second.instructions.insert("assert_eq".to_string(), 1);
+        second.instructions.insert("ret".to_string(), 1);
+
+        first.merge(&second);
+
+        assert_eq!(first.instructions["assert_eq"], 3);
+        assert_eq!(first.steps(), 4);
+        assert_eq!(first.hints["AllocSegment"], 1);
+
+        let json: serde_json::Value = serde_json::from_str(&first.to_json().unwrap()).unwrap();
+        assert_eq!(json["instructions"]["ret"], 1);
+        assert_eq!(json["hints"]["AllocSegment"], 1);
+    }
+}
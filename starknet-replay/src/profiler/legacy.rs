@@ -0,0 +1,350 @@
+//! The module [`crate::profiler::legacy`] contains the code to profile the
+//! calls of legacy Cairo 0 contracts.
+//!
+//! Cairo 0 contracts don't have a Sierra program, therefore the visited program
+//! counters are mapped directly to the CASM instructions of the program and to
+//! the Cairo 0 functions which contain them. The function of each instruction
+//! is taken from the debug info of the program if available. Otherwise, it's
+//! the function with the closest entry point before the instruction, as
+//! declared in the identifiers of the program.
+//!
+//! [`blockifier`] runs Cairo 0 contracts without tracing, therefore their
+//! visited program counters aren't recorded. The steps of a call without
+//! visited program counters are taken from its execution resources and they
+//! are attributed to the function of the entry point called.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `LegacyProfiler`
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use flate2::bufread;
+use starknet_core::types::{CompressedLegacyContractClass, Felt};
+
+use crate::error::ProfilerError;
+use crate::profiler::entry_point_statistics::UNKNOWN_ENTRY_POINT;
+use crate::profiler::legacy_statistics::LegacyFunctionSteps;
+use crate::runner::call_trace::CallTrace;
+use crate::runner::pc_histogram::PcHistogram;
+
+/// The profiler of a legacy Cairo 0 program which maps program counters to
+/// CASM instructions, hints and functions.
+pub struct LegacyProfiler {
+    /// The number of memory cells of the program bytecode.
+    bytecode_length: usize,
+
+    /// The entry point and the name of each function of the program sorted by
+    /// entry point.
+    functions: Vec<(usize, String)>,
+
+    /// The map between a program counter and the name of the function which
+    /// contains it, as reported in the debug info.
+    scopes: HashMap<usize, String>,
+
+    /// The map between a program counter and the number of hints executed
+    /// before the instruction at that program counter.
+    hints: HashMap<usize, usize>,
+
+    /// The map between the selector of an entry point of the contract and its
+    /// offset in the program.
+    entry_points: HashMap<Felt, usize>,
+}
+impl LegacyProfiler {
+    /// Generates a new [`LegacyProfiler`] object from a compressed legacy
+    /// contract class.
+    ///
+    /// # Arguments
+    ///
+    /// - `contract_class`: The legacy contract class as returned by the RPC
+    ///   endpoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the program can't be decompressed or deserialised.
+    pub fn new(contract_class: &CompressedLegacyContractClass) -> Result<Self, ProfilerError> {
+        let mut gz = bufread::GzDecoder::new(&contract_class.program[..]);
+        let mut decoded_program = String::new();
+        gz.read_to_string(&mut decoded_program).map_err(|_| {
+            ProfilerError::Unknown("Error decompressing legacy program".to_string())
+        })?;
+        let program: serde_json::Value = serde_json::from_str(&decoded_program)?;
+
+        let entry_points_by_type = &contract_class.entry_points_by_type;
+        let entry_points = entry_points_by_type
+            .constructor
+            .iter()
+            .chain(&entry_points_by_type.external)
+            .chain(&entry_points_by_type.l1_handler)
+            .filter_map(|entry_point| {
+                Some((
+                    entry_point.selector,
+                    usize::try_from(entry_point.offset).ok()?,
+                ))
+            })
+            .collect();
+        Ok(Self::from_program(&program, entry_points))
+    }
+
+    /// Generates a new [`LegacyProfiler`] object from the JSON of a Cairo 0
+    /// program.
+    ///
+    /// Missing sections of the program are treated as empty.
+    ///
+    /// # Arguments
+    ///
+    /// - `program`: The Cairo 0 program.
+    /// - `entry_points`: The offset in the program of the entry point of each
+    ///   selector.
+    #[must_use]
+    pub fn from_program(program: &serde_json::Value, entry_points: HashMap<Felt, usize>) -> Self {
+        let bytecode_length = program["data"].as_array().map_or(0, Vec::len);
+
+        let mut functions: Vec<(usize, String)> = program["identifiers"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, identifier)| identifier["type"] == "function")
+            .filter_map(|(name, identifier)| {
+                let pc = usize::try_from(identifier["pc"].as_u64()?).ok()?;
+                Some((pc, name.clone()))
+            })
+            .collect();
+        functions.sort_unstable();
+
+        let scopes = program["debug_info"]["instruction_locations"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(pc, location)| {
+                let pc = pc.parse::<usize>().ok()?;
+                let scope = location["accessible_scopes"].as_array()?.last()?.as_str()?;
+                Some((pc, scope.to_string()))
+            })
+            .collect();
+
+        let hints = program["hints"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(pc, hints)| {
+                let pc = pc.parse::<usize>().ok()?;
+                Some((pc, hints.as_array()?.len()))
+            })
+            .collect();
+
+        LegacyProfiler {
+            bytecode_length,
+            functions,
+            scopes,
+            hints,
+            entry_points,
+        }
+    }
+
+    /// Returns the name of the function which contains the instruction at
+    /// `pc`.
+    ///
+    /// Returns [`None`] if `pc` is outside of the program or before the first
+    /// function.
+    ///
+    /// # Arguments
+    ///
+    /// - `pc`: The program counter to query.
+    #[must_use]
+    pub fn function_at_pc(&self, pc: usize) -> Option<&str> {
+        if pc >= self.bytecode_length {
+            return None;
+        }
+        if let Some(scope) = self.scopes.get(&pc) {
+            return Some(scope);
+        }
+        let idx = self
+            .functions
+            .partition_point(|(entry_point, _)| *entry_point <= pc);
+        let (_, name) = self.functions.get(idx.checked_sub(1)?)?;
+        Some(name)
+    }
+
+    /// Returns the number of hints executed before the instruction at `pc`.
+    ///
+    /// # Arguments
+    ///
+    /// - `pc`: The program counter to query.
+    #[must_use]
+    pub fn hints_at_pc(&self, pc: usize) -> usize {
+        self.hints.get(&pc).copied().unwrap_or_default()
+    }
+
+    /// Returns the name of the function of the entry point with `selector`.
+    ///
+    /// Returns [`None`] if the contract doesn't have an entry point with
+    /// `selector` or if its offset is outside of any function.
+    ///
+    /// # Arguments
+    ///
+    /// - `selector`: The selector of the entry point.
+    #[must_use]
+    pub fn entry_point_function(&self, selector: &Felt) -> Option<&str> {
+        self.function_at_pc(*self.entry_points.get(selector)?)
+    }
+
+    /// Returns the number of steps and hints executed in each function.
+    ///
+    /// Each visit of a program counter is a CASM step. Program counters outside
+    /// of the program are skipped and program counters outside of any function
+    /// are reported as [`UNKNOWN_ENTRY_POINT`].
+    ///
+    /// # Arguments
+    ///
+    /// - `pcs`: The histogram of program counters to analyse.
+    #[must_use]
+    pub fn collect_function_steps(
+        &self,
+        pcs: &PcHistogram,
+    ) -> HashMap<String, LegacyFunctionSteps> {
+        let mut function_steps: HashMap<String, LegacyFunctionSteps> = HashMap::new();
+        for (pc, frequency) in pcs {
+            if *pc >= self.bytecode_length {
+                continue;
+            }
+            let function = self.function_at_pc(*pc).unwrap_or(UNKNOWN_ENTRY_POINT);
+            let steps = function_steps.entry(function.to_string()).or_default();
+            steps.steps += frequency;
+            steps.hints += frequency * self.hints_at_pc(*pc);
+        }
+        function_steps
+    }
+
+    /// Returns the number of calls, steps and hints executed in each function
+    /// by `call_trace`, excluding its inner calls.
+    ///
+    /// The call is counted in the function of the entry point called. If the
+    /// program counters of the call are recorded, they are mapped to functions
+    /// with [`LegacyProfiler::collect_function_steps`]. Otherwise, the steps
+    /// of the call are attributed to the function of the entry point and the
+    /// hints aren't counted.
+    ///
+    /// # Arguments
+    ///
+    /// - `call_trace`: The call to analyse.
+    #[must_use]
+    pub fn collect_call_steps(
+        &self,
+        call_trace: &CallTrace,
+    ) -> HashMap<String, LegacyFunctionSteps> {
+        let entry_point = self
+            .entry_point_function(&call_trace.selector)
+            .unwrap_or(UNKNOWN_ENTRY_POINT)
+            .to_string();
+        let mut function_steps = if call_trace.pcs.is_empty() {
+            HashMap::from([(
+                entry_point.clone(),
+                LegacyFunctionSteps {
+                    steps: call_trace.n_steps,
+                    ..LegacyFunctionSteps::default()
+                },
+            )])
+        } else {
+            let pcs: PcHistogram = call_trace.pcs.iter().copied().collect();
+            self.collect_function_steps(&pcs)
+        };
+        function_steps.entry(entry_point).or_default().calls += 1;
+        function_steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_function_steps() {
+        let program = serde_json::json!({
+            "data": ["0x1", "0x2", "0x3", "0x4", "0x5", "0x6"],
+            "identifiers": {
+                "__main__.transfer": { "type": "function", "pc": 0 },
+                "__main__.transfer.Args": { "type": "struct", "size": 2 },
+                "starkware.cairo.common.uint256.uint256_add": { "type": "function", "pc": 3 },
+            },
+            "hints": {
+                "3": [{ "code": "memory[ap] = 0" }],
+            },
+            "debug_info": {
+                "instruction_locations": {
+                    "5": { "accessible_scopes": ["__main__", "__main__.__wrappers__"] },
+                },
+            },
+        });
+        let profiler = LegacyProfiler::from_program(&program, HashMap::new());
+
+        assert_eq!(profiler.function_at_pc(2), Some("__main__.transfer"));
+        assert_eq!(
+            profiler.function_at_pc(4),
+            Some("starkware.cairo.common.uint256.uint256_add")
+        );
+        assert_eq!(profiler.function_at_pc(5), Some("__main__.__wrappers__"));
+        assert_eq!(profiler.function_at_pc(6), None);
+
+        let pcs: PcHistogram = [0, 1, 3, 4, 3, 4, 5, 100].into_iter().collect();
+        let function_steps = profiler.collect_function_steps(&pcs);
+
+        assert_eq!(function_steps.len(), 3);
+        assert_eq!(function_steps["__main__.transfer"].steps, 2);
+        let uint256_add = &function_steps["starkware.cairo.common.uint256.uint256_add"];
+        assert_eq!(uint256_add.steps, 4);
+        assert_eq!(uint256_add.hints, 2);
+        assert_eq!(function_steps["__main__.__wrappers__"].steps, 1);
+    }
+
+    #[test]
+    fn test_collect_call_steps() {
+        let program = serde_json::json!({
+            "data": ["0x1", "0x2", "0x3", "0x4", "0x5", "0x6"],
+            "identifiers": {
+                "__main__.transfer": { "type": "function", "pc": 0 },
+                "__wrappers__.transfer": { "type": "function", "pc": 4 },
+            },
+        });
+        let transfer = Felt::from(10_u64);
+        let profiler = LegacyProfiler::from_program(&program, HashMap::from([(transfer, 4)]));
+
+        // Without visited program counters, the steps of the call are
+        // attributed to the function of the entry point.
+        let call_trace = CallTrace {
+            selector: transfer,
+            n_steps: 120,
+            ..CallTrace::default()
+        };
+        let function_steps = profiler.collect_call_steps(&call_trace);
+        assert_eq!(
+            function_steps["__wrappers__.transfer"],
+            LegacyFunctionSteps {
+                calls: 1,
+                steps: 120,
+                hints: 0
+            }
+        );
+
+        // Visited program counters are mapped to the functions which contain
+        // them.
+        let call_trace = CallTrace {
+            selector: transfer,
+            pcs: vec![4, 5, 0, 1],
+            n_steps: 4,
+            ..CallTrace::default()
+        };
+        let function_steps = profiler.collect_call_steps(&call_trace);
+        assert_eq!(function_steps["__wrappers__.transfer"].calls, 1);
+        assert_eq!(function_steps["__wrappers__.transfer"].steps, 2);
+        assert_eq!(function_steps["__main__.transfer"].steps, 2);
+
+        // Unknown selectors are reported as unknown entry points.
+        let call_trace = CallTrace {
+            selector: Felt::from(11_u64),
+            n_steps: 5,
+            ..CallTrace::default()
+        };
+        let function_steps = profiler.collect_call_steps(&call_trace);
+        assert_eq!(function_steps[UNKNOWN_ENTRY_POINT].steps, 5);
+    }
+}
@@ -0,0 +1,243 @@
+//! The module which provides the breakdown of the CASM steps executed in each
+//! function of legacy Cairo 0 contract classes.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `LegacyStatistics`
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use itertools::Itertools;
+use serde::Serialize;
+use starknet_api::core::ClassHash;
+
+/// The number of calls, CASM steps and hints executed in a Cairo 0 function.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Serialize)]
+pub struct LegacyFunctionSteps {
+    /// The number of calls to the contract which entered the function as entry
+    /// point.
+    pub calls: usize,
+
+    /// The number of CASM steps executed.
+    pub steps: usize,
+
+    /// The number of hints executed. Hints are counted only if the visited
+    /// program counters of the call are recorded.
+    pub hints: usize,
+}
+impl LegacyFunctionSteps {
+    /// Adds the calls, steps and hints in `other` to `self`.
+    ///
+    /// # Arguments
+    ///
+    /// - `other`: The calls, steps and hints to add.
+    pub fn merge(&mut self, other: &LegacyFunctionSteps) {
+        self.calls += other.calls;
+        self.steps += other.steps;
+        self.hints += other.hints;
+    }
+}
+
+/// The struct to hold the CASM steps executed in each function of each legacy
+/// contract class.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct LegacyStatistics {
+    /// This field contains the association between the pair of class hash and
+    /// function name (key) and the steps executed in the function (value).
+    pub functions: BTreeMap<(ClassHash, String), LegacyFunctionSteps>,
+}
+
+/// The steps of a function in the JSON report.
+#[derive(Debug, Serialize)]
+struct LegacyFunctionReport<'a> {
+    /// The class hash of the contract.
+    class_hash: ClassHash,
+
+    /// The name of the function.
+    function: &'a str,
+
+    /// The calls, steps and hints executed in the function.
+    #[serde(flatten)]
+    steps: LegacyFunctionSteps,
+}
+
+impl LegacyStatistics {
+    /// Initialisation of [`LegacyStatistics`].
+    ///
+    /// The struct is initialised with field `functions` empty.
+    #[must_use]
+    pub fn new() -> Self {
+        LegacyStatistics {
+            functions: BTreeMap::default(),
+        }
+    }
+
+    /// Adds the steps executed in a function.
+    ///
+    /// If the function already exists, the steps are summed.
+    ///
+    /// # Arguments
+    ///
+    /// - `class_hash`: The class hash of the contract.
+    /// - `function`: The name of the function.
+    /// - `steps`: The calls, steps and hints executed in the function.
+    pub fn add(&mut self, class_hash: ClassHash, function: &str, steps: &LegacyFunctionSteps) {
+        self.functions
+            .entry((class_hash, function.to_string()))
+            .or_default()
+            .merge(steps);
+    }
+
+    /// Update the object with data in `from`.
+    ///
+    /// # Arguments
+    ///
+    /// - `from`: Input `LegacyStatistics` to get data from.
+    pub fn merge(&mut self, from: &LegacyStatistics) {
+        for ((class_hash, function), steps) in &from.functions {
+            self.add(*class_hash, function, steps);
+        }
+    }
+
+    /// Returns the sum of the calls, steps and hints of all functions.
+    #[must_use]
+    pub fn total(&self) -> LegacyFunctionSteps {
+        let mut total = LegacyFunctionSteps::default();
+        for steps in self.functions.values() {
+            total.merge(steps);
+        }
+        total
+    }
+
+    /// Returns the functions sorted by descending number of steps.
+    fn functions_by_steps(&self) -> Vec<(&ClassHash, &str, &LegacyFunctionSteps)> {
+        self.functions
+            .iter()
+            .map(|((class_hash, function), steps)| (class_hash, function.as_str(), steps))
+            .sorted_by(|a, b| {
+                Ord::cmp(&b.2.steps, &a.2.steps)
+                    .then(Ord::cmp(a.0, b.0))
+                    .then(Ord::cmp(a.1, b.1))
+            })
+            .collect()
+    }
+
+    /// Serialises [`LegacyStatistics`] to CSV format.
+    ///
+    /// Functions are reported in descending order of steps.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str;
+    /// # use indoc::indoc;
+    /// # use starknet_api::core::ClassHash;
+    /// # use starknet_core::types::Felt;
+    /// # use starknet_replay::profiler::legacy_statistics::{LegacyFunctionSteps, LegacyStatistics};
+    /// let mut legacy_statistics = LegacyStatistics::new();
+    /// let transfer = LegacyFunctionSteps {
+    ///     calls: 1,
+    ///     steps: 12,
+    ///     hints: 1,
+    /// };
+    /// let uint256_add = LegacyFunctionSteps {
+    ///     calls: 0,
+    ///     steps: 30,
+    ///     hints: 2,
+    /// };
+    /// legacy_statistics.add(ClassHash(Felt::from(1_u64)), "__main__.transfer", &transfer);
+    /// legacy_statistics.add(
+    ///     ClassHash(Felt::from(1_u64)),
+    ///     "starkware.cairo.common.uint256.uint256_add",
+    ///     &uint256_add,
+    /// );
+    /// let expected_string = indoc! {r#"
+    ///     Class Hash,Function Name,Calls,Steps,Hints
+    ///     0x1,"starkware.cairo.common.uint256.uint256_add",0,30,2
+    ///     0x1,"__main__.transfer",1,12,1
+    /// "#};
+    /// let csv_output = legacy_statistics.to_csv_bytes().unwrap();
+    /// assert_eq!(
+    ///     str::from_utf8(csv_output.as_slice()).unwrap(),
+    ///     expected_string
+    /// );
+    /// ```
+    pub fn to_csv_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut f = Vec::new();
+        writeln!(f, "Class Hash,Function Name,Calls,Steps,Hints")?;
+        for (class_hash, function, steps) in self.functions_by_steps() {
+            writeln!(
+                f,
+                "{:#x},\"{function}\",{},{},{}",
+                class_hash.0, steps.calls, steps.steps, steps.hints
+            )?;
+        }
+        Ok(f)
+    }
+
+    /// Serialises [`LegacyStatistics`] to JSON format.
+    ///
+    /// Functions are reported in descending order of steps.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an error serialising the object.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        let report: Vec<LegacyFunctionReport> = self
+            .functions_by_steps()
+            .into_iter()
+            .map(|(class_hash, function, steps)| LegacyFunctionReport {
+                class_hash: *class_hash,
+                function,
+                steps: *steps,
+            })
+            .collect();
+        serde_json::to_string_pretty(&report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_core::types::Felt;
+
+    use super::*;
+
+    #[test]
+    fn test_legacy_statistics() {
+        let class_hash = ClassHash(Felt::from(1_u64));
+        let other_class_hash = ClassHash(Felt::from(2_u64));
+        let steps = LegacyFunctionSteps {
+            calls: 1,
+            steps: 5,
+            hints: 1,
+        };
+
+        let mut legacy_statistics = LegacyStatistics::new();
+        legacy_statistics.add(class_hash, "__main__.transfer", &steps);
+        legacy_statistics.add(other_class_hash, "__main__.transfer", &steps);
+
+        let mut other = LegacyStatistics::new();
+        other.add(class_hash, "__main__.transfer", &steps);
+        legacy_statistics.merge(&other);
+
+        assert_eq!(
+            legacy_statistics.functions[&(class_hash, "__main__.transfer".to_string())],
+            LegacyFunctionSteps {
+                calls: 2,
+                steps: 10,
+                hints: 2
+            }
+        );
+        assert_eq!(legacy_statistics.total().steps, 15);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&legacy_statistics.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["function"], "__main__.transfer");
+        assert_eq!(json[0]["calls"], 2);
+        assert_eq!(json[0]["steps"], 10);
+        assert_eq!(json[1]["hints"], 1);
+    }
+}
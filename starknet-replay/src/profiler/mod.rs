@@ -5,14 +5,34 @@
 
 #![allow(clippy::module_name_repetitions)] // Added because of `SierraProfiler`
 
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
 
 use cairo_lang_casm::assembler::InstructionRepr;
 use cairo_lang_casm::instructions::Instruction;
-use cairo_lang_runner::RunnerError as CairoError;
+use cairo_lang_runner::{token_gas_cost, RunnerError as CairoError};
+use cairo_lang_sierra::extensions::circuit::{CircuitInfo, CircuitTypeConcrete};
+use cairo_lang_sierra::extensions::core::{
+    CoreConcreteLibfunc,
+    CoreLibfunc,
+    CoreType,
+    CoreTypeConcrete,
+};
+use cairo_lang_sierra::extensions::gas::CostTokenType;
+use cairo_lang_sierra::extensions::lib_func::SignatureAndFunctionConcreteLibfunc;
+use cairo_lang_sierra::extensions::ConcreteLibfunc;
+use cairo_lang_sierra::ids::{ConcreteLibfuncId, ConcreteTypeId, GenericLibfuncId};
 use cairo_lang_sierra::program::{GenStatement, Program, Statement, StatementIdx};
-use cairo_lang_sierra_to_casm::compiler::{compile, CairoProgram, SierraToCasmConfig};
+use cairo_lang_sierra::program_registry::ProgramRegistry;
+use cairo_lang_sierra_gas::core_libfunc_cost::{core_libfunc_cost, InvocationCostInfoProvider};
+use cairo_lang_sierra_to_casm::circuit::CircuitsInfo;
+use cairo_lang_sierra_to_casm::compiler::{
+    compile,
+    CairoProgram,
+    CompilationError,
+    SierraToCasmConfig,
+};
 use cairo_lang_sierra_to_casm::metadata::{
     calc_metadata,
     calc_metadata_ap_change_only,
@@ -20,15 +40,35 @@ use cairo_lang_sierra_to_casm::metadata::{
     MetadataComputationConfig,
     MetadataError,
 };
-use itertools::Itertools;
+use cairo_lang_sierra_type_size::{get_type_size_map, TypeSizeMap};
+use cairo_lang_utils::bigint::BigUintAsHex;
+use cairo_lang_utils::unordered_hash_map::UnorderedHashMap;
+use starknet_core::types::Felt;
 use tracing::trace;
 
 use crate::error::ProfilerError;
+use crate::profiler::replay_statistics::LibfuncCost;
+use crate::runner::pc_histogram::PcHistogram;
 
 pub mod analysis;
+pub mod class_cache;
+pub mod contract_statistics;
+pub mod coverage;
+pub mod entry_point_statistics;
+pub mod flamegraph;
+pub mod instruction_statistics;
+pub mod legacy;
+pub mod legacy_statistics;
+pub mod pprof;
 pub mod replace_ids;
 pub mod replay_statistics;
 pub mod report;
+pub mod results_database;
+pub mod run_metadata;
+pub mod skipped_classes;
+pub mod statistics_diff;
+pub mod time_series_statistics;
+pub mod version_statistics;
 
 /// This structure contains the mapping between the Sierra statement, CASM
 /// instruction and memory opcode.
@@ -53,6 +93,19 @@ pub struct CompiledStatement {
     /// The CASM instruction index (starting from 1)
     instruction_idx: usize,
 }
+impl CompiledStatement {
+    /// Returns the program counter at the beginning of the memory content.
+    #[must_use]
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Returns the index of the Sierra statement.
+    #[must_use]
+    pub fn statement_idx(&self) -> usize {
+        self.statement_idx
+    }
+}
 impl Display for CompiledStatement {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(
@@ -68,6 +121,68 @@ impl Display for CompiledStatement {
     }
 }
 
+/// The information required by [`core_libfunc_cost`] to compute the cost of
+/// the invocation of a libfunc at a Sierra statement.
+///
+/// It mirrors the implementation of [`InvocationCostInfoProvider`] used by
+/// [`compile`] to validate the gas usage of the program.
+struct InvocationCostInfo<'a> {
+    /// The metadata of the program.
+    metadata: &'a Metadata,
+
+    /// The sizes of the types of the program.
+    type_sizes: &'a TypeSizeMap,
+
+    /// The information about the circuits of the program.
+    circuits_info: &'a CircuitsInfo,
+
+    /// The index of the statement invoking the libfunc.
+    statement_idx: StatementIdx,
+
+    /// The info returned for circuit types missing from `circuits_info`.
+    empty_circuit_info: &'a CircuitInfo,
+
+    /// The first circuit type requested which is missing from
+    /// `circuits_info`.
+    missing_circuit: RefCell<Option<ConcreteTypeId>>,
+}
+impl InvocationCostInfoProvider for InvocationCostInfo<'_> {
+    fn type_size(&self, ty: &ConcreteTypeId) -> usize {
+        self.type_sizes
+            .get(ty)
+            .map_or(0, |size| usize::try_from(*size).unwrap_or_default())
+    }
+
+    fn token_usages(&self, token_type: CostTokenType) -> usize {
+        self.metadata
+            .gas_info
+            .variable_values
+            .get(&(self.statement_idx, token_type))
+            .map_or(0, |usages| usize::try_from(*usages).unwrap_or_default())
+    }
+
+    fn ap_change_var_value(&self) -> usize {
+        self.metadata
+            .ap_change_info
+            .variable_values
+            .get(&self.statement_idx)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn circuit_info(&self, ty: &ConcreteTypeId) -> &CircuitInfo {
+        // The trait requires a reference, therefore a missing circuit type is
+        // recorded and reported by `compute_statement_gas` once the cost is
+        // computed.
+        self.circuits_info.circuits.get(ty).unwrap_or_else(|| {
+            self.missing_circuit
+                .borrow_mut()
+                .get_or_insert_with(|| ty.clone());
+            self.empty_circuit_info
+        })
+    }
+}
+
 /// Creates the metadata required for a lowering a Sierra program to CASM.
 ///
 /// This function is copied from crate [`cairo_lang_runner`] because it
@@ -114,6 +229,29 @@ pub struct SierraProfiler {
     /// The vector containing the whole program with correspondance between
     /// Sierra, CASM and memory.
     pub commands: Vec<CompiledStatement>,
+
+    /// The map between a program counter and the indices of the elements of
+    /// `commands` starting at that program counter.
+    commands_by_pc: HashMap<usize, Vec<usize>>,
+
+    /// The map between a program counter and the index of the CASM instruction
+    /// starting at that program counter in `casm_program`.
+    instructions_by_pc: HashMap<usize, usize>,
+
+    /// The map between a concrete libfunc and its generic libfunc.
+    generic_libfuncs: HashMap<ConcreteLibfuncId, GenericLibfuncId>,
+
+    /// The map between a program counter and the index of the Sierra statement
+    /// which contains the CASM instruction starting at that program counter.
+    statements_by_pc: HashMap<usize, usize>,
+
+    /// The map between a concrete libfunc and the number of builtins it takes
+    /// as parameters. Libfuncs without builtin parameters are not included.
+    builtin_libfuncs: HashMap<ConcreteLibfuncId, usize>,
+
+    /// The map between the index of a Sierra statement invoking a libfunc and
+    /// the Sierra gas cost of the invocation.
+    statement_gas: HashMap<StatementIdx, usize>,
 }
 impl SierraProfiler {
     /// Generates a new [`SierraProfiler`] object.
@@ -131,11 +269,48 @@ impl SierraProfiler {
     ///
     /// - The call to `create_metadata` fails
     /// - The generation of `[sierra_program_registry`] fails
+    /// - The sizes of the types or the circuits of the program can't be
+    ///   computed
     pub fn new(sierra_program: Program, pc_offset: Option<usize>) -> Result<Self, ProfilerError> {
-        // `metadata_config` is set as per default values preventing the user from
-        // choosing `None` as in the original `SierraCasmRunner`. This is to
-        // ensure the profiler is always run with the same configuration.
-        let metadata_config = Some(MetadataComputationConfig::default());
+        Self::with_metadata_config(
+            sierra_program,
+            pc_offset,
+            MetadataComputationConfig::default(),
+        )
+    }
+
+    /// Generates a new [`SierraProfiler`] object compiling the Sierra program
+    /// with the metadata configuration `metadata_config`.
+    ///
+    /// The CASM bytecode depends on the metadata configuration, therefore the
+    /// program of a contract class must be compiled with the same
+    /// configuration used to compile the contract class executed.
+    ///
+    /// # Arguments
+    ///
+    /// - `sierra_program`: The sierra program considered in the runner.
+    /// - `pc_offset`: The initial PC value at the first program instruction.
+    ///   For Cairo programs this is not 0 because it depends on the length of
+    ///   the header. For Cairo contracts this is 0.
+    /// - `metadata_config`: The configuration of the metadata computation.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if:
+    ///
+    /// - The call to `create_metadata` fails
+    /// - The generation of `[sierra_program_registry`] fails
+    /// - The sizes of the types or the circuits of the program can't be
+    ///   computed
+    pub fn with_metadata_config(
+        sierra_program: Program,
+        pc_offset: Option<usize>,
+        metadata_config: MetadataComputationConfig,
+    ) -> Result<Self, ProfilerError> {
+        // `metadata_config` is always set, preventing `None` as in the original
+        // `SierraCasmRunner`. This is to ensure the gas usage is always
+        // computed and checked.
+        let metadata_config = Some(metadata_config);
         let gas_usage_check = metadata_config.is_some();
         let metadata = create_metadata(&sierra_program, metadata_config)?;
         let casm_program = compile(
@@ -147,22 +322,46 @@ impl SierraProfiler {
             },
         )?;
 
+        // Sierra statements grouped by the index of their first CASM instruction.
+        let mut statements_by_instruction: Vec<Vec<usize>> =
+            vec![Vec::new(); casm_program.instructions.len()];
+        for (statement_idx, statement_info) in casm_program
+            .debug_info
+            .sierra_statement_info
+            .iter()
+            .enumerate()
+        {
+            // Statements without CASM instructions point past the last instruction.
+            if let Some(statements) =
+                statements_by_instruction.get_mut(statement_info.instruction_idx)
+            {
+                statements.push(statement_idx);
+            }
+        }
+
         let mut commands: Vec<CompiledStatement> = Vec::new();
+        let mut commands_by_pc: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut instructions_by_pc: HashMap<usize, usize> = HashMap::new();
+        let mut statements_by_pc: HashMap<usize, usize> = HashMap::new();
+        // The statement containing the current instruction is the last statement
+        // starting at or before the instruction.
+        let mut current_statement: Option<usize> = None;
         let pc_offset = pc_offset.unwrap_or(0);
         let mut last_pc: usize = pc_offset;
 
         casm_program
             .instructions
             .iter()
+            .zip(statements_by_instruction)
             .enumerate()
-            .for_each(|(instruction_idx, instruction)| {
-                for (statement_idx, _) in casm_program
-                    .debug_info
-                    .sierra_statement_info
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, s)| s.instruction_idx == instruction_idx)
-                {
+            .for_each(|(instruction_idx, (instruction, statements))| {
+                if let Some(statement_idx) = statements.last() {
+                    current_statement = Some(*statement_idx);
+                }
+                if let Some(statement_idx) = current_statement {
+                    statements_by_pc.insert(last_pc, statement_idx);
+                }
+                for statement_idx in statements {
                     let sierra_statement = &sierra_program.statements[statement_idx];
                     let casm_instruction = instruction;
                     let command = CompiledStatement {
@@ -174,31 +373,237 @@ impl SierraProfiler {
                         instruction_idx: instruction_idx + pc_offset,
                     };
                     trace!("{}", command);
+                    commands_by_pc
+                        .entry(last_pc)
+                        .or_default()
+                        .push(commands.len());
                     commands.push(command);
                 }
+                instructions_by_pc.insert(last_pc, instruction_idx);
                 last_pc += instruction.assemble().encode().len();
             });
 
+        let generic_libfuncs = sierra_program
+            .libfunc_declarations
+            .iter()
+            .map(|declaration| {
+                (
+                    declaration.id.clone(),
+                    declaration.long_id.generic_id.clone(),
+                )
+            })
+            .collect();
+
+        let registry = ProgramRegistry::<CoreType, CoreLibfunc>::new(&sierra_program)?;
+        let builtin_libfuncs = Self::builtin_libfuncs(&sierra_program, &registry);
+        let statement_gas = Self::compute_statement_gas(&sierra_program, &registry, &metadata)?;
+
         Ok(Self {
             sierra_program,
             casm_program,
             commands,
+            commands_by_pc,
+            instructions_by_pc,
+            generic_libfuncs,
+            statements_by_pc,
+            builtin_libfuncs,
+            statement_gas,
         })
     }
 
+    /// Returns the map between each concrete libfunc and the number of
+    /// builtins it takes as parameters.
+    ///
+    /// User function calls are excluded because they only pass the builtins
+    /// to the function called.
+    ///
+    /// # Arguments
+    ///
+    /// - `sierra_program`: The sierra program.
+    /// - `registry`: The registry of the sierra program.
+    fn builtin_libfuncs(
+        sierra_program: &Program,
+        registry: &ProgramRegistry<CoreType, CoreLibfunc>,
+    ) -> HashMap<ConcreteLibfuncId, usize> {
+        sierra_program
+            .libfunc_declarations
+            .iter()
+            .filter(|declaration| declaration.long_id.generic_id.0 != "function_call")
+            .filter_map(|declaration| {
+                let libfunc = registry.get_libfunc(&declaration.id).ok()?;
+                let builtins = libfunc
+                    .param_signatures()
+                    .iter()
+                    .filter(|param| {
+                        registry.get_type(&param.ty).is_ok_and(|ty| {
+                            matches!(
+                                ty,
+                                CoreTypeConcrete::RangeCheck(_)
+                                    | CoreTypeConcrete::RangeCheck96(_)
+                                    | CoreTypeConcrete::Pedersen(_)
+                                    | CoreTypeConcrete::Poseidon(_)
+                                    | CoreTypeConcrete::Bitwise(_)
+                                    | CoreTypeConcrete::EcOp(_)
+                                    | CoreTypeConcrete::SegmentArena(_)
+                                    | CoreTypeConcrete::Circuit(
+                                        CircuitTypeConcrete::AddMod(_)
+                                            | CircuitTypeConcrete::MulMod(_)
+                                    )
+                            )
+                        })
+                    })
+                    .count();
+                (builtins > 0).then(|| (declaration.id.clone(), builtins))
+            })
+            .collect()
+    }
+
+    /// Returns the map between the index of each Sierra statement invoking a
+    /// libfunc and the Sierra gas cost of the invocation.
+    ///
+    /// The cost of each branch of the libfunc is computed by
+    /// [`core_libfunc_cost`] from the gas info of the program metadata, the
+    /// same way the compiler validates the gas usage of the program. The cost
+    /// tokens are converted to Sierra gas with [`token_gas_cost`]. The
+    /// branch taken by each invocation isn't known, therefore the cost of the
+    /// most expensive branch is used. Gas withdrawn or refunded by a libfunc
+    /// isn't a cost, therefore negative costs are ignored. The cost of a user
+    /// function call excludes the cost of the function called because it's
+    /// already counted in the libfuncs of the function.
+    ///
+    /// # Arguments
+    ///
+    /// - `sierra_program`: The sierra program.
+    /// - `registry`: The registry of the sierra program.
+    /// - `metadata`: The metadata of the sierra program.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if:
+    ///
+    /// - The sizes of the types of the program can't be computed
+    /// - The circuits of the program can't be computed
+    /// - A libfunc invoked isn't in the registry
+    /// - The cost of a libfunc requires the info of a circuit type which isn't
+    ///   declared in the program
+    fn compute_statement_gas(
+        sierra_program: &Program,
+        registry: &ProgramRegistry<CoreType, CoreLibfunc>,
+        metadata: &Metadata,
+    ) -> Result<HashMap<StatementIdx, usize>, ProfilerError> {
+        let type_sizes = get_type_size_map(sierra_program, registry)
+            .ok_or_else(|| Box::new(CompilationError::FailedBuildingTypeInformation))?;
+        let circuits_info = CircuitsInfo::new(
+            registry,
+            sierra_program
+                .type_declarations
+                .iter()
+                .map(|declaration| &declaration.id),
+        )
+        .map_err(Box::new)?;
+
+        let empty_circuit_info = CircuitInfo {
+            n_inputs: 0,
+            values: UnorderedHashMap::default(),
+            add_offsets: Vec::new(),
+            mul_offsets: Vec::new(),
+        };
+
+        let mut statement_gas = HashMap::new();
+        for (statement_idx, statement) in sierra_program.statements.iter().enumerate() {
+            let GenStatement::Invocation(invocation) = statement else {
+                continue;
+            };
+            let statement_idx = StatementIdx(statement_idx);
+            let libfunc = registry.get_libfunc(&invocation.libfunc_id)?;
+            let callee_cost = match libfunc {
+                CoreConcreteLibfunc::FunctionCall(SignatureAndFunctionConcreteLibfunc {
+                    function,
+                    ..
+                }) => metadata.gas_info.function_costs.get(&function.id),
+                _ => None,
+            };
+            let cost_info = InvocationCostInfo {
+                metadata,
+                type_sizes: &type_sizes,
+                circuits_info: &circuits_info,
+                statement_idx,
+                empty_circuit_info: &empty_circuit_info,
+                missing_circuit: RefCell::new(None),
+            };
+            let gas = core_libfunc_cost(&metadata.gas_info, &statement_idx, libfunc, &cost_info)
+                .into_iter()
+                .flatten()
+                .map(|branch_cost| {
+                    branch_cost
+                        .iter()
+                        .map(|(token_type, cost)| {
+                            let callee_cost = callee_cost
+                                .and_then(|callee_cost| callee_cost.get(token_type))
+                                .copied()
+                                .unwrap_or_default();
+                            usize::try_from(cost - callee_cost).unwrap_or_default()
+                                * token_gas_cost(*token_type)
+                        })
+                        .sum::<usize>()
+                })
+                .max()
+                .unwrap_or_default();
+            if let Some(ty) = cost_info.missing_circuit.take() {
+                return Err(ProfilerError::MissingCircuitInfo(ty.to_string()));
+            }
+            statement_gas.insert(statement_idx, gas);
+        }
+        Ok(statement_gas)
+    }
+
+    /// Checks that the CASM program compiled by the profiler is the same
+    /// bytecode executed by [`blockifier`].
+    ///
+    /// The profiler maps program counters to Sierra statements using its own
+    /// compilation of the Sierra program, which is valid only if it assembles
+    /// to `bytecode`. Every word of the assembled program, including the
+    /// immediate values and the constant segments after the instructions, is
+    /// compared as a field element with the word at the same program counter
+    /// in `bytecode`, and both must have the same length.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytecode`: The bytecode of the compiled contract class executed
+    ///   during the replay.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] with the program counter of the first word which
+    /// doesn't match `bytecode`.
+    pub fn check_bytecode(&self, bytecode: &[BigUintAsHex]) -> Result<(), ProfilerError> {
+        let compiled = self.casm_program.assemble().bytecode;
+        if let Some(pc) = compiled
+            .iter()
+            .zip(bytecode)
+            .position(|(compiled, executed)| Felt::from(compiled) != Felt::from(&executed.value))
+        {
+            return Err(ProfilerError::BytecodeMismatch(pc));
+        }
+        if compiled.len() != bytecode.len() {
+            return Err(ProfilerError::BytecodeMismatch(
+                compiled.len().min(bytecode.len()),
+            ));
+        }
+        Ok(())
+    }
+
     /// Returns the map between the Sierra statement id and the frequency of
     /// usage.
     ///
     /// # Arguments
     ///
-    /// - `pcs`: The sequence of program counters to analyse.
+    /// - `pcs`: The histogram of program counters to analyse.
     #[must_use]
-    pub fn collect_profiling_info(&self, pcs: &[usize]) -> HashMap<StatementIdx, usize> {
+    pub fn collect_profiling_info(&self, pcs: &PcHistogram) -> HashMap<StatementIdx, usize> {
         let mut sierra_statement_weights = HashMap::default();
-        for (pc, frequency) in pcs.iter().counts() {
-            let statements: Vec<&CompiledStatement> =
-                self.commands.iter().filter(|c| c.pc == *pc).collect();
-            for statement in statements {
+        for (pc, frequency) in pcs {
+            for statement in self.commands_at_pc(*pc) {
                 let statement_idx = StatementIdx(statement.statement_idx);
                 *sierra_statement_weights.entry(statement_idx).or_insert(0) += frequency;
             }
@@ -207,6 +612,110 @@ impl SierraProfiler {
         sierra_statement_weights
     }
 
+    /// Returns the map between the Sierra statement id and the number of CASM
+    /// steps executed in the statement.
+    ///
+    /// # Arguments
+    ///
+    /// - `pcs`: The histogram of program counters to analyse.
+    #[must_use]
+    pub fn collect_statement_steps(&self, pcs: &PcHistogram) -> HashMap<StatementIdx, usize> {
+        let mut statement_steps = HashMap::default();
+        for (pc, frequency) in pcs {
+            if let Some(statement_idx) = self.statements_by_pc.get(pc) {
+                *statement_steps
+                    .entry(StatementIdx(*statement_idx))
+                    .or_insert(0) += frequency;
+            }
+        }
+        statement_steps
+    }
+
+    /// Returns the list of [`CompiledStatement`] starting at the program
+    /// counter `pc`.
+    ///
+    /// # Arguments
+    ///
+    /// - `pc`: The program counter to query.
+    pub fn commands_at_pc(&self, pc: usize) -> impl Iterator<Item = &CompiledStatement> {
+        self.commands_by_pc
+            .get(&pc)
+            .into_iter()
+            .flatten()
+            .map(|idx| &self.commands[*idx])
+    }
+
+    /// Returns the CASM instruction starting at the program counter `pc`.
+    ///
+    /// Returns [`None`] if `pc` isn't the beginning of an instruction of the
+    /// program.
+    ///
+    /// # Arguments
+    ///
+    /// - `pc`: The program counter to query.
+    #[must_use]
+    pub fn instruction_at_pc(&self, pc: usize) -> Option<&Instruction> {
+        self.instructions_by_pc
+            .get(&pc)
+            .map(|idx| &self.casm_program.instructions[*idx])
+    }
+
+    /// Returns the program counter of the first CASM instruction of the Sierra
+    /// statement `statement_idx`.
+    ///
+    /// Returns [`None`] if the statement doesn't exist.
+    ///
+    /// # Arguments
+    ///
+    /// - `statement_idx`: The Sierra statement id.
+    #[must_use]
+    pub fn statement_pc(&self, statement_idx: StatementIdx) -> Option<usize> {
+        self.commands
+            .iter()
+            .find(|command| command.statement_idx == statement_idx.0)
+            .map(|command| command.pc)
+    }
+
+    /// Returns the generic libfunc of the concrete libfunc `libfunc_id`.
+    ///
+    /// Returns [`None`] if the libfunc isn't declared in the Sierra program.
+    ///
+    /// # Arguments
+    ///
+    /// - `libfunc_id`: The concrete libfunc id.
+    #[must_use]
+    pub fn generic_libfunc_id(&self, libfunc_id: &ConcreteLibfuncId) -> Option<&GenericLibfuncId> {
+        self.generic_libfuncs.get(libfunc_id)
+    }
+
+    /// Returns the number of builtins taken as parameters by the concrete
+    /// libfunc `libfunc_id`.
+    ///
+    /// # Arguments
+    ///
+    /// - `libfunc_id`: The concrete libfunc id.
+    #[must_use]
+    pub fn builtin_calls(&self, libfunc_id: &ConcreteLibfuncId) -> usize {
+        self.builtin_libfuncs
+            .get(libfunc_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the Sierra gas cost of the invocation of a libfunc at the
+    /// Sierra statement `statement_idx`.
+    ///
+    /// # Arguments
+    ///
+    /// - `statement_idx`: The Sierra statement id.
+    #[must_use]
+    pub fn statement_gas(&self, statement_idx: StatementIdx) -> usize {
+        self.statement_gas
+            .get(&statement_idx)
+            .copied()
+            .unwrap_or_default()
+    }
+
     /// Translates the given Sierra statement index into the actual statement.
     ///
     /// # Arguments
@@ -242,10 +751,82 @@ impl SierraProfiler {
         libfunc_weights
     }
 
+    /// Returns the map between the generic libfunc and the frequency of
+    /// usage.
+    ///
+    /// # Arguments
+    ///
+    /// - `statements`: The map with the frequency of Sierra statements.
+    #[must_use]
+    pub fn unpack_generic_profiling_info(
+        &self,
+        statements: &HashMap<StatementIdx, usize>,
+    ) -> HashMap<String, usize> {
+        let mut libfunc_weights = HashMap::default();
+        for (statement_idx, frequency) in statements {
+            if let Some(GenStatement::Invocation(invocation)) =
+                self.statement_idx_to_gen_statement(*statement_idx)
+            {
+                let Some(generic_id) = self.generic_libfunc_id(&invocation.libfunc_id) else {
+                    continue;
+                };
+                *(libfunc_weights.entry(generic_id.to_string()).or_insert(0)) += frequency;
+            }
+        }
+        libfunc_weights
+    }
+
+    /// Returns the maps between the concrete and generic libfuncs and the cost
+    /// of their calls.
+    ///
+    /// The CASM steps are the steps executed in each Sierra statement. The
+    /// Sierra gas cost is the gas cost of each invocation, as computed from
+    /// the program metadata, multiplied by the number of invocations.
+    ///
+    /// # Arguments
+    ///
+    /// - `statements`: The map with the frequency of Sierra statements.
+    /// - `statement_steps`: The map with the CASM steps executed in each Sierra
+    ///   statement.
+    #[must_use]
+    pub fn unpack_cost_info(
+        &self,
+        statements: &HashMap<StatementIdx, usize>,
+        statement_steps: &HashMap<StatementIdx, usize>,
+    ) -> (HashMap<String, LibfuncCost>, HashMap<String, LibfuncCost>) {
+        let mut concrete_costs: HashMap<String, LibfuncCost> = HashMap::default();
+        let mut generic_costs: HashMap<String, LibfuncCost> = HashMap::default();
+        for (statement_idx, frequency) in statements {
+            if let Some(GenStatement::Invocation(invocation)) =
+                self.statement_idx_to_gen_statement(*statement_idx)
+            {
+                let steps = statement_steps
+                    .get(statement_idx)
+                    .copied()
+                    .unwrap_or_default();
+                let cost = LibfuncCost {
+                    steps,
+                    gas: frequency * self.statement_gas(*statement_idx),
+                };
+                concrete_costs
+                    .entry(invocation.libfunc_id.to_string())
+                    .or_default()
+                    .merge(&cost);
+                if let Some(generic_id) = self.generic_libfunc_id(&invocation.libfunc_id) {
+                    generic_costs
+                        .entry(generic_id.to_string())
+                        .or_default()
+                        .merge(&cost);
+                }
+            }
+        }
+        (concrete_costs, generic_costs)
+    }
+
     #[cfg(test)]
     fn get_libfuncs_at_pc(&self, pc: usize) -> Vec<String> {
         let mut libfuncs = Vec::new();
-        let raw_profiling_info = self.collect_profiling_info(vec![pc].as_slice());
+        let raw_profiling_info = self.collect_profiling_info(&[pc].into_iter().collect());
         for (statement_idx, _) in raw_profiling_info {
             if let Some(GenStatement::Invocation(invocation)) =
                 self.statement_idx_to_gen_statement(statement_idx)
@@ -0,0 +1,328 @@
+//! The module which exports [`FoldedStacks`] in the gzipped protobuf format of
+//! [pprof](https://github.com/google/pprof/blob/main/proto/profile.proto), so
+//! that the profile can be opened with `go tool pprof` or speedscope.
+//!
+//! Each call stack is a sample with three values: the number of CASM steps, the
+//! number of libfunc calls and the number of builtins passed to the libfuncs.
+//! Each frame is a function with a single location. The frames don't have
+//! source files because function names aren't stored on chain.
+//!
+//! Only the subset of the protobuf encoding required by the profile message is
+//! implemented.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::profiler::flamegraph::FoldedStacks;
+
+/// The name of the sample types of the profile, in the same order of the values
+/// of each sample.
+const SAMPLE_TYPES: [&str; 3] = ["steps", "libfunc_calls", "builtin_calls"];
+
+/// The unit of all the sample types.
+const SAMPLE_UNIT: &str = "count";
+
+/// The protobuf wire type of varint fields.
+const WIRE_TYPE_VARINT: u64 = 0;
+
+/// The protobuf wire type of length-delimited fields.
+const WIRE_TYPE_LEN: u64 = 2;
+
+/// The field number of `sample_type` in the message `Profile`.
+const FIELD_SAMPLE_TYPE: u64 = 1;
+
+/// The field number of `sample` in the message `Profile`.
+const FIELD_SAMPLE: u64 = 2;
+
+/// The field number of `location` in the message `Profile`.
+const FIELD_LOCATION: u64 = 4;
+
+/// The field number of `function` in the message `Profile`.
+const FIELD_FUNCTION: u64 = 5;
+
+/// The field number of `string_table` in the message `Profile`.
+const FIELD_STRING_TABLE: u64 = 6;
+
+/// The field number of `period_type` in the message `Profile`.
+const FIELD_PERIOD_TYPE: u64 = 11;
+
+/// The field number of `period` in the message `Profile`.
+const FIELD_PERIOD: u64 = 12;
+
+/// The field number of `default_sample_type` in the message `Profile`.
+const FIELD_DEFAULT_SAMPLE_TYPE: u64 = 14;
+
+/// The table of the strings of the profile. The first string is always empty.
+struct StringTable {
+    /// The list of strings in order of insertion.
+    strings: Vec<String>,
+
+    /// The map between a string and its index in `strings`.
+    ids: HashMap<String, u64>,
+}
+impl StringTable {
+    /// Initialisation of [`StringTable`] with the empty string.
+    fn new() -> Self {
+        StringTable {
+            strings: vec![String::new()],
+            ids: HashMap::from([(String::new(), 0)]),
+        }
+    }
+
+    /// Returns the index of `string`, adding it to the table if missing.
+    ///
+    /// # Arguments
+    ///
+    /// - `string`: The string to look up.
+    fn id(&mut self, string: &str) -> u64 {
+        if let Some(id) = self.ids.get(string) {
+            return *id;
+        }
+        let id = self.strings.len() as u64;
+        self.strings.push(string.to_string());
+        self.ids.insert(string.to_string(), id);
+        id
+    }
+}
+
+/// Appends `value` to `buf` in varint encoding (LEB128).
+///
+/// The encoding is shared with the file of visited program counters.
+///
+/// # Arguments
+///
+/// - `buf`: The buffer to write to.
+/// - `value`: The value to encode.
+#[allow(clippy::cast_possible_truncation)] // Added because only the lowest 7 bits are kept.
+pub(crate) fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Appends a varint field to `buf`. Fields with value 0 are skipped because 0
+/// is the default value.
+///
+/// # Arguments
+///
+/// - `buf`: The buffer to write to.
+/// - `field`: The field number.
+/// - `value`: The value of the field.
+fn write_uint_field(buf: &mut Vec<u8>, field: u64, value: u64) {
+    if value != 0 {
+        write_varint(buf, (field << 3) | WIRE_TYPE_VARINT);
+        write_varint(buf, value);
+    }
+}
+
+/// Appends a length-delimited field to `buf`.
+///
+/// # Arguments
+///
+/// - `buf`: The buffer to write to.
+/// - `field`: The field number.
+/// - `bytes`: The content of the field.
+fn write_bytes_field(buf: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    write_varint(buf, (field << 3) | WIRE_TYPE_LEN);
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+/// Appends a packed repeated varint field to `buf`.
+///
+/// # Arguments
+///
+/// - `buf`: The buffer to write to.
+/// - `field`: The field number.
+/// - `values`: The values of the field.
+fn write_packed_field(buf: &mut Vec<u8>, field: u64, values: &[u64]) {
+    let mut packed = Vec::new();
+    for value in values {
+        write_varint(&mut packed, *value);
+    }
+    write_bytes_field(buf, field, &packed);
+}
+
+/// Returns the encoding of a message `ValueType`.
+///
+/// # Arguments
+///
+/// - `strings`: The string table of the profile.
+/// - `value_type`: The name of the type.
+fn value_type(strings: &mut StringTable, value_type: &str) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_uint_field(&mut buf, 1, strings.id(value_type));
+    write_uint_field(&mut buf, 2, strings.id(SAMPLE_UNIT));
+    buf
+}
+
+/// Serialises [`FoldedStacks`] to the gzipped pprof protobuf format.
+///
+/// # Arguments
+///
+/// - `folded_stacks`: The samples collected for each call stack.
+///
+/// # Errors
+///
+/// Returns [`Err`] if there is an IO error writing to the buffer.
+pub fn to_pprof_bytes(folded_stacks: &FoldedStacks) -> Result<Vec<u8>, std::io::Error> {
+    let mut strings = StringTable::new();
+    let mut profile = Vec::new();
+
+    for sample_type in SAMPLE_TYPES {
+        let sample_type = value_type(&mut strings, sample_type);
+        write_bytes_field(&mut profile, FIELD_SAMPLE_TYPE, &sample_type);
+    }
+
+    // The id of each frame is used both as function id and as location id.
+    let mut frames: Vec<&str> = Vec::new();
+    let mut frame_ids: HashMap<&str, u64> = HashMap::new();
+    for (stack, samples) in &folded_stacks.stacks {
+        // The first location of a sample is the leaf of the call stack.
+        let location_ids: Vec<u64> = stack
+            .split(';')
+            .rev()
+            .map(|frame| {
+                *frame_ids.entry(frame).or_insert_with(|| {
+                    frames.push(frame);
+                    frames.len() as u64
+                })
+            })
+            .collect();
+        let values =
+            [samples.steps, samples.libfunc_calls, samples.builtin_calls].map(|value| value as u64);
+
+        let mut sample = Vec::new();
+        write_packed_field(&mut sample, 1, &location_ids);
+        write_packed_field(&mut sample, 2, &values);
+        write_bytes_field(&mut profile, FIELD_SAMPLE, &sample);
+    }
+
+    for (id, frame) in (1..).zip(&frames) {
+        let mut line = Vec::new();
+        write_uint_field(&mut line, 1, id);
+        let mut location = Vec::new();
+        write_uint_field(&mut location, 1, id);
+        write_bytes_field(&mut location, 4, &line);
+        write_bytes_field(&mut profile, FIELD_LOCATION, &location);
+
+        let name = strings.id(frame);
+        let mut function = Vec::new();
+        write_uint_field(&mut function, 1, id);
+        write_uint_field(&mut function, 2, name);
+        write_uint_field(&mut function, 3, name);
+        write_bytes_field(&mut profile, FIELD_FUNCTION, &function);
+    }
+
+    let period_type = value_type(&mut strings, SAMPLE_TYPES[0]);
+    write_bytes_field(&mut profile, FIELD_PERIOD_TYPE, &period_type);
+    write_uint_field(&mut profile, FIELD_PERIOD, 1);
+    let default_sample_type = strings.id(SAMPLE_TYPES[0]);
+    write_uint_field(&mut profile, FIELD_DEFAULT_SAMPLE_TYPE, default_sample_type);
+    for string in &strings.strings {
+        write_bytes_field(&mut profile, FIELD_STRING_TABLE, string.as_bytes());
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&profile)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+    use crate::profiler::flamegraph::StackSamples;
+
+    fn read_varint(buf: &[u8], offset: &mut usize) -> u64 {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = buf[*offset];
+            *offset += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte < 0x80 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    /// Returns the list of pairs of field number and content of the
+    /// length-delimited fields of a message.
+    fn read_fields(buf: &[u8]) -> Vec<(u64, Vec<u8>)> {
+        let mut fields = Vec::new();
+        let mut offset = 0;
+        while offset < buf.len() {
+            let key = read_varint(buf, &mut offset);
+            if key & 0x7 == WIRE_TYPE_LEN {
+                let len = usize::try_from(read_varint(buf, &mut offset)).unwrap();
+                fields.push((key >> 3, buf[offset..offset + len].to_vec()));
+                offset += len;
+            } else {
+                read_varint(buf, &mut offset);
+            }
+        }
+        fields
+    }
+
+    #[test]
+    fn test_write_varint() {
+        let mut buf = Vec::new();
+        write_varint(&mut buf, 1);
+        write_varint(&mut buf, 300);
+        assert_eq!(buf, vec![0x01, 0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_to_pprof_bytes() {
+        let mut folded_stacks = FoldedStacks::new();
+        folded_stacks.add(
+            "0x1::transfer;[0];store_temp<felt252>",
+            &StackSamples {
+                steps: 2,
+                libfunc_calls: 2,
+                builtin_calls: 0,
+            },
+        );
+        folded_stacks.add(
+            "0x1::transfer;[0];u128_overflowing_add",
+            &StackSamples {
+                steps: 3,
+                libfunc_calls: 1,
+                builtin_calls: 1,
+            },
+        );
+
+        let output = to_pprof_bytes(&folded_stacks).unwrap();
+        let mut profile = Vec::new();
+        GzDecoder::new(output.as_slice())
+            .read_to_end(&mut profile)
+            .unwrap();
+        let fields = read_fields(&profile);
+        let count = |field: u64| fields.iter().filter(|(number, _)| *number == field).count();
+
+        assert_eq!(count(FIELD_SAMPLE_TYPE), 3);
+        assert_eq!(count(FIELD_SAMPLE), 2);
+        // The frames `0x1::transfer` and `[0]` are shared by both samples.
+        assert_eq!(count(FIELD_LOCATION), 4);
+        assert_eq!(count(FIELD_FUNCTION), 4);
+
+        let strings: Vec<String> = fields
+            .iter()
+            .filter(|(number, _)| *number == FIELD_STRING_TABLE)
+            .map(|(_, string)| String::from_utf8(string.clone()).unwrap())
+            .collect();
+        assert_eq!(strings[0], "");
+        assert!(strings.contains(&"steps".to_string()));
+        assert!(strings.contains(&"u128_overflowing_add".to_string()));
+    }
+}
@@ -1,31 +1,235 @@
 //! The module which provides an interface to libfunc usage statistics.
 
 use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
 use std::io::Write;
 use std::ops::{Div, Mul};
+use std::str::FromStr;
 
 use itertools::Itertools;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ProfilerError;
+
+/// The cumulative share in percentage of the libfunc calls kept by
+/// [`ReplayStatistics::filter_most_frequent`].
+pub const DEFAULT_CUMULATIVE_SHARE: usize = 80;
+
+/// The header of the CSV format of [`ReplayStatistics`], without the weight
+/// and the granularity.
+const CSV_HEADER: &str = "Function Name,Weight";
+
+/// The granularity of the libfunc names reported.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibfuncGranularity {
+    /// Libfuncs are reported with their fully specialised name (e.g.
+    /// `store_temp<core::integer::u256>`).
+    #[default]
+    Concrete,
+
+    /// Libfuncs are reported with their generic name (e.g. `store_temp`).
+    Generic,
+
+    /// Libfuncs are reported with both their concrete and generic names.
+    /// Generic names are marked with the suffix ` (generic)`.
+    Both,
+}
+impl Display for LibfuncGranularity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let granularity = match self {
+            LibfuncGranularity::Concrete => "concrete",
+            LibfuncGranularity::Generic => "generic",
+            LibfuncGranularity::Both => "both",
+        };
+        write!(f, "{granularity}")
+    }
+}
+impl FromStr for LibfuncGranularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "concrete" => Ok(LibfuncGranularity::Concrete),
+            "generic" => Ok(LibfuncGranularity::Generic),
+            "both" => Ok(LibfuncGranularity::Both),
+            _ => Err(format!(
+                "Invalid libfunc granularity `{s}`. Expected `concrete`, `generic` or `both`."
+            )),
+        }
+    }
+}
+
+/// The weight used to rank libfuncs in the reports and in the histogram.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibfuncWeight {
+    /// Libfuncs are ranked by number of calls.
+    #[default]
+    Calls,
+
+    /// Libfuncs are ranked by number of CASM steps executed.
+    Steps,
+
+    /// Libfuncs are ranked by Sierra gas cost.
+    ///
+    /// The branch taken by each call isn't recorded, therefore each call is
+    /// charged the cost of the most expensive branch of the libfunc. The gas
+    /// cost of branching libfuncs is overestimated.
+    Gas,
+}
+impl Display for LibfuncWeight {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let weight = match self {
+            LibfuncWeight::Calls => "calls",
+            LibfuncWeight::Steps => "steps",
+            LibfuncWeight::Gas => "gas",
+        };
+        write!(f, "{weight}")
+    }
+}
+impl FromStr for LibfuncWeight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "calls" => Ok(LibfuncWeight::Calls),
+            "steps" => Ok(LibfuncWeight::Steps),
+            "gas" => Ok(LibfuncWeight::Gas),
+            _ => Err(format!(
+                "Invalid libfunc weight `{s}`. Expected `calls`, `steps` or `gas`."
+            )),
+        }
+    }
+}
+
+/// The cost of the calls of a libfunc.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub struct LibfuncCost {
+    /// The number of CASM steps executed.
+    pub steps: usize,
+
+    /// The Sierra gas cost.
+    pub gas: usize,
+}
+impl LibfuncCost {
+    /// Adds the cost in `other` to `self`.
+    ///
+    /// # Arguments
+    ///
+    /// - `other`: The cost to add.
+    pub fn merge(&mut self, other: &LibfuncCost) {
+        self.steps += other.steps;
+        self.gas += other.gas;
+    }
+}
+
+/// Returns the ratio between `part` and `total`, or 0 if `total` is 0.
+///
+/// # Arguments
+///
+/// - `part`: The numerator.
+/// - `total`: The denominator.
+#[allow(clippy::cast_precision_loss)] // Added because shares don't need to be exact.
+pub(crate) fn share(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+/// Returns the values of `concrete` or `generic` according to `granularity`.
+///
+/// With [`LibfuncGranularity::Both`], the keys of `generic` are marked with
+/// the suffix ` (generic)`.
+///
+/// # Arguments
+///
+/// - `concrete`: The map with concrete libfunc names.
+/// - `generic`: The map with generic libfunc names.
+/// - `granularity`: The granularity of the libfunc names.
+fn select_granularity<T: Clone>(
+    concrete: &HashMap<String, T>,
+    generic: &HashMap<String, T>,
+    granularity: LibfuncGranularity,
+) -> HashMap<String, T> {
+    match granularity {
+        LibfuncGranularity::Concrete => concrete.clone(),
+        LibfuncGranularity::Generic => generic.clone(),
+        LibfuncGranularity::Both => concrete
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .chain(
+                generic
+                    .iter()
+                    .map(|(name, value)| (format!("{name} (generic)"), value.clone())),
+            )
+            .collect(),
+    }
+}
 
 /// The struct to hold a list of libfunc names with their related call
 /// frequency.
-#[derive(Debug, Clone, Eq, PartialEq, Default)]
+///
+/// Missing fields are empty when deserialising, apart from `weight` which is
+/// [`LibfuncWeight::Calls`] and `granularity` which is
+/// [`LibfuncGranularity::Concrete`].
+#[derive(Debug, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ReplayStatistics {
     /// This field contains the association between libfunc name (key) and
     /// number of calls (value).
     pub concrete_libfunc: HashMap<String, usize>,
+
+    /// This field contains the association between generic libfunc name (key)
+    /// and number of calls (value).
+    pub generic_libfunc: HashMap<String, usize>,
+
+    /// This field contains the association between libfunc name (key) and
+    /// cost of its calls (value).
+    pub concrete_libfunc_cost: HashMap<String, LibfuncCost>,
+
+    /// This field contains the association between generic libfunc name (key)
+    /// and cost of its calls (value).
+    pub generic_libfunc_cost: HashMap<String, LibfuncCost>,
+
+    /// The weight of the libfuncs in fields `concrete_libfunc` and
+    /// `generic_libfunc`, as selected by [`ReplayStatistics::with_weight`].
+    pub weight: LibfuncWeight,
+
+    /// The granularity of the libfunc names in fields `concrete_libfunc` and
+    /// `concrete_libfunc_cost`, as selected by
+    /// [`ReplayStatistics::with_granularity`].
+    pub granularity: LibfuncGranularity,
 }
 
 impl ReplayStatistics {
     /// Initialisation of [`ReplayStatistics`].
     ///
-    /// The struct is initialised with field `concrete_libfunc` empty.
+    /// The struct is initialised with all fields empty.
     #[must_use]
     pub fn new() -> Self {
         ReplayStatistics {
             concrete_libfunc: HashMap::default(),
+            generic_libfunc: HashMap::default(),
+            concrete_libfunc_cost: HashMap::default(),
+            generic_libfunc_cost: HashMap::default(),
+            weight: LibfuncWeight::default(),
+            granularity: LibfuncGranularity::default(),
         }
     }
 
+    /// Returns `true` if no libfunc has been recorded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.concrete_libfunc.is_empty()
+            && self.generic_libfunc.is_empty()
+            && self.concrete_libfunc_cost.is_empty()
+            && self.generic_libfunc_cost.is_empty()
+    }
+
     /// Add libfunc with frequency to [`ReplayStatistics`].
     ///
     /// `name` is added to [`ReplayStatistics::concrete_libfunc`] if not
@@ -43,6 +247,55 @@ impl ReplayStatistics {
             .or_insert(frequency);
     }
 
+    /// Add generic libfunc with frequency to [`ReplayStatistics`].
+    ///
+    /// `name` is added to [`ReplayStatistics::generic_libfunc`] if not
+    /// present. If the `name` already exists, the `frequency` is increased
+    /// accordingly.
+    ///
+    /// # Arguments
+    ///
+    /// - `name`: Name of generic libfunc.
+    /// - `frequency`: Number of calls to `name`.
+    pub fn update_generic(&mut self, name: &impl ToString, frequency: usize) {
+        self.generic_libfunc
+            .entry(name.to_string())
+            .and_modify(|e| *e += frequency)
+            .or_insert(frequency);
+    }
+
+    /// Add the cost of the calls of a libfunc to [`ReplayStatistics`].
+    ///
+    /// If `name` already exists in [`ReplayStatistics::concrete_libfunc_cost`],
+    /// the costs are summed.
+    ///
+    /// # Arguments
+    ///
+    /// - `name`: Name of libfunc.
+    /// - `cost`: Cost of the calls to `name`.
+    pub fn update_cost(&mut self, name: &impl ToString, cost: &LibfuncCost) {
+        self.concrete_libfunc_cost
+            .entry(name.to_string())
+            .or_default()
+            .merge(cost);
+    }
+
+    /// Add the cost of the calls of a generic libfunc to [`ReplayStatistics`].
+    ///
+    /// If `name` already exists in [`ReplayStatistics::generic_libfunc_cost`],
+    /// the costs are summed.
+    ///
+    /// # Arguments
+    ///
+    /// - `name`: Name of generic libfunc.
+    /// - `cost`: Cost of the calls to `name`.
+    pub fn update_generic_cost(&mut self, name: &impl ToString, cost: &LibfuncCost) {
+        self.generic_libfunc_cost
+            .entry(name.to_string())
+            .or_default()
+            .merge(cost);
+    }
+
     /// Update [`ReplayStatistics`] with results from contract replay.
     ///
     /// Keys are added to [`ReplayStatistics::concrete_libfunc`] if not present.
@@ -60,20 +313,224 @@ impl ReplayStatistics {
         self
     }
 
+    /// Update [`ReplayStatistics`] with generic libfunc results from contract
+    /// replay.
+    ///
+    /// Keys are added to [`ReplayStatistics::generic_libfunc`] if not present.
+    /// If the key already exists, the value (count) is increased
+    /// accordingly.
+    ///
+    /// # Arguments
+    ///
+    /// - `input`: Input map of generic libfuncs.
+    #[must_use]
+    pub fn add_generic_statistics(mut self, input: &HashMap<impl ToString, usize>) -> Self {
+        for (name, frequency) in input {
+            self.update_generic(&name.to_string(), *frequency);
+        }
+        self
+    }
+
+    /// Update [`ReplayStatistics`] with the cost of libfuncs from contract
+    /// replay.
+    ///
+    /// # Arguments
+    ///
+    /// - `concrete_input`: Input map of libfunc costs.
+    /// - `generic_input`: Input map of generic libfunc costs.
+    #[must_use]
+    pub fn add_cost_statistics(
+        mut self,
+        concrete_input: &HashMap<impl ToString, LibfuncCost>,
+        generic_input: &HashMap<impl ToString, LibfuncCost>,
+    ) -> Self {
+        for (name, cost) in concrete_input {
+            self.update_cost(name, cost);
+        }
+        for (name, cost) in generic_input {
+            self.update_generic_cost(name, cost);
+        }
+        self
+    }
+
     /// Update the object with data in `from`.
     ///
-    /// This function adopts the same logic as `self.add_statistics`.
+    /// This function adopts the same logic as `self.add_statistics`. If `self`
+    /// is empty, it takes the weight and the granularity of `from`. They aren't
+    /// checked: use [`ReplayStatistics::try_merge`] to merge statistics which
+    /// may have a different weight or granularity, e.g. statistics read from
+    /// files.
     ///
     /// # Arguments
     ///
     /// - `from`: Input `ReplayStatistics` to get data from.
     pub fn merge(&mut self, from: &ReplayStatistics) {
+        if self.is_empty() {
+            self.weight = from.weight;
+            self.granularity = from.granularity;
+        }
         for (libfunc, weight) in &from.concrete_libfunc {
             self.concrete_libfunc
                 .entry(libfunc.clone())
                 .and_modify(|e| *e += *weight)
                 .or_insert(*weight);
         }
+        for (libfunc, weight) in &from.generic_libfunc {
+            self.generic_libfunc
+                .entry(libfunc.clone())
+                .and_modify(|e| *e += *weight)
+                .or_insert(*weight);
+        }
+        for (libfunc, cost) in &from.concrete_libfunc_cost {
+            self.update_cost(libfunc, cost);
+        }
+        for (libfunc, cost) in &from.generic_libfunc_cost {
+            self.update_generic_cost(libfunc, cost);
+        }
+    }
+
+    /// Update the object with data in `from` after checking that both have the
+    /// same weight and granularity.
+    ///
+    /// # Arguments
+    ///
+    /// - `from`: Input `ReplayStatistics` to get data from.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if `self` and `from` aren't empty and have a different
+    /// weight or granularity.
+    pub fn try_merge(&mut self, from: &ReplayStatistics) -> Result<(), ProfilerError> {
+        self.check_compatible(from)?;
+        self.merge(from);
+        Ok(())
+    }
+
+    /// Checks that the weights of `self` and `other` can be compared or summed.
+    ///
+    /// Empty statistics are compatible with any statistics.
+    ///
+    /// # Arguments
+    ///
+    /// - `other`: The statistics to compare with `self`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if `self` and `other` aren't empty and have a different
+    /// weight or granularity.
+    pub fn check_compatible(&self, other: &ReplayStatistics) -> Result<(), ProfilerError> {
+        if self.is_empty() || other.is_empty() {
+            return Ok(());
+        }
+        if self.weight != other.weight {
+            return Err(ProfilerError::IncompatibleStatistics(format!(
+                "libfunc weight `{}` differs from `{}`",
+                self.weight, other.weight
+            )));
+        }
+        if self.granularity != other.granularity {
+            return Err(ProfilerError::IncompatibleStatistics(format!(
+                "libfunc granularity `{}` differs from `{}`",
+                self.granularity, other.granularity
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the statistics with the libfunc names of the chosen
+    /// granularity in field `concrete_libfunc`, which is the field used by
+    /// reports and histograms.
+    ///
+    /// The same applies to field `concrete_libfunc_cost`. Fields
+    /// `generic_libfunc` and `generic_libfunc_cost` are kept unchanged and
+    /// field `granularity` is set to `granularity`.
+    ///
+    /// # Arguments
+    ///
+    /// - `granularity`: The granularity of the libfunc names.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the granularity of `self` is neither
+    /// [`LibfuncGranularity::Concrete`] nor `granularity`, because the
+    /// concrete libfunc names aren't available anymore.
+    pub fn with_granularity(
+        &self,
+        granularity: LibfuncGranularity,
+    ) -> Result<ReplayStatistics, ProfilerError> {
+        if self.granularity == granularity {
+            return Ok(self.clone());
+        }
+        if self.granularity != LibfuncGranularity::Concrete {
+            return Err(ProfilerError::IncompatibleStatistics(format!(
+                "libfunc granularity `{granularity}` can't be selected from granularity `{}`",
+                self.granularity
+            )));
+        }
+        Ok(ReplayStatistics {
+            concrete_libfunc: select_granularity(
+                &self.concrete_libfunc,
+                &self.generic_libfunc,
+                granularity,
+            ),
+            generic_libfunc: self.generic_libfunc.clone(),
+            concrete_libfunc_cost: select_granularity(
+                &self.concrete_libfunc_cost,
+                &self.generic_libfunc_cost,
+                granularity,
+            ),
+            generic_libfunc_cost: self.generic_libfunc_cost.clone(),
+            weight: self.weight,
+            granularity,
+        })
+    }
+
+    /// Returns the statistics with the chosen weight of each libfunc in fields
+    /// `concrete_libfunc` and `generic_libfunc`, which are the fields used by
+    /// reports and histograms.
+    ///
+    /// The cost fields are kept unchanged and field `weight` is set to
+    /// `weight`.
+    ///
+    /// # Arguments
+    ///
+    /// - `weight`: The weight of the libfuncs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the weight of `self` is neither
+    /// [`LibfuncWeight::Calls`] nor `weight`, because the number of calls isn't
+    /// available anymore.
+    pub fn with_weight(&self, weight: LibfuncWeight) -> Result<ReplayStatistics, ProfilerError> {
+        if self.weight == weight {
+            return Ok(self.clone());
+        }
+        if self.weight != LibfuncWeight::Calls {
+            return Err(ProfilerError::IncompatibleStatistics(format!(
+                "libfunc weight `{weight}` can't be selected from weight `{}`",
+                self.weight
+            )));
+        }
+        let select =
+            |calls: &HashMap<String, usize>, costs: &HashMap<String, LibfuncCost>| match weight {
+                LibfuncWeight::Calls => calls.clone(),
+                LibfuncWeight::Steps => costs
+                    .iter()
+                    .map(|(name, cost)| (name.clone(), cost.steps))
+                    .collect(),
+                LibfuncWeight::Gas => costs
+                    .iter()
+                    .map(|(name, cost)| (name.clone(), cost.gas))
+                    .collect(),
+            };
+        Ok(ReplayStatistics {
+            concrete_libfunc: select(&self.concrete_libfunc, &self.concrete_libfunc_cost),
+            generic_libfunc: select(&self.generic_libfunc, &self.generic_libfunc_cost),
+            concrete_libfunc_cost: self.concrete_libfunc_cost.clone(),
+            generic_libfunc_cost: self.generic_libfunc_cost.clone(),
+            weight,
+            granularity: self.granularity,
+        })
     }
 
     /// Returns the number of different concrete libfunc names in the
@@ -123,51 +580,170 @@ impl ReplayStatistics {
     /// Panics if the total sum of frequencies doesn't fit in a `usize` number.
     #[must_use]
     pub fn filter_most_frequent(&self) -> ReplayStatistics {
+        self.filter_cumulative_share(DEFAULT_CUMULATIVE_SHARE)
+    }
+
+    /// Filter the most called libfuncs which together account for
+    /// `percentage` percent of the libfunc calls.
+    ///
+    /// Libfuncs are added from the most frequent until their cumulative
+    /// frequency exceeds `percentage` percent of the total. If `percentage` is
+    /// 100 or more, all the libfuncs are kept.
+    ///
+    /// # Arguments
+    ///
+    /// - `percentage`: The cumulative share of the libfunc calls to keep.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the total sum of frequencies doesn't fit in a `usize` number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+    /// let mut replay_statistics = ReplayStatistics::default();
+    /// replay_statistics.update(&"enum_match".to_string(), 700);
+    /// replay_statistics.update(&"store_temp".to_string(), 200);
+    /// replay_statistics.update(&"u32_to_felt252".to_string(), 100);
+    /// let filtered_statistics = replay_statistics.filter_cumulative_share(80);
+    /// assert_eq!(filtered_statistics.get_libfuncs(), vec!["enum_match"]);
+    /// let filtered_statistics = replay_statistics.filter_cumulative_share(100);
+    /// assert_eq!(filtered_statistics.get_number_of_libfuncs(), 3);
+    /// ```
+    #[must_use]
+    pub fn filter_cumulative_share(&self, percentage: usize) -> ReplayStatistics {
         tracing::info!(
             "Number of libfunc before filtering: {}",
             self.get_number_of_libfuncs()
         );
+        if percentage >= 100 {
+            return self.filter_top(self.get_number_of_libfuncs());
+        }
         let total_libfunc_calls: usize = self.concrete_libfunc.values().sum();
         // Ignoring overflows because the resulting number is less than
         // `total_libfunc_calls`.
-        let percentage_of_total: usize = total_libfunc_calls.div(100).mul(80);
+        let percentage_of_total: usize = total_libfunc_calls.div(100).mul(percentage);
 
         let mut cumulative_frequency: usize = 0;
         let mut truncation_index = self.concrete_libfunc.len();
-        for (idx, (_, frequency)) in self
-            .concrete_libfunc
-            .iter()
-            .sorted_by(|a, b| Ord::cmp(&a.1, &b.1))
-            .rev()
-            .enumerate()
-        {
+        for (idx, (_, frequency)) in self.sorted_by_frequency().into_iter().enumerate() {
             cumulative_frequency = cumulative_frequency
-                .checked_add(*frequency)
+                .checked_add(frequency)
                 .expect("Sum of libfunc frequencies should not overflow.");
             if cumulative_frequency > percentage_of_total {
                 truncation_index = idx;
                 break;
             }
         }
-        let ordered_libfuncs: HashMap<String, usize> = self
-            .concrete_libfunc
-            .iter()
-            .sorted_by(|a, b| Ord::cmp(&a.1, &b.1))
-            .rev()
-            .take(truncation_index)
-            .map(|(name, freq)| (name.clone(), *freq))
-            .collect();
-        let filtered_libfuncs = ReplayStatistics::default().add_statistics(&ordered_libfuncs);
+        let filtered_libfuncs = self.filter_top(truncation_index);
         tracing::info!(
-            "Number of libfunc before filtering: {}",
+            "Number of libfunc after filtering: {}",
             filtered_libfuncs.get_number_of_libfuncs()
         );
         filtered_libfuncs
     }
 
+    /// Filter the `count` most called libfuncs.
+    ///
+    /// Libfuncs with the same frequency are ranked by name. Only field
+    /// `concrete_libfunc` is kept, together with the weight and the
+    /// granularity.
+    ///
+    /// # Arguments
+    ///
+    /// - `count`: The number of libfuncs to keep.
+    #[must_use]
+    pub fn filter_top(&self, count: usize) -> ReplayStatistics {
+        let top_libfuncs: HashMap<&str, usize> =
+            self.sorted_by_frequency().into_iter().take(count).collect();
+        self.empty_copy().add_statistics(&top_libfuncs)
+    }
+
+    /// Filter the libfuncs whose name matches `include` and doesn't match
+    /// `exclude`.
+    ///
+    /// If `include` is `None`, all the libfuncs not matching `exclude` are
+    /// kept. Only field `concrete_libfunc` is kept, together with the weight
+    /// and the granularity.
+    ///
+    /// # Arguments
+    ///
+    /// - `include`: The pattern of the libfunc names to keep.
+    /// - `exclude`: The pattern of the libfunc names to remove.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use regex::Regex;
+    /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+    /// let mut replay_statistics = ReplayStatistics::default();
+    /// replay_statistics.update(&"u32_to_felt252".to_string(), 759);
+    /// replay_statistics.update(&"u32_overflowing_add".to_string(), 264);
+    /// replay_statistics.update(&"store_temp".to_string(), 367);
+    /// let include = Regex::new("^u32_").unwrap();
+    /// let exclude = Regex::new("felt252").unwrap();
+    /// let filtered_statistics = replay_statistics.filter_names(Some(&include), Some(&exclude));
+    /// assert_eq!(
+    ///     filtered_statistics.get_libfuncs(),
+    ///     vec!["u32_overflowing_add"]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn filter_names(
+        &self,
+        include: Option<&Regex>,
+        exclude: Option<&Regex>,
+    ) -> ReplayStatistics {
+        let libfuncs: HashMap<&str, usize> = self
+            .concrete_libfunc
+            .iter()
+            .filter(|(name, _)| include.map_or(true, |include| include.is_match(name)))
+            .filter(|(name, _)| !exclude.is_some_and(|exclude| exclude.is_match(name)))
+            .map(|(name, frequency)| (name.as_str(), *frequency))
+            .collect();
+        self.empty_copy().add_statistics(&libfuncs)
+    }
+
+    /// Returns empty statistics with the same weight and granularity as `self`.
+    fn empty_copy(&self) -> ReplayStatistics {
+        ReplayStatistics {
+            weight: self.weight,
+            granularity: self.granularity,
+            ..ReplayStatistics::new()
+        }
+    }
+
+    /// Returns the header of the CSV format.
+    ///
+    /// The weight and the granularity are added to the header of the weight
+    /// unless they are [`LibfuncWeight::Calls`] and
+    /// [`LibfuncGranularity::Concrete`], e.g. `Function Name,Weight (steps;
+    /// generic)`.
+    fn csv_header(&self) -> String {
+        if self.weight == LibfuncWeight::Calls && self.granularity == LibfuncGranularity::Concrete {
+            CSV_HEADER.to_string()
+        } else {
+            format!("{CSV_HEADER} ({}; {})", self.weight, self.granularity)
+        }
+    }
+
+    /// Returns the concrete libfuncs ordered from the most frequent libfunc.
+    ///
+    /// Libfuncs with the same frequency are ordered by name.
+    fn sorted_by_frequency(&self) -> Vec<(&str, usize)> {
+        self.concrete_libfunc
+            .iter()
+            .sorted_by(|a, b| Ord::cmp(&b.1, &a.1).then(Ord::cmp(&a.0, &b.0)))
+            .map(|(name, frequency)| (name.as_str(), *frequency))
+            .collect()
+    }
+
     /// Serialises [`ReplayStatistics`] to CSV format.
     ///
-    /// Libfuncs are reported in ascending order of weight.
+    /// Libfuncs are reported in ascending order of weight. The weight and the
+    /// granularity of the libfunc names are written in the header, see
+    /// [`ReplayStatistics::from_csv_bytes`].
     ///
     /// # Errors
     ///
@@ -195,7 +771,7 @@ impl ReplayStatistics {
     /// ```
     pub fn to_csv_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
         let mut f = Vec::new();
-        writeln!(f, "Function Name,Weight")?;
+        writeln!(f, "{}", self.csv_header())?;
         for (concrete_name, weight) in self
             .concrete_libfunc
             .iter()
@@ -205,6 +781,139 @@ impl ReplayStatistics {
         }
         Ok(f)
     }
+
+    /// Serialises [`ReplayStatistics`] to JSON format.
+    ///
+    /// Unlike the CSV format, all fields are serialised, therefore the object
+    /// is fully restored by [`ReplayStatistics::from_json`], including the
+    /// weight and the granularity.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an error serialising the object.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Deserialises [`ReplayStatistics`] from the JSON format of
+    /// [`ReplayStatistics::to_json`].
+    ///
+    /// The statistics are also read from the field `statistics` of a report
+    /// with metadata, as written by
+    /// [`crate::profiler::run_metadata::RunMetadata::wrap_json`].
+    ///
+    /// # Arguments
+    ///
+    /// - `input`: The JSON content.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if `input` isn't a valid JSON serialisation of
+    /// [`ReplayStatistics`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+    /// let mut replay_statistics = ReplayStatistics::default();
+    /// replay_statistics.update(&"u32_to_felt252".to_string(), 759);
+    /// replay_statistics.update_generic(&"u32_to_felt252".to_string(), 759);
+    /// let json_output = replay_statistics.to_json().unwrap();
+    /// let parsed_statistics = ReplayStatistics::from_json(&json_output).unwrap();
+    /// assert_eq!(parsed_statistics, replay_statistics);
+    /// ```
+    pub fn from_json(input: &str) -> Result<ReplayStatistics, serde_json::Error> {
+        let mut value: serde_json::Value = serde_json::from_str(input)?;
+        if value.get("metadata").is_some() {
+            if let Some(statistics) = value.get_mut("statistics") {
+                value = statistics.take();
+            }
+        }
+        serde_json::from_value(value)
+    }
+
+    /// Deserialises [`ReplayStatistics`] from the CSV format of
+    /// [`ReplayStatistics::to_csv_bytes`].
+    ///
+    /// Only fields `concrete_libfunc`, `weight` and `granularity` are restored
+    /// because they are the only fields serialised. The header is
+    /// `Function Name,Weight`, followed by the weight and the granularity in
+    /// brackets unless they are [`LibfuncWeight::Calls`] and
+    /// [`LibfuncGranularity::Concrete`]. The weight is the last value of each
+    /// line because libfunc names may contain commas. Quotes around libfunc
+    /// names are removed.
+    ///
+    /// # Arguments
+    ///
+    /// - `input`: The CSV content.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if:
+    ///
+    /// - `input` isn't valid UTF-8.
+    /// - The header is missing or its weight or granularity isn't valid.
+    /// - A line doesn't end with a weight.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+    /// let mut replay_statistics = ReplayStatistics::default();
+    /// replay_statistics.update(&"u32_to_felt252".to_string(), 759);
+    /// replay_statistics.update(&"store_temp<Tuple<felt252, u32>>".to_string(), 264);
+    /// let csv_output = replay_statistics.to_csv_bytes().unwrap();
+    /// let parsed_statistics = ReplayStatistics::from_csv_bytes(&csv_output).unwrap();
+    /// assert_eq!(parsed_statistics, replay_statistics);
+    /// ```
+    pub fn from_csv_bytes(input: &[u8]) -> Result<ReplayStatistics, ProfilerError> {
+        let input = std::str::from_utf8(input)
+            .map_err(|err| ProfilerError::InvalidStatistics(1, err.to_string()))?;
+        let mut lines = input.lines();
+        let header = lines.next().map(str::trim).unwrap_or_default();
+        let Some(settings) = header.strip_prefix(CSV_HEADER) else {
+            return Err(ProfilerError::InvalidStatistics(
+                1,
+                format!("Expected header `{CSV_HEADER}`."),
+            ));
+        };
+        let mut statistics = ReplayStatistics::new();
+        if let Some((weight, granularity)) = settings
+            .trim()
+            .strip_prefix('(')
+            .and_then(|settings| settings.strip_suffix(')'))
+            .and_then(|settings| settings.split_once(';'))
+        {
+            statistics.weight = weight
+                .trim()
+                .parse()
+                .map_err(|err| ProfilerError::InvalidStatistics(1, err))?;
+            statistics.granularity = granularity
+                .trim()
+                .parse()
+                .map_err(|err| ProfilerError::InvalidStatistics(1, err))?;
+        } else if !settings.is_empty() {
+            return Err(ProfilerError::InvalidStatistics(
+                1,
+                format!("Expected header `{CSV_HEADER}`."),
+            ));
+        }
+        // Line numbers start from 1 and the header is the first line.
+        for (line_number, line) in (2..).zip(lines) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (name, weight) = line.rsplit_once(',').ok_or_else(|| {
+                ProfilerError::InvalidStatistics(line_number, "Missing weight.".to_string())
+            })?;
+            let weight = weight
+                .trim()
+                .parse::<usize>()
+                .map_err(|err| ProfilerError::InvalidStatistics(line_number, err.to_string()))?;
+            statistics.update(&name.trim().trim_matches('"'), weight);
+        }
+        Ok(statistics)
+    }
 }
 
 #[cfg(test)]
@@ -229,4 +938,183 @@ mod tests {
         b.update(&"u512_safe_divmod_by_u256".to_string(), 118);
         assert_ne!(a, b);
     }
+
+    #[test]
+    fn test_with_granularity() {
+        let mut statistics = ReplayStatistics::new();
+        statistics.update(&"store_temp<felt252>".to_string(), 3);
+        statistics.update(&"store_temp<u128>".to_string(), 2);
+        statistics.update(&"felt252_add".to_string(), 1);
+        statistics.update_generic(&"store_temp".to_string(), 5);
+        statistics.update_generic(&"felt252_add".to_string(), 1);
+
+        let concrete = statistics
+            .with_granularity(LibfuncGranularity::Concrete)
+            .unwrap();
+        assert_eq!(concrete, statistics);
+
+        let generic = statistics
+            .with_granularity(LibfuncGranularity::Generic)
+            .unwrap();
+        assert_eq!(generic.get_number_of_libfuncs(), 2);
+        assert_eq!(generic.get_libfunc_frequency("store_temp"), 5);
+        assert_eq!(generic.granularity, LibfuncGranularity::Generic);
+        assert!(matches!(
+            generic.with_granularity(LibfuncGranularity::Both),
+            Err(ProfilerError::IncompatibleStatistics(_))
+        ));
+
+        let both = statistics
+            .with_granularity(LibfuncGranularity::Both)
+            .unwrap();
+        assert_eq!(both.get_number_of_libfuncs(), 5);
+        assert_eq!(both.get_libfunc_frequency("felt252_add"), 1);
+        assert_eq!(both.get_libfunc_frequency("felt252_add (generic)"), 1);
+
+        // Statistics of different granularity can't be merged.
+        let mut merged = statistics.clone();
+        assert!(matches!(
+            merged.try_merge(&generic),
+            Err(ProfilerError::IncompatibleStatistics(_))
+        ));
+        let mut merged = ReplayStatistics::new();
+        merged.try_merge(&generic).unwrap();
+        merged.try_merge(&generic).unwrap();
+        assert_eq!(merged.granularity, LibfuncGranularity::Generic);
+        assert_eq!(merged.get_libfunc_frequency("store_temp"), 10);
+
+        // The granularity is restored from both CSV and JSON formats.
+        let csv_output = generic.filter_top(1).to_csv_bytes().unwrap();
+        assert!(csv_output.starts_with(b"Function Name,Weight (calls; generic)\n"));
+        let parsed_statistics = ReplayStatistics::from_csv_bytes(&csv_output).unwrap();
+        assert_eq!(parsed_statistics.granularity, LibfuncGranularity::Generic);
+        assert_eq!(parsed_statistics.get_libfunc_frequency("store_temp"), 5);
+        let parsed_statistics = ReplayStatistics::from_json(&generic.to_json().unwrap()).unwrap();
+        assert_eq!(parsed_statistics, generic);
+
+        assert_eq!(
+            "generic".parse::<LibfuncGranularity>(),
+            Ok(LibfuncGranularity::Generic)
+        );
+        assert!("specific".parse::<LibfuncGranularity>().is_err());
+    }
+
+    #[test]
+    fn test_with_weight() {
+        let mut statistics = ReplayStatistics::new();
+        statistics.update(&"felt252_add".to_string(), 3);
+        statistics.update(&"keccak_syscall".to_string(), 1);
+        statistics.update_generic(&"felt252_add".to_string(), 3);
+        statistics.update_cost(
+            &"felt252_add".to_string(),
+            &LibfuncCost { steps: 3, gas: 300 },
+        );
+        statistics.update_cost(
+            &"keccak_syscall".to_string(),
+            &LibfuncCost {
+                steps: 12,
+                gas: 1300,
+            },
+        );
+        statistics.update_generic_cost(
+            &"felt252_add".to_string(),
+            &LibfuncCost { steps: 3, gas: 300 },
+        );
+
+        let mut other = statistics.clone();
+        other.merge(&statistics);
+        assert_eq!(other.concrete_libfunc_cost["keccak_syscall"].steps, 24);
+
+        let calls = statistics.with_weight(LibfuncWeight::Calls).unwrap();
+        assert_eq!(calls, statistics);
+
+        let steps = statistics.with_weight(LibfuncWeight::Steps).unwrap();
+        assert_eq!(steps.get_libfunc_frequency("keccak_syscall"), 12);
+        assert_eq!(steps.generic_libfunc["felt252_add"], 3);
+        assert_eq!(steps.weight, LibfuncWeight::Steps);
+
+        let gas = statistics
+            .with_weight(LibfuncWeight::Gas)
+            .unwrap()
+            .with_granularity(LibfuncGranularity::Both)
+            .unwrap();
+        assert_eq!(gas.get_libfunc_frequency("keccak_syscall"), 1300);
+        assert_eq!(gas.get_libfunc_frequency("felt252_add (generic)"), 300);
+        assert_eq!(gas.concrete_libfunc_cost["felt252_add (generic)"].steps, 3);
+        assert!(matches!(
+            gas.with_weight(LibfuncWeight::Steps),
+            Err(ProfilerError::IncompatibleStatistics(_))
+        ));
+
+        // Statistics of different weight can't be merged.
+        let mut merged = steps.clone();
+        assert!(matches!(
+            merged.try_merge(&statistics),
+            Err(ProfilerError::IncompatibleStatistics(_))
+        ));
+        merged.try_merge(&steps).unwrap();
+        assert_eq!(merged.get_libfunc_frequency("keccak_syscall"), 24);
+
+        // The weight is restored from both CSV and JSON formats.
+        let csv_output = steps.to_csv_bytes().unwrap();
+        assert!(csv_output.starts_with(b"Function Name,Weight (steps; concrete)\n"));
+        let parsed_statistics = ReplayStatistics::from_csv_bytes(&csv_output).unwrap();
+        assert_eq!(parsed_statistics.weight, LibfuncWeight::Steps);
+        assert_eq!(
+            parsed_statistics.get_libfunc_frequency("keccak_syscall"),
+            12
+        );
+        let parsed_statistics = ReplayStatistics::from_json(&gas.to_json().unwrap()).unwrap();
+        assert_eq!(parsed_statistics, gas);
+
+        assert_eq!("gas".parse::<LibfuncWeight>(), Ok(LibfuncWeight::Gas));
+        assert!("time".parse::<LibfuncWeight>().is_err());
+    }
+
+    #[test]
+    fn test_from_csv_bytes() {
+        let input = "Function Name,Weight\n\"felt252_add\",3\n\nstore_temp<felt252>,2\n";
+        let statistics = ReplayStatistics::from_csv_bytes(input.as_bytes()).unwrap();
+        assert_eq!(statistics.get_libfunc_frequency("felt252_add"), 3);
+        assert_eq!(statistics.get_libfunc_frequency("store_temp<felt252>"), 2);
+
+        let missing_header = "felt252_add,3\n";
+        assert!(matches!(
+            ReplayStatistics::from_csv_bytes(missing_header.as_bytes()),
+            Err(ProfilerError::InvalidStatistics(1, _))
+        ));
+
+        let invalid_granularity = "Function Name,Weight (calls; specific)\nfelt252_add,3\n";
+        assert!(matches!(
+            ReplayStatistics::from_csv_bytes(invalid_granularity.as_bytes()),
+            Err(ProfilerError::InvalidStatistics(1, _))
+        ));
+
+        let invalid_weight = "Function Name,Weight\nfelt252_add,3\nfelt252_sub,-1\n";
+        assert!(matches!(
+            ReplayStatistics::from_csv_bytes(invalid_weight.as_bytes()),
+            Err(ProfilerError::InvalidStatistics(3, _))
+        ));
+    }
+
+    #[test]
+    fn test_from_json() {
+        let input = r#"{ "concrete_libfunc": { "felt252_add": 3 } }"#;
+        let statistics = ReplayStatistics::from_json(input).unwrap();
+        assert_eq!(statistics.get_libfunc_frequency("felt252_add"), 3);
+        assert!(statistics.generic_libfunc.is_empty());
+
+        let mut other = ReplayStatistics::new();
+        other.update(&"felt252_add".to_string(), 1);
+        other.update_cost(
+            &"felt252_add".to_string(),
+            &LibfuncCost { steps: 1, gas: 100 },
+        );
+        let mut merged = statistics.clone();
+        merged.merge(&ReplayStatistics::from_json(&other.to_json().unwrap()).unwrap());
+        assert_eq!(merged.get_libfunc_frequency("felt252_add"), 4);
+        assert_eq!(merged.concrete_libfunc_cost["felt252_add"].gas, 100);
+
+        assert!(ReplayStatistics::from_json(r#"{ "concrete_libfunc": [] }"#).is_err());
+    }
 }
@@ -1,11 +1,44 @@
-//! This module writes a report of the transactions replay. At the moment it
-//! exports [`ReplayStatistics`] to a text file.
+//! This module writes the reports of the transactions replay to file. Each
+//! report, such as [`ReplayStatistics`] in CSV format or [`FoldedStacks`] in
+//! pprof format, is serialised by its own type and written with
+//! [`write_report`]. JSON reports can be written together with the
+//! [`RunMetadata`] of the run.
 
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use starknet_api::core::ClassHash;
 
 use crate::error::ProfilerError;
+use crate::profiler::coverage::{to_lcov_bytes, ClassCoverage};
+use crate::profiler::flamegraph::FoldedStacks;
+use crate::profiler::pprof::to_pprof_bytes;
 use crate::profiler::replay_statistics::ReplayStatistics;
+use crate::profiler::run_metadata::RunMetadata;
+
+/// This function writes a report, such as the output of
+/// [`crate::profiler::contract_statistics::ContractStatistics::to_csv_bytes`]
+/// or [`crate::profiler::statistics_diff::StatisticsDiff::to_markdown_bytes`],
+/// to a file.
+///
+/// If the file already exists, it is overwritten.
+///
+/// # Arguments
+///
+/// - `filename`: the file to write.
+/// - `output`: the content of the report.
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - `filename` can't be written to.
+/// - The list of parent directories in `filename` doesn't exist.
+pub fn write_report(filename: &Path, output: impl AsRef<[u8]>) -> Result<(), ProfilerError> {
+    fs::write(filename, output)?;
+    Ok(())
+}
 
 /// This function writes a [`ReplayStatistics`] object in CSV format to a file.
 ///
@@ -26,8 +59,147 @@ pub fn write_to_file(
     filename: &PathBuf,
     replay_statistics: &ReplayStatistics,
 ) -> Result<(), ProfilerError> {
-    let output = replay_statistics.to_csv_bytes()?;
-    fs::write(filename, output)?;
+    write_report(filename, replay_statistics.to_csv_bytes()?)
+}
+
+/// This function reads a [`ReplayStatistics`] object from a file.
+///
+/// Files with extension `json` are read in the JSON format written by
+/// [`write_json_report`]. Any other file is read in the CSV format
+/// written by [`write_to_file`].
+///
+/// # Arguments
+///
+/// - `filename`: the file to read.
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - `filename` can't be read.
+/// - The content of `filename` can't be parsed.
+pub fn read_from_file(filename: &PathBuf) -> Result<ReplayStatistics, ProfilerError> {
+    let input = fs::read(filename)?;
+    if filename
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        let input = String::from_utf8_lossy(&input);
+        Ok(ReplayStatistics::from_json(&input)?)
+    } else {
+        ReplayStatistics::from_csv_bytes(&input)
+    }
+}
+
+/// This function reads the [`ReplayStatistics`] objects in `filenames` and
+/// merges them into one object.
+///
+/// # Arguments
+///
+/// - `filenames`: the files to read, in any of the formats accepted by
+///   [`read_from_file`].
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - Any of the files can't be read or parsed.
+/// - The statistics of the files have a different weight or granularity.
+pub fn read_and_merge_files(filenames: &[PathBuf]) -> Result<ReplayStatistics, ProfilerError> {
+    let mut merged_statistics = ReplayStatistics::new();
+    for filename in filenames {
+        merged_statistics.try_merge(&read_from_file(filename)?)?;
+    }
+    Ok(merged_statistics)
+}
+
+/// This function writes a JSON report to a file, placed below the metadata of
+/// the run if `metadata` isn't [`None`].
+///
+/// If the file already exists, it is overwritten.
+///
+/// # Arguments
+///
+/// - `filename`: the file to write.
+/// - `json`: the JSON report, such as the output of
+///   [`ReplayStatistics::to_json`] or
+///   [`crate::profiler::contract_statistics::ContractStatistics::to_json`].
+/// - `metadata`: the metadata of the run.
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - `json` isn't valid JSON.
+/// - `filename` can't be written to.
+/// - The list of parent directories in `filename` doesn't exist.
+pub fn write_json_report(
+    filename: &PathBuf,
+    json: &str,
+    metadata: Option<&RunMetadata>,
+) -> Result<(), ProfilerError> {
+    let output = match metadata {
+        Some(metadata) => metadata.wrap_json(json)?,
+        None => json.to_string(),
+    };
+    write_report(filename, output)
+}
+
+/// This function writes the Sierra statement coverage of a list of contract
+/// classes in lcov format to a file.
+///
+/// The Sierra program of each contract class is written as source file
+/// `<class hash>.sierra` in the directory of `filename`. If the files already
+/// exist, they are overwritten.
+///
+/// # Arguments
+///
+/// - `filename`: the file to write.
+/// - `coverage`: the coverage of each contract class.
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - `filename` or the source files can't be written to.
+/// - The list of parent directories in `filename` doesn't exist.
+pub fn write_coverage_lcov(
+    filename: &PathBuf,
+    coverage: &[ClassCoverage],
+) -> Result<(), ProfilerError> {
+    let source_directory = filename.parent().unwrap_or_else(|| Path::new(""));
+    for class_coverage in coverage {
+        write_report(
+            &source_directory.join(class_coverage.source_filename()),
+            class_coverage.to_source_bytes(),
+        )?;
+    }
+    write_report(filename, to_lcov_bytes(coverage, source_directory)?)
+}
+
+/// This function writes a pprof profile for each contract class in the
+/// directory `directory`. Each file is named `<class hash>.pb.gz`.
+///
+/// The directory is created if missing. Existing files are overwritten.
+///
+/// # Arguments
+///
+/// - `directory`: the directory to write the files to.
+/// - `folded_stacks`: the [`FoldedStacks`] object of each contract class.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the directory can't be created or any file can't be
+/// written to.
+pub fn write_pprof_by_class(
+    directory: &PathBuf,
+    folded_stacks: &BTreeMap<ClassHash, FoldedStacks>,
+) -> Result<(), ProfilerError> {
+    fs::create_dir_all(directory)?;
+    for (class_hash, class_folded_stacks) in folded_stacks {
+        let filename = directory.join(format!("{:#x}.pb.gz", class_hash.0));
+        write_report(&filename, to_pprof_bytes(class_folded_stacks)?)?;
+    }
     Ok(())
 }
 
@@ -64,4 +236,28 @@ mod tests {
         "};
         assert_eq!(read_file(&filename.into()), expected_string);
     }
+
+    #[test]
+    fn test_read_and_merge_files() {
+        let mut csv_statistics = ReplayStatistics::default();
+        csv_statistics.update(&"u32_to_felt252".to_string(), 759);
+        let mut json_statistics = ReplayStatistics::default();
+        json_statistics.update(&"u32_to_felt252".to_string(), 1);
+        json_statistics.update_generic(&"u32_to_felt252".to_string(), 1);
+
+        let csv_filename: PathBuf = "test_read_and_merge_files.csv".into();
+        let json_filename: PathBuf = "test_read_and_merge_files.json".into();
+        write_to_file(&csv_filename, &csv_statistics).unwrap();
+        write_json_report(&json_filename, &json_statistics.to_json().unwrap(), None).unwrap();
+
+        assert_eq!(read_from_file(&csv_filename).unwrap(), csv_statistics);
+        assert_eq!(read_from_file(&json_filename).unwrap(), json_statistics);
+
+        let merged_statistics = read_and_merge_files(&[csv_filename, json_filename]).unwrap();
+        assert_eq!(
+            merged_statistics.get_libfunc_frequency("u32_to_felt252"),
+            760
+        );
+        assert_eq!(merged_statistics.generic_libfunc["u32_to_felt252"], 1);
+    }
 }
@@ -0,0 +1,261 @@
+//! The module which saves the results of a replay in a SQLite database.
+//!
+//! The database contains the tables:
+//!
+//! - `transactions`: one row for each transaction replayed with the block, the
+//!   hash, the type, the revert status, the fee and the CASM steps.
+//! - `blocks`: one row for each block replayed with the Starknet version, the
+//!   number of transactions and the time spent replaying the block.
+//! - `class_libfuncs`: one row for each libfunc called by each contract class
+//!   with the weight of the libfunc, the kind of weight (e.g. `steps`) and the
+//!   granularity of the libfunc name.
+//!
+//! The database can be queried with SQL, for example to find the contract
+//! classes which call a libfunc the most or the transactions whose revert
+//! status doesn't match the one recorded on chain.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `write_results_database`
+
+use std::fs;
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection};
+
+use crate::error::ProfilerError;
+use crate::profiler::contract_statistics::ContractStatistics;
+use crate::runner::replay_record::ReplayRecords;
+
+/// The schema of the results database.
+///
+/// The fee is declared `NUMERIC` because it may not fit in a 64-bit integer.
+/// Fees that don't fit are stored as floating point numbers.
+const SCHEMA: &str = "
+    CREATE TABLE transactions (
+        block_number INTEGER NOT NULL,
+        transaction_index INTEGER NOT NULL,
+        transaction_hash TEXT NOT NULL,
+        transaction_type TEXT NOT NULL,
+        reverted INTEGER NOT NULL,
+        revert_status_matches INTEGER NOT NULL,
+        fee NUMERIC NOT NULL,
+        steps INTEGER NOT NULL,
+        PRIMARY KEY (block_number, transaction_index)
+    );
+    CREATE INDEX transactions_hash ON transactions (transaction_hash);
+    CREATE TABLE blocks (
+        block_number INTEGER PRIMARY KEY,
+        starknet_version TEXT NOT NULL,
+        transactions INTEGER NOT NULL,
+        replay_seconds REAL NOT NULL
+    );
+    CREATE TABLE class_libfuncs (
+        class_hash TEXT NOT NULL,
+        libfunc TEXT NOT NULL,
+        weight INTEGER NOT NULL,
+        weight_kind TEXT NOT NULL,
+        granularity TEXT NOT NULL,
+        PRIMARY KEY (class_hash, libfunc)
+    );
+";
+
+/// Writes the records of a replay and the libfunc usage statistics of each
+/// contract class in `connection`.
+///
+/// All the rows are inserted in a single SQL transaction.
+///
+/// # Arguments
+///
+/// - `connection`: The connection to an empty database.
+/// - `records`: The records of the blocks and transactions replayed.
+/// - `contract_statistics`: The libfunc usage statistics of each contract
+///   class.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the tables already exist or any row can't be inserted.
+fn write_to_connection(
+    connection: &mut Connection,
+    records: &ReplayRecords,
+    contract_statistics: &ContractStatistics,
+) -> Result<(), rusqlite::Error> {
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(SCHEMA)?;
+    {
+        let mut insert_transaction = transaction.prepare(
+            "INSERT INTO transactions (block_number, transaction_index, transaction_hash, \
+             transaction_type, reverted, revert_status_matches, fee, steps) VALUES (?1, ?2, ?3, \
+             ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for record in &records.transactions {
+            insert_transaction.execute(params![
+                record.block_number.get(),
+                record.transaction_index,
+                format!("{:#x}", record.transaction_hash.0),
+                record.transaction_type,
+                record.reverted,
+                record.revert_status_matches,
+                // The fee is bound as text so that SQLite converts it to the
+                // affinity of the column.
+                record.fee.to_string(),
+                record.steps,
+            ])?;
+        }
+
+        let mut insert_block = transaction.prepare(
+            "INSERT INTO blocks (block_number, starknet_version, transactions, replay_seconds) \
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for record in &records.blocks {
+            insert_block.execute(params![
+                record.block_number.get(),
+                record.starknet_version,
+                record.transactions,
+                record.elapsed.as_secs_f64(),
+            ])?;
+        }
+
+        let mut insert_libfunc = transaction.prepare(
+            "INSERT INTO class_libfuncs (class_hash, libfunc, weight, weight_kind, granularity) \
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (class_hash, statistics) in &contract_statistics.classes {
+            let class_hash = format!("{:#x}", class_hash.0);
+            let weight_kind = statistics.weight.to_string();
+            let granularity = statistics.granularity.to_string();
+            for (libfunc, weight) in &statistics.concrete_libfunc {
+                insert_libfunc.execute(params![
+                    class_hash,
+                    libfunc,
+                    weight,
+                    weight_kind,
+                    granularity
+                ])?;
+            }
+        }
+    }
+    transaction.commit()
+}
+
+/// This function writes the records of a replay and the libfunc usage
+/// statistics of each contract class to a SQLite database.
+///
+/// If the file already exists, it is overwritten.
+///
+/// # Arguments
+///
+/// - `filename`: The file of the database.
+/// - `records`: The records of the blocks and transactions replayed.
+/// - `contract_statistics`: The libfunc usage statistics of each contract
+///   class.
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - The existing `filename` can't be removed.
+/// - The database can't be created or written.
+pub fn write_results_database(
+    filename: &PathBuf,
+    records: &ReplayRecords,
+    contract_statistics: &ContractStatistics,
+) -> Result<(), ProfilerError> {
+    if filename.exists() {
+        fs::remove_file(filename)?;
+    }
+    let mut connection = Connection::open(filename)?;
+    write_to_connection(&mut connection, records, contract_statistics)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use starknet_api::core::ClassHash;
+    use starknet_api::transaction::TransactionHash;
+    use starknet_core::types::Felt;
+
+    use super::*;
+    use crate::block_number::BlockNumber;
+    use crate::profiler::replay_statistics::ReplayStatistics;
+    use crate::runner::replay_record::{BlockRecord, TransactionRecord};
+
+    #[test]
+    fn test_write_to_connection() {
+        let block_number = BlockNumber::new(632_917);
+        let mut records = ReplayRecords::default();
+        records.add_block(
+            BlockRecord {
+                block_number,
+                starknet_version: "0.13.1.1".to_string(),
+                transactions: 2,
+                elapsed: Duration::from_millis(1500),
+            },
+            vec![
+                TransactionRecord {
+                    block_number,
+                    transaction_index: 0,
+                    transaction_hash: TransactionHash(Felt::from(10_u64)),
+                    transaction_type: "INVOKE",
+                    reverted: false,
+                    revert_status_matches: true,
+                    fee: 1_000,
+                    steps: 200,
+                },
+                TransactionRecord {
+                    block_number,
+                    transaction_index: 1,
+                    transaction_hash: TransactionHash(Felt::from(11_u64)),
+                    transaction_type: "L1_HANDLER",
+                    reverted: true,
+                    revert_status_matches: false,
+                    fee: u128::MAX,
+                    steps: 50,
+                },
+            ],
+        );
+        let mut contract_statistics = ContractStatistics::new();
+        let mut statistics = ReplayStatistics::new();
+        statistics.update(&"u32_to_felt252", 7);
+        statistics.update(&"felt252_add", 3);
+        contract_statistics.add(ClassHash(Felt::from(1_u64)), &statistics);
+
+        let mut connection = Connection::open_in_memory().unwrap();
+        write_to_connection(&mut connection, &records, &contract_statistics).unwrap();
+
+        let steps: i64 = connection
+            .query_row("SELECT SUM(steps) FROM transactions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(steps, 250);
+        let mismatched: String = connection
+            .query_row(
+                "SELECT transaction_hash FROM transactions WHERE NOT revert_status_matches",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(mismatched, "0xb");
+        let fee: i64 = connection
+            .query_row(
+                "SELECT fee FROM transactions WHERE transaction_index = 0",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(fee, 1_000);
+        let replay_seconds: f64 = connection
+            .query_row("SELECT replay_seconds FROM blocks", [], |row| row.get(0))
+            .unwrap();
+        assert!((replay_seconds - 1.5).abs() < f64::EPSILON);
+        let (weight, weight_kind): (i64, String) = connection
+            .query_row(
+                "SELECT weight, weight_kind FROM class_libfuncs WHERE class_hash = '0x1' AND \
+                 libfunc = 'u32_to_felt252'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(weight, 7);
+        assert_eq!(weight_kind, "calls");
+    }
+}
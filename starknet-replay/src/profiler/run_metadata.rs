@@ -0,0 +1,198 @@
+//! The module which describes the context of a run of the profiler: the range
+//! of blocks, the network, the tool version and the settings used.
+//!
+//! The metadata is added to the JSON reports so that they can be ingested
+//! without losing the context of the run. The statistics are placed in the
+//! field `statistics` below the field `metadata`.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `RunMetadata`
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::block_number::BlockNumber;
+use crate::error::ProfilerError;
+use crate::runner::replay_record::ReplayRecords;
+use crate::runner::transaction_visited_pcs::TransactionVisitedPcs;
+use crate::storage::Storage;
+
+/// The struct to hold the metadata of a run of the profiler.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct RunMetadata {
+    /// The version of `starknet-replay`.
+    pub tool_version: String,
+
+    /// The chain id of the network, if it has been queried.
+    pub chain_id: Option<String>,
+
+    /// The first block of the run.
+    pub start_block: BlockNumber,
+
+    /// The last block (included) of the run.
+    pub end_block: BlockNumber,
+
+    /// The number of blocks replayed.
+    pub blocks: usize,
+
+    /// The number of transactions replayed.
+    pub transactions: usize,
+
+    /// The Starknet versions of the blocks replayed.
+    pub starknet_versions: BTreeSet<String>,
+
+    /// The settings of the run, such as the libfunc weight and granularity.
+    pub settings: BTreeMap<String, String>,
+}
+
+/// A JSON report together with the metadata of the run.
+#[derive(Debug, Serialize)]
+struct ReportWithMetadata<'a> {
+    /// The metadata of the run.
+    metadata: &'a RunMetadata,
+
+    /// The content of the report.
+    statistics: Value,
+}
+
+impl RunMetadata {
+    /// Initialisation of [`RunMetadata`].
+    ///
+    /// The chain id is empty until [`RunMetadata::query_chain_id`] is called.
+    /// The counts and the Starknet versions are empty until
+    /// [`RunMetadata::with_records`] or [`RunMetadata::with_transactions`] is
+    /// called.
+    ///
+    /// # Arguments
+    ///
+    /// - `start_block`: The first block of the run.
+    /// - `end_block`: The last block (included) of the run.
+    #[must_use]
+    pub fn new(start_block: BlockNumber, end_block: BlockNumber) -> Self {
+        RunMetadata {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            chain_id: None,
+            start_block,
+            end_block,
+            blocks: 0,
+            transactions: 0,
+            starknet_versions: BTreeSet::new(),
+            settings: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the metadata with the setting `name` set to `value`.
+    ///
+    /// # Arguments
+    ///
+    /// - `name`: The name of the setting.
+    /// - `value`: The value of the setting.
+    #[must_use]
+    pub fn with_setting(mut self, name: &str, value: &impl ToString) -> Self {
+        self.settings.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Returns the metadata with the number of blocks and transactions and
+    /// the Starknet versions taken from the records of a replay.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: The records of the blocks and transactions replayed.
+    #[must_use]
+    pub fn with_records(mut self, records: &ReplayRecords) -> Self {
+        self.blocks = records.blocks.len();
+        self.transactions = records.transactions.len();
+        self.starknet_versions = records
+            .blocks
+            .iter()
+            .map(|block| block.starknet_version.clone())
+            .collect();
+        self
+    }
+
+    /// Returns the metadata with the number of blocks and transactions taken
+    /// from the visited program counters of each transaction.
+    ///
+    /// Only the transactions in `transactions` are counted, i.e. the
+    /// transactions which visited any program counter of Sierra contract
+    /// classes. The Starknet versions are left empty because the block
+    /// headers are not available.
+    ///
+    /// # Arguments
+    ///
+    /// - `transactions`: The visited program counters of each transaction.
+    #[must_use]
+    pub fn with_transactions(mut self, transactions: &[TransactionVisitedPcs]) -> Self {
+        self.blocks = transactions
+            .iter()
+            .map(|transaction| transaction.block_number)
+            .collect::<BTreeSet<_>>()
+            .len();
+        self.transactions = transactions.len();
+        self.starknet_versions.clear();
+        self
+    }
+
+    /// Queries the chain id of the network.
+    ///
+    /// # Arguments
+    ///
+    /// - `storage`: The object to query the starknet blockchain using the RPC
+    ///   protocol.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the chain id can't be queried.
+    pub fn query_chain_id(&mut self, storage: &impl Storage) -> Result<(), ProfilerError> {
+        let chain_id = storage
+            .get_chain_id()
+            .map_err(|err| ProfilerError::Unknown(format!("Failed querying metadata: {err}")))?;
+        self.chain_id = Some(chain_id.to_string());
+        Ok(())
+    }
+
+    /// Returns the JSON report `statistics_json` placed below the metadata.
+    ///
+    /// # Arguments
+    ///
+    /// - `statistics_json`: A JSON report, such as the output of
+    ///   [`crate::profiler::replay_statistics::ReplayStatistics::to_json`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if `statistics_json` isn't valid JSON or there is an
+    /// error serialising to JSON.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use starknet_replay::block_number::BlockNumber;
+    /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+    /// # use starknet_replay::profiler::run_metadata::RunMetadata;
+    /// let mut replay_statistics = ReplayStatistics::default();
+    /// replay_statistics.update(&"u32_to_felt252".to_string(), 759);
+    /// let metadata = RunMetadata::new(BlockNumber::new(632917), BlockNumber::new(632918))
+    ///     .with_setting("libfunc_weight", &"calls");
+    /// let json_output = metadata
+    ///     .wrap_json(&replay_statistics.to_json().unwrap())
+    ///     .unwrap();
+    /// let value: serde_json::Value = serde_json::from_str(&json_output).unwrap();
+    /// assert_eq!(value["metadata"]["start_block"], 632917);
+    /// assert_eq!(value["metadata"]["settings"]["libfunc_weight"], "calls");
+    /// assert_eq!(
+    ///     value["statistics"]["concrete_libfunc"]["u32_to_felt252"],
+    ///     759
+    /// );
+    /// let parsed_statistics = ReplayStatistics::from_json(&json_output).unwrap();
+    /// assert_eq!(parsed_statistics, replay_statistics);
+    /// ```
+    pub fn wrap_json(&self, statistics_json: &str) -> Result<String, serde_json::Error> {
+        let report = ReportWithMetadata {
+            metadata: self,
+            statistics: serde_json::from_str(statistics_json)?,
+        };
+        serde_json::to_string_pretty(&report)
+    }
+}
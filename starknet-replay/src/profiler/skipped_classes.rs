@@ -0,0 +1,283 @@
+//! The module which collects the contract classes skipped by the libfunc
+//! analysis.
+//!
+//! The visited program counters of a skipped class aren't mapped to Sierra
+//! statements, therefore the libfunc usage statistics undercount the libfuncs
+//! called by the class. Reporting the skipped classes together with the number
+//! of visited program counters lost shows how reliable the statistics are.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `SkippedClasses`
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::io::Write;
+
+use itertools::Itertools;
+use serde::Serialize;
+use starknet_api::core::ClassHash;
+
+use crate::block_number::BlockNumber;
+
+/// The reason why a contract class has been skipped.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The contract class can't be queried from the storage.
+    QueryFailed,
+
+    /// The contract class is a legacy Cairo 0 class, which doesn't call
+    /// libfuncs.
+    Legacy,
+
+    /// The Sierra program can't be extracted from the contract class.
+    InvalidSierraProgram,
+
+    /// The Cairo 0 program can't be decompressed from the legacy contract
+    /// class.
+    InvalidLegacyProgram,
+
+    /// The Sierra program can't be compiled by the profiler.
+    CompilationFailed,
+
+    /// The program compiled by the profiler doesn't match the CASM bytecode
+    /// compiled by the node.
+    BytecodeMismatch,
+}
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::QueryFailed => write!(f, "query_failed"),
+            SkipReason::Legacy => write!(f, "legacy"),
+            SkipReason::InvalidSierraProgram => write!(f, "invalid_sierra_program"),
+            SkipReason::InvalidLegacyProgram => write!(f, "invalid_legacy_program"),
+            SkipReason::CompilationFailed => write!(f, "compilation_failed"),
+            SkipReason::BytecodeMismatch => write!(f, "bytecode_mismatch"),
+        }
+    }
+}
+
+/// A contract class skipped by the libfunc analysis.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct SkippedClass {
+    /// The class hash of the contract.
+    pub class_hash: ClassHash,
+
+    /// The first block in which the class has been skipped.
+    pub block_number: BlockNumber,
+
+    /// The reason why the class has been skipped.
+    pub reason: SkipReason,
+
+    /// The description of the error which caused the class to be skipped.
+    pub message: String,
+
+    /// The number of visited program counters of the class which haven't been
+    /// analysed.
+    pub visited_pcs: usize,
+}
+
+/// The struct to hold the contract classes skipped by the libfunc analysis.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct SkippedClasses {
+    /// This field contains the association between class hash (key) and the
+    /// skipped class (value).
+    pub classes: BTreeMap<ClassHash, SkippedClass>,
+}
+
+impl SkippedClasses {
+    /// Initialisation of [`SkippedClasses`].
+    ///
+    /// The struct is initialised with all fields empty.
+    #[must_use]
+    pub fn new() -> Self {
+        SkippedClasses {
+            classes: BTreeMap::default(),
+        }
+    }
+
+    /// Adds a skipped contract class.
+    ///
+    /// If the class has already been skipped, the visited program counters are
+    /// summed and the reason of the earliest block is kept.
+    ///
+    /// # Arguments
+    ///
+    /// - `skipped_class`: The class skipped.
+    pub fn add(&mut self, skipped_class: &SkippedClass) {
+        self.classes
+            .entry(skipped_class.class_hash)
+            .and_modify(|existing| {
+                existing.visited_pcs += skipped_class.visited_pcs;
+                if skipped_class.block_number < existing.block_number {
+                    existing.block_number = skipped_class.block_number;
+                    existing.reason = skipped_class.reason;
+                    existing.message.clone_from(&skipped_class.message);
+                }
+            })
+            .or_insert_with(|| skipped_class.clone());
+    }
+
+    /// Update the object with data in `from`.
+    ///
+    /// # Arguments
+    ///
+    /// - `from`: Input `SkippedClasses` to get data from.
+    pub fn merge(&mut self, from: &SkippedClasses) {
+        for skipped_class in from.classes.values() {
+            self.add(skipped_class);
+        }
+    }
+
+    /// Returns `true` if no class has been skipped.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// Returns the number of visited program counters of the skipped classes.
+    #[must_use]
+    pub fn total_visited_pcs(&self) -> usize {
+        self.classes
+            .values()
+            .map(|skipped_class| skipped_class.visited_pcs)
+            .sum()
+    }
+
+    /// Returns the classes skipped for reasons other than being legacy Cairo 0
+    /// classes.
+    ///
+    /// Legacy classes don't call libfuncs, therefore only these classes cause
+    /// the libfunc usage statistics to undercount.
+    #[must_use]
+    pub fn undercounted(&self) -> Vec<&SkippedClass> {
+        self.classes
+            .values()
+            .filter(|skipped_class| skipped_class.reason != SkipReason::Legacy)
+            .collect()
+    }
+
+    /// Returns the skipped classes sorted by descending number of visited
+    /// program counters.
+    fn classes_by_visited_pcs(&self) -> Vec<&SkippedClass> {
+        self.classes
+            .values()
+            .sorted_by(|a, b| {
+                Ord::cmp(&b.visited_pcs, &a.visited_pcs)
+                    .then(Ord::cmp(&a.class_hash, &b.class_hash))
+            })
+            .collect()
+    }
+
+    /// Serialises [`SkippedClasses`] to CSV format.
+    ///
+    /// Classes are sorted by descending number of visited program counters.
+    /// The message is quoted because it may contain commas.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str;
+    /// # use indoc::indoc;
+    /// # use starknet_api::core::ClassHash;
+    /// # use starknet_core::types::Felt;
+    /// # use starknet_replay::block_number::BlockNumber;
+    /// # use starknet_replay::profiler::skipped_classes::{SkipReason, SkippedClass, SkippedClasses};
+    /// let mut skipped_classes = SkippedClasses::new();
+    /// skipped_classes.add(&SkippedClass {
+    ///     class_hash: ClassHash(Felt::from(10_u64)),
+    ///     block_number: BlockNumber::new(632917),
+    ///     reason: SkipReason::QueryFailed,
+    ///     message: "Class not found".to_string(),
+    ///     visited_pcs: 120,
+    /// });
+    /// let expected_string = indoc! {r#"
+    ///     Class Hash,Block Number,Reason,Visited PCs,Message
+    ///     0xa,632917,query_failed,120,"Class not found"
+    /// "#};
+    /// let csv_output = skipped_classes.to_csv_bytes().unwrap();
+    /// assert_eq!(
+    ///     str::from_utf8(csv_output.as_slice()).unwrap(),
+    ///     expected_string
+    /// );
+    /// ```
+    pub fn to_csv_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut f = Vec::new();
+        writeln!(f, "Class Hash,Block Number,Reason,Visited PCs,Message")?;
+        for skipped_class in self.classes_by_visited_pcs() {
+            let message = skipped_class
+                .message
+                .replace('"', "\"\"")
+                .replace('\n', " ");
+            writeln!(
+                f,
+                "{:#x},{},{},{},\"{message}\"",
+                skipped_class.class_hash.0,
+                skipped_class.block_number,
+                skipped_class.reason,
+                skipped_class.visited_pcs,
+            )?;
+        }
+        Ok(f)
+    }
+
+    /// Serialises [`SkippedClasses`] to JSON format.
+    ///
+    /// Classes are sorted by descending number of visited program counters.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an error serialising to JSON.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(&self.classes_by_visited_pcs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_core::types::Felt;
+
+    use super::*;
+
+    fn skipped_class(
+        class_hash: u64,
+        block_number: u64,
+        reason: SkipReason,
+        visited_pcs: usize,
+    ) -> SkippedClass {
+        SkippedClass {
+            class_hash: ClassHash(Felt::from(class_hash)),
+            block_number: BlockNumber::new(block_number),
+            reason,
+            message: reason.to_string(),
+            visited_pcs,
+        }
+    }
+
+    #[test]
+    fn test_skipped_classes() {
+        let mut skipped_classes = SkippedClasses::new();
+        skipped_classes.add(&skipped_class(1, 20, SkipReason::BytecodeMismatch, 5));
+        let mut other = SkippedClasses::new();
+        other.add(&skipped_class(1, 10, SkipReason::QueryFailed, 7));
+        other.add(&skipped_class(2, 10, SkipReason::Legacy, 100));
+        skipped_classes.merge(&other);
+
+        assert_eq!(skipped_classes.classes.len(), 2);
+        assert_eq!(skipped_classes.total_visited_pcs(), 112);
+        let first = &skipped_classes.classes[&ClassHash(Felt::from(1_u64))];
+        assert_eq!(first.visited_pcs, 12);
+        assert_eq!(first.block_number, BlockNumber::new(10));
+        assert_eq!(first.reason, SkipReason::QueryFailed);
+
+        let undercounted = skipped_classes.undercounted();
+        assert_eq!(undercounted.len(), 1);
+        assert_eq!(undercounted[0].class_hash, ClassHash(Felt::from(1_u64)));
+
+        let csv = String::from_utf8(skipped_classes.to_csv_bytes().unwrap()).unwrap();
+        assert_eq!(csv.lines().nth(1).unwrap(), "0x2,10,legacy,100,\"legacy\"");
+    }
+}
@@ -0,0 +1,323 @@
+//! The module which compares the libfunc usage statistics of two replays, for
+//! example of two ranges of blocks.
+//!
+//! Libfuncs are compared by their share of the total weight of each replay
+//! because the replays may have a different number of transactions.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `StatisticsDiff`
+
+use std::collections::BTreeSet;
+use std::io::Write;
+
+use itertools::Itertools;
+
+use crate::error::ProfilerError;
+use crate::profiler::replay_statistics::{share, ReplayStatistics};
+
+/// The number of libfuncs reported in the list of biggest movers of the
+/// Markdown report and of the chart.
+pub const BIGGEST_MOVERS: usize = 20;
+
+/// The change of a libfunc between two replays.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibfuncChange {
+    /// The name of the libfunc.
+    pub name: String,
+
+    /// The weight of the libfunc in the first replay.
+    pub before: usize,
+
+    /// The weight of the libfunc in the second replay.
+    pub after: usize,
+
+    /// The ratio between the weight of the libfunc and the total weight of the
+    /// first replay.
+    pub before_share: f64,
+
+    /// The ratio between the weight of the libfunc and the total weight of the
+    /// second replay.
+    pub after_share: f64,
+}
+impl LibfuncChange {
+    /// Returns the absolute change in share, `after_share - before_share`.
+    #[must_use]
+    pub fn share_change(&self) -> f64 {
+        self.after_share - self.before_share
+    }
+
+    /// Returns the change in share relative to the share in the first replay.
+    ///
+    /// Returns [`None`] if the libfunc isn't used in the first replay.
+    #[must_use]
+    pub fn relative_share_change(&self) -> Option<f64> {
+        (self.before > 0).then(|| self.share_change() / self.before_share)
+    }
+}
+
+/// The struct to compare the libfunc usage statistics of two replays.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct StatisticsDiff {
+    /// The libfunc usage statistics of the first replay.
+    pub before: ReplayStatistics,
+
+    /// The libfunc usage statistics of the second replay.
+    pub after: ReplayStatistics,
+}
+
+impl StatisticsDiff {
+    /// Initialisation of [`StatisticsDiff`].
+    ///
+    /// # Arguments
+    ///
+    /// - `before`: The libfunc usage statistics of the first replay.
+    /// - `after`: The libfunc usage statistics of the second replay.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if `before` and `after` have a different weight or
+    /// granularity, because their weights can't be compared.
+    pub fn new(before: ReplayStatistics, after: ReplayStatistics) -> Result<Self, ProfilerError> {
+        before.check_compatible(&after)?;
+        Ok(StatisticsDiff { before, after })
+    }
+
+    /// Returns the libfuncs used in the second replay but not in the first
+    /// one, sorted by name.
+    #[must_use]
+    pub fn added(&self) -> Vec<&str> {
+        self.after
+            .get_libfuncs()
+            .into_iter()
+            .filter(|name| self.before.get_libfunc_frequency(name) == 0)
+            .sorted()
+            .collect()
+    }
+
+    /// Returns the libfuncs used in the first replay but not in the second
+    /// one, sorted by name.
+    #[must_use]
+    pub fn removed(&self) -> Vec<&str> {
+        self.before
+            .get_libfuncs()
+            .into_iter()
+            .filter(|name| self.after.get_libfunc_frequency(name) == 0)
+            .sorted()
+            .collect()
+    }
+
+    /// Returns the change of each libfunc used in either replay sorted by
+    /// descending absolute change in share.
+    #[must_use]
+    pub fn changes(&self) -> Vec<LibfuncChange> {
+        let total_before: usize = self.before.concrete_libfunc.values().sum();
+        let total_after: usize = self.after.concrete_libfunc.values().sum();
+        let names: BTreeSet<&str> = self
+            .before
+            .get_libfuncs()
+            .into_iter()
+            .chain(self.after.get_libfuncs())
+            .collect();
+        names
+            .into_iter()
+            .map(|name| {
+                let before = self.before.get_libfunc_frequency(name);
+                let after = self.after.get_libfunc_frequency(name);
+                LibfuncChange {
+                    name: name.to_string(),
+                    before,
+                    after,
+                    before_share: share(before, total_before),
+                    after_share: share(after, total_after),
+                }
+            })
+            .sorted_by(|a, b| {
+                b.share_change()
+                    .abs()
+                    .total_cmp(&a.share_change().abs())
+                    .then(Ord::cmp(&a.name, &b.name))
+            })
+            .collect()
+    }
+
+    /// Serialises [`StatisticsDiff`] to CSV format.
+    ///
+    /// Libfuncs are reported in descending order of absolute change in share.
+    /// The relative change in share is empty for libfuncs not used in the
+    /// first replay.
+    ///
+    /// The function name is quoted because it may contain commas.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str;
+    /// # use indoc::indoc;
+    /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+    /// # use starknet_replay::profiler::statistics_diff::StatisticsDiff;
+    /// let mut before = ReplayStatistics::default();
+    /// before.update(&"felt252_add".to_string(), 3);
+    /// before.update(&"u256_safe_divmod".to_string(), 1);
+    /// let mut after = ReplayStatistics::default();
+    /// after.update(&"felt252_add".to_string(), 4);
+    /// let expected_string = indoc! {r#"
+    ///     Function Name,Before,After,Before Share,After Share,Share Change,Relative Share Change
+    ///     "felt252_add",3,4,0.750000,1.000000,0.250000,0.333333
+    ///     "u256_safe_divmod",1,0,0.250000,0.000000,-0.250000,-1.000000
+    /// "#};
+    /// let diff = StatisticsDiff::new(before, after).unwrap();
+    /// let csv_output = diff.to_csv_bytes().unwrap();
+    /// assert_eq!(
+    ///     str::from_utf8(csv_output.as_slice()).unwrap(),
+    ///     expected_string
+    /// );
+    /// ```
+    pub fn to_csv_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut f = Vec::new();
+        writeln!(
+            f,
+            "Function Name,Before,After,Before Share,After Share,Share Change,Relative Share \
+             Change"
+        )?;
+        for change in self.changes() {
+            let relative_share_change = change
+                .relative_share_change()
+                .map(|relative| format!("{relative:.6}"))
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "\"{}\",{},{},{:.6},{:.6},{:.6},{relative_share_change}",
+                change.name,
+                change.before,
+                change.after,
+                change.before_share,
+                change.after_share,
+                change.share_change()
+            )?;
+        }
+        Ok(f)
+    }
+
+    /// Serialises [`StatisticsDiff`] to Markdown format.
+    ///
+    /// The report contains the total weight of each replay, the libfuncs added
+    /// and removed and the [`BIGGEST_MOVERS`] libfuncs with the biggest
+    /// absolute change in share. Shares are reported as percentages.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    pub fn to_markdown_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut f = Vec::new();
+        writeln!(f, "# Libfunc statistics diff")?;
+        writeln!(f)?;
+        writeln!(f, "| | Before | After |")?;
+        writeln!(f, "|---|---:|---:|")?;
+        writeln!(
+            f,
+            "| Total weight | {} | {} |",
+            self.before.concrete_libfunc.values().sum::<usize>(),
+            self.after.concrete_libfunc.values().sum::<usize>()
+        )?;
+        writeln!(
+            f,
+            "| Libfuncs | {} | {} |",
+            self.before.get_number_of_libfuncs(),
+            self.after.get_number_of_libfuncs()
+        )?;
+
+        for (title, libfuncs) in [
+            ("Added libfuncs", self.added()),
+            ("Removed libfuncs", self.removed()),
+        ] {
+            writeln!(f)?;
+            writeln!(f, "## {title}")?;
+            writeln!(f)?;
+            if libfuncs.is_empty() {
+                writeln!(f, "None.")?;
+            }
+            for name in libfuncs {
+                writeln!(f, "- `{name}`")?;
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(f, "## Biggest movers")?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "| Rank | Function Name | Before Share | After Share | Share Change | Relative Share \
+             Change |"
+        )?;
+        writeln!(f, "|---:|---|---:|---:|---:|---:|")?;
+        for (rank, change) in self.changes().iter().take(BIGGEST_MOVERS).enumerate() {
+            let relative_share_change = change.relative_share_change().map_or_else(
+                || "new".to_string(),
+                |relative| format!("{:+.2}%", relative * 100.0),
+            );
+            writeln!(
+                f,
+                "| {} | `{}` | {:.2}% | {:.2}% | {:+.2} pp | {relative_share_change} |",
+                rank + 1,
+                change.name,
+                change.before_share * 100.0,
+                change.after_share * 100.0,
+                change.share_change() * 100.0
+            )?;
+        }
+        Ok(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profiler::replay_statistics::LibfuncGranularity;
+
+    #[test]
+    fn test_statistics_diff() {
+        let mut before = ReplayStatistics::new();
+        before.update(&"felt252_add".to_string(), 2);
+        before.update(&"store_temp".to_string(), 2);
+        before.update(&"u256_safe_divmod".to_string(), 4);
+        let mut after = ReplayStatistics::new();
+        after.update(&"felt252_add".to_string(), 2);
+        after.update(&"store_temp".to_string(), 1);
+        after.update(&"keccak_syscall".to_string(), 1);
+
+        let diff = StatisticsDiff::new(before, after).unwrap();
+
+        assert_eq!(diff.added(), vec!["keccak_syscall"]);
+        assert_eq!(diff.removed(), vec!["u256_safe_divmod"]);
+
+        let changes = diff.changes();
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes[0].name, "u256_safe_divmod");
+        assert!((changes[0].share_change() + 0.5).abs() < f64::EPSILON);
+        assert_eq!(changes[1].name, "felt252_add");
+        assert!((changes[1].relative_share_change().unwrap() - 1.0).abs() < f64::EPSILON);
+        let keccak = changes
+            .iter()
+            .find(|change| change.name == "keccak_syscall")
+            .unwrap();
+        assert_eq!(keccak.relative_share_change(), None);
+
+        let markdown = String::from_utf8(diff.to_markdown_bytes().unwrap()).unwrap();
+        assert!(markdown.contains("## Added libfuncs\n\n- `keccak_syscall`\n"));
+        assert!(markdown.contains("| 1 | `u256_safe_divmod` | 50.00% | 0.00% | -50.00 pp |"));
+
+        // Concrete and generic libfunc names can't be compared.
+        let mut generic = ReplayStatistics::new();
+        generic.update_generic(&"store_temp".to_string(), 3);
+        let generic = generic
+            .with_granularity(LibfuncGranularity::Generic)
+            .unwrap();
+        assert!(matches!(
+            StatisticsDiff::new(diff.before, generic),
+            Err(ProfilerError::IncompatibleStatistics(_))
+        ));
+    }
+}
@@ -0,0 +1,383 @@
+//! The module which provides the libfunc usage statistics over time.
+//!
+//! The replayed blocks are grouped in buckets of a fixed number of blocks or
+//! by the timestamp of the block (day or week). Each bucket has its own
+//! libfunc usage statistics.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `TimeSeriesStatistics`
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::io::Write;
+use std::str::FromStr;
+
+use itertools::Itertools;
+
+use crate::error::ProfilerError;
+use crate::profiler::replay_statistics::{LibfuncGranularity, LibfuncWeight, ReplayStatistics};
+
+/// The number of seconds in a day.
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The number of days in a week.
+const DAYS_PER_WEEK: u64 = 7;
+
+/// The size of the buckets used to group the replayed blocks.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BucketSize {
+    /// Buckets of a fixed number of consecutive blocks.
+    Blocks(u64),
+
+    /// Buckets of the blocks with a timestamp in the same UTC day.
+    Day,
+
+    /// Buckets of the blocks with a timestamp in the same UTC week, starting
+    /// on Monday.
+    Week,
+}
+impl BucketSize {
+    /// Returns `true` if the bucket of a block depends on the block
+    /// timestamp.
+    #[must_use]
+    pub fn needs_timestamp(&self) -> bool {
+        !matches!(self, BucketSize::Blocks(_))
+    }
+
+    /// Returns the bucket of a block.
+    ///
+    /// The bucket is identified by its first block number if the size is a
+    /// number of blocks. Otherwise, it's identified by the number of days
+    /// since the Unix epoch of its first day.
+    ///
+    /// # Arguments
+    ///
+    /// - `block_number`: The block number.
+    /// - `timestamp`: The timestamp of the block in seconds since the Unix
+    ///   epoch. It's ignored if the size is a number of blocks.
+    #[must_use]
+    pub fn bucket(&self, block_number: u64, timestamp: u64) -> u64 {
+        match self {
+            BucketSize::Blocks(blocks) => block_number - block_number % blocks,
+            BucketSize::Day => timestamp / SECONDS_PER_DAY,
+            BucketSize::Week => {
+                let day = timestamp / SECONDS_PER_DAY;
+                // The Unix epoch is a Thursday, 3 days after Monday. The days
+                // before the first Monday after the epoch are grouped in the
+                // bucket of the epoch.
+                ((day + 3) / DAYS_PER_WEEK * DAYS_PER_WEEK).saturating_sub(3)
+            }
+        }
+    }
+
+    /// Returns the label of a bucket.
+    ///
+    /// The label is the first block number of the bucket if the size is a
+    /// number of blocks. Otherwise, it's the date of the first day of the
+    /// bucket in format `YYYY-MM-DD`.
+    ///
+    /// # Arguments
+    ///
+    /// - `bucket`: The bucket as returned by [`BucketSize::bucket`].
+    #[must_use]
+    pub fn label(&self, bucket: u64) -> String {
+        match self {
+            BucketSize::Blocks(_) => bucket.to_string(),
+            BucketSize::Day | BucketSize::Week => {
+                let (year, month, day) = civil_from_days(bucket);
+                format!("{year:04}-{month:02}-{day:02}")
+            }
+        }
+    }
+}
+impl Display for BucketSize {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            BucketSize::Blocks(blocks) => write!(f, "{blocks}"),
+            BucketSize::Day => write!(f, "day"),
+            BucketSize::Week => write!(f, "week"),
+        }
+    }
+}
+impl FromStr for BucketSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(BucketSize::Day),
+            "week" => Ok(BucketSize::Week),
+            _ => match s.parse::<u64>() {
+                Ok(blocks) if blocks > 0 => Ok(BucketSize::Blocks(blocks)),
+                _ => Err(format!(
+                    "Invalid bucket size `{s}`. Expected a positive number of blocks, `day` or \
+                     `week`."
+                )),
+            },
+        }
+    }
+}
+
+/// Returns the date `(year, month, day)` of the day `days` since the Unix
+/// epoch in the proleptic Gregorian calendar.
+///
+/// # Arguments
+///
+/// - `days`: The number of days since 1970-01-01.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Algorithm from http://howardhinnant.github.io/date_algorithms.html shifted
+    // to start the year in March so that the leap day is the last day of the
+    // year.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// The struct to hold the libfunc usage statistics of each bucket of blocks.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TimeSeriesStatistics {
+    /// The size of the buckets.
+    pub bucket_size: BucketSize,
+
+    /// This field contains the association between the bucket (key) and the
+    /// libfunc usage statistics of the blocks in the bucket (value).
+    pub buckets: BTreeMap<u64, ReplayStatistics>,
+}
+
+impl TimeSeriesStatistics {
+    /// Initialisation of [`TimeSeriesStatistics`].
+    ///
+    /// The struct is initialised with field `buckets` empty.
+    ///
+    /// # Arguments
+    ///
+    /// - `bucket_size`: The size of the buckets.
+    #[must_use]
+    pub fn new(bucket_size: BucketSize) -> Self {
+        TimeSeriesStatistics {
+            bucket_size,
+            buckets: BTreeMap::default(),
+        }
+    }
+
+    /// Adds the libfunc usage statistics of a bucket.
+    ///
+    /// If `bucket` already exists, the statistics are merged.
+    ///
+    /// # Arguments
+    ///
+    /// - `bucket`: The bucket as returned by [`BucketSize::bucket`].
+    /// - `statistics`: The libfunc usage statistics to add.
+    pub fn add(&mut self, bucket: u64, statistics: &ReplayStatistics) {
+        self.buckets.entry(bucket).or_default().merge(statistics);
+    }
+
+    /// Update the object with data in `from`.
+    ///
+    /// # Arguments
+    ///
+    /// - `from`: Input `TimeSeriesStatistics` to get data from.
+    pub fn merge(&mut self, from: &TimeSeriesStatistics) {
+        for (bucket, statistics) in &from.buckets {
+            self.add(*bucket, statistics);
+        }
+    }
+
+    /// Returns the statistics with the libfunc names of the chosen
+    /// granularity.
+    ///
+    /// # Arguments
+    ///
+    /// - `granularity`: The granularity of the libfunc names.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the granularity can't be selected for the statistics
+    /// of each bucket, see [`ReplayStatistics::with_granularity`].
+    pub fn with_granularity(
+        &self,
+        granularity: LibfuncGranularity,
+    ) -> Result<TimeSeriesStatistics, ProfilerError> {
+        Ok(TimeSeriesStatistics {
+            bucket_size: self.bucket_size,
+            buckets: self
+                .buckets
+                .iter()
+                .map(|(bucket, statistics)| {
+                    Ok((*bucket, statistics.with_granularity(granularity)?))
+                })
+                .collect::<Result<_, ProfilerError>>()?,
+        })
+    }
+
+    /// Returns the statistics with the chosen weight of each libfunc.
+    ///
+    /// # Arguments
+    ///
+    /// - `weight`: The weight of the libfuncs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the weight can't be selected for the statistics of
+    /// each bucket, see [`ReplayStatistics::with_weight`].
+    pub fn with_weight(
+        &self,
+        weight: LibfuncWeight,
+    ) -> Result<TimeSeriesStatistics, ProfilerError> {
+        Ok(TimeSeriesStatistics {
+            bucket_size: self.bucket_size,
+            buckets: self
+                .buckets
+                .iter()
+                .map(|(bucket, statistics)| Ok((*bucket, statistics.with_weight(weight)?)))
+                .collect::<Result<_, ProfilerError>>()?,
+        })
+    }
+
+    /// Returns the labels of the buckets in chronological order.
+    #[must_use]
+    pub fn labels(&self) -> Vec<String> {
+        self.buckets
+            .keys()
+            .map(|bucket| self.bucket_size.label(*bucket))
+            .collect()
+    }
+
+    /// Returns the `count` libfuncs with the highest weight over all buckets
+    /// in descending order of weight.
+    ///
+    /// # Arguments
+    ///
+    /// - `count`: The maximum number of libfuncs returned.
+    #[must_use]
+    pub fn top_libfuncs(&self, count: usize) -> Vec<&str> {
+        let mut totals: HashMap<&str, usize> = HashMap::new();
+        for statistics in self.buckets.values() {
+            for (name, weight) in &statistics.concrete_libfunc {
+                *totals.entry(name.as_str()).or_default() += weight;
+            }
+        }
+        totals
+            .into_iter()
+            .sorted_by(|a, b| Ord::cmp(&b.1, &a.1).then(Ord::cmp(a.0, b.0)))
+            .take(count)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Serialises [`TimeSeriesStatistics`] to CSV format.
+    ///
+    /// The output is in long format: one row for each pair of bucket and
+    /// libfunc. Buckets are reported in chronological order. For each bucket,
+    /// libfuncs are reported in descending order of weight.
+    ///
+    /// The function name is quoted because it may contain commas.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str;
+    /// # use indoc::indoc;
+    /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+    /// # use starknet_replay::profiler::time_series_statistics::{BucketSize, TimeSeriesStatistics};
+    /// let mut statistics = ReplayStatistics::default();
+    /// statistics.update(&"u256_safe_divmod".to_string(), 3);
+    /// statistics.update(&"felt252_add".to_string(), 5);
+    /// let mut time_series = TimeSeriesStatistics::new(BucketSize::Blocks(100));
+    /// time_series.add(632_100, &statistics);
+    /// time_series.add(632_000, &statistics);
+    /// let expected_string = indoc! {r#"
+    ///     Bucket,Function Name,Weight
+    ///     632000,"felt252_add",5
+    ///     632000,"u256_safe_divmod",3
+    ///     632100,"felt252_add",5
+    ///     632100,"u256_safe_divmod",3
+    /// "#};
+    /// let csv_output = time_series.to_csv_bytes().unwrap();
+    /// assert_eq!(
+    ///     str::from_utf8(csv_output.as_slice()).unwrap(),
+    ///     expected_string
+    /// );
+    /// ```
+    pub fn to_csv_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut f = Vec::new();
+        writeln!(f, "Bucket,Function Name,Weight")?;
+        for (bucket, statistics) in &self.buckets {
+            let label = self.bucket_size.label(*bucket);
+            for (concrete_name, weight) in statistics
+                .concrete_libfunc
+                .iter()
+                .sorted_by(|a, b| Ord::cmp(&b.1, &a.1).then(Ord::cmp(a.0, b.0)))
+            {
+                writeln!(f, "{label},\"{concrete_name}\",{weight}")?;
+            }
+        }
+        Ok(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_size() {
+        assert_eq!("100".parse::<BucketSize>(), Ok(BucketSize::Blocks(100)));
+        assert_eq!("day".parse::<BucketSize>(), Ok(BucketSize::Day));
+        assert_eq!("week".parse::<BucketSize>(), Ok(BucketSize::Week));
+        assert!("0".parse::<BucketSize>().is_err());
+        assert!("month".parse::<BucketSize>().is_err());
+
+        assert_eq!(BucketSize::Blocks(100).bucket(632_917, 0), 632_900);
+
+        // Monday 2024-04-15 07:53:40 UTC.
+        let timestamp = 1_713_167_620;
+        let day = BucketSize::Day.bucket(0, timestamp);
+        assert_eq!(BucketSize::Day.label(day), "2024-04-15");
+        // Sunday 2024-04-21 07:53:40 UTC.
+        let next_sunday = timestamp + 6 * SECONDS_PER_DAY;
+        let week = BucketSize::Week.bucket(0, next_sunday);
+        assert_eq!(week, day);
+        assert_eq!(BucketSize::Week.label(week), "2024-04-15");
+        // Saturday 1970-01-03 and Monday 1970-01-05.
+        assert_eq!(BucketSize::Week.bucket(0, 2 * SECONDS_PER_DAY), 0);
+        assert_eq!(BucketSize::Week.bucket(0, 4 * SECONDS_PER_DAY), 4);
+        assert_eq!(BucketSize::Day.label(0), "1970-01-01");
+        assert_eq!(BucketSize::Day.label(11_016), "2000-02-29");
+    }
+
+    #[test]
+    fn test_top_libfuncs() {
+        let mut first = ReplayStatistics::new();
+        first.update(&"felt252_add".to_string(), 2);
+        first.update(&"store_temp".to_string(), 3);
+        let mut second = ReplayStatistics::new();
+        second.update(&"felt252_add".to_string(), 4);
+
+        let mut time_series = TimeSeriesStatistics::new(BucketSize::Blocks(10));
+        time_series.add(10, &first);
+        time_series.add(20, &second);
+        time_series.add(20, &second);
+
+        assert_eq!(time_series.top_libfuncs(1), vec!["felt252_add"]);
+        assert_eq!(time_series.labels(), vec!["10", "20"]);
+        assert_eq!(
+            time_series.buckets[&20].get_libfunc_frequency("felt252_add"),
+            8
+        );
+    }
+}
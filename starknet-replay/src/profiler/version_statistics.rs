@@ -0,0 +1,242 @@
+//! The module which provides the breakdown of libfunc usage statistics by
+//! Sierra version and compiler version of the contract classes.
+//!
+//! The versions are read from the header of the Sierra program of each class.
+//! Classes compiled with the same pair of versions are reported together.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `VersionStatistics`
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::io::Write;
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::profiler::replay_statistics::ReplayStatistics;
+
+/// The Sierra version and the compiler version of a contract class.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default, Serialize)]
+pub struct ClassVersion {
+    /// The version of the Sierra language (e.g. `1.6.0`).
+    pub sierra_version: String,
+
+    /// The version of the compiler which generated the Sierra program (e.g.
+    /// `2.7.0`).
+    pub compiler_version: String,
+}
+impl Display for ClassVersion {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Sierra {} (compiler {})",
+            self.sierra_version, self.compiler_version
+        )
+    }
+}
+
+/// The libfunc usage statistics of the contract classes with the same
+/// versions.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct VersionEntry {
+    /// The number of contract classes with these versions.
+    pub classes: usize,
+
+    /// The libfunc usage statistics of the contract classes.
+    pub statistics: ReplayStatistics,
+}
+
+/// The struct to hold the libfunc usage statistics of each pair of Sierra
+/// version and compiler version.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct VersionStatistics {
+    /// This field contains the association between the versions (key) and the
+    /// libfunc usage statistics of the classes with these versions (value).
+    pub versions: BTreeMap<ClassVersion, VersionEntry>,
+}
+
+/// The libfunc usage of a pair of versions in the JSON report.
+#[derive(Debug, Serialize)]
+struct VersionReport<'a> {
+    /// The Sierra version and the compiler version.
+    #[serde(flatten)]
+    version: &'a ClassVersion,
+
+    /// The number of contract classes with these versions.
+    classes: usize,
+
+    /// The number of libfunc calls of the contract classes.
+    total_calls: usize,
+
+    /// The number of calls of each libfunc called by the contract classes.
+    libfuncs: BTreeMap<&'a str, usize>,
+}
+
+impl VersionStatistics {
+    /// Initialisation of [`VersionStatistics`].
+    ///
+    /// The struct is initialised with field `versions` empty.
+    #[must_use]
+    pub fn new() -> Self {
+        VersionStatistics {
+            versions: BTreeMap::default(),
+        }
+    }
+
+    /// Adds the libfunc usage statistics of a contract class.
+    ///
+    /// If `version` already exists, the statistics are merged and the number
+    /// of classes is increased by 1.
+    ///
+    /// # Arguments
+    ///
+    /// - `version`: The versions of the contract class.
+    /// - `statistics`: The libfunc usage statistics of the contract class.
+    pub fn add_class(&mut self, version: &ClassVersion, statistics: &ReplayStatistics) {
+        let entry = self.versions.entry(version.clone()).or_default();
+        entry.classes += 1;
+        entry.statistics.merge(statistics);
+    }
+
+    /// Returns the versions sorted by descending number of libfunc calls
+    /// together with their number of calls.
+    fn versions_by_calls(&self) -> Vec<(&ClassVersion, &VersionEntry, usize)> {
+        self.versions
+            .iter()
+            .map(|(version, entry)| {
+                let calls = entry.statistics.concrete_libfunc.values().sum();
+                (version, entry, calls)
+            })
+            .sorted_by(|a, b| Ord::cmp(&b.2, &a.2).then(Ord::cmp(a.0, b.0)))
+            .collect()
+    }
+
+    /// Serialises [`VersionStatistics`] to CSV format.
+    ///
+    /// Versions are reported in descending order of libfunc calls. For each
+    /// pair of versions, libfuncs are reported in descending order of weight.
+    ///
+    /// The function name is quoted because it may contain commas.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an IO error writing to the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str;
+    /// # use indoc::indoc;
+    /// # use starknet_replay::profiler::replay_statistics::ReplayStatistics;
+    /// # use starknet_replay::profiler::version_statistics::{ClassVersion, VersionStatistics};
+    /// let mut statistics = ReplayStatistics::default();
+    /// statistics.update(&"u256_safe_divmod".to_string(), 3);
+    /// statistics.update(&"felt252_add".to_string(), 5);
+    /// let version = ClassVersion {
+    ///     sierra_version: "1.6.0".to_string(),
+    ///     compiler_version: "2.7.0".to_string(),
+    /// };
+    /// let mut version_statistics = VersionStatistics::new();
+    /// version_statistics.add_class(&version, &statistics);
+    /// version_statistics.add_class(&version, &statistics);
+    /// let expected_string = indoc! {r#"
+    ///     Sierra Version,Compiler Version,Classes,Function Name,Weight
+    ///     1.6.0,2.7.0,2,"felt252_add",10
+    ///     1.6.0,2.7.0,2,"u256_safe_divmod",6
+    /// "#};
+    /// let csv_output = version_statistics.to_csv_bytes().unwrap();
+    /// assert_eq!(
+    ///     str::from_utf8(csv_output.as_slice()).unwrap(),
+    ///     expected_string
+    /// );
+    /// ```
+    pub fn to_csv_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut f = Vec::new();
+        writeln!(
+            f,
+            "Sierra Version,Compiler Version,Classes,Function Name,Weight"
+        )?;
+        for (version, entry, _) in self.versions_by_calls() {
+            for (concrete_name, weight) in entry
+                .statistics
+                .concrete_libfunc
+                .iter()
+                .sorted_by(|a, b| Ord::cmp(&b.1, &a.1).then(Ord::cmp(a.0, b.0)))
+            {
+                writeln!(
+                    f,
+                    "{},{},{},\"{concrete_name}\",{weight}",
+                    version.sierra_version, version.compiler_version, entry.classes
+                )?;
+            }
+        }
+        Ok(f)
+    }
+
+    /// Serialises [`VersionStatistics`] to JSON format.
+    ///
+    /// Versions are reported in descending order of libfunc calls.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is an error serialising the object.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        let report: Vec<VersionReport> = self
+            .versions_by_calls()
+            .into_iter()
+            .map(|(version, entry, calls)| VersionReport {
+                version,
+                classes: entry.classes,
+                total_calls: calls,
+                libfuncs: entry
+                    .statistics
+                    .concrete_libfunc
+                    .iter()
+                    .map(|(name, weight)| (name.as_str(), *weight))
+                    .collect(),
+            })
+            .collect();
+        serde_json::to_string_pretty(&report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_statistics() {
+        let old_version = ClassVersion {
+            sierra_version: "1.3.0".to_string(),
+            compiler_version: "2.4.0".to_string(),
+        };
+        let new_version = ClassVersion {
+            sierra_version: "1.6.0".to_string(),
+            compiler_version: "2.7.0".to_string(),
+        };
+
+        let mut statistics = ReplayStatistics::new();
+        statistics.update(&"felt252_add".to_string(), 2);
+
+        let mut version_statistics = VersionStatistics::new();
+        version_statistics.add_class(&old_version, &statistics);
+        version_statistics.add_class(&new_version, &statistics);
+        version_statistics.add_class(&new_version, &statistics);
+
+        assert_eq!(version_statistics.versions[&old_version].classes, 1);
+        assert_eq!(version_statistics.versions[&new_version].classes, 2);
+        assert_eq!(
+            version_statistics.versions[&new_version]
+                .statistics
+                .get_libfunc_frequency("felt252_add"),
+            4
+        );
+        assert_eq!(new_version.to_string(), "Sierra 1.6.0 (compiler 2.7.0)");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&version_statistics.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["sierra_version"], "1.6.0");
+        assert_eq!(json[0]["classes"], 2);
+        assert_eq!(json[1]["total_calls"], 2);
+    }
+}
@@ -0,0 +1,233 @@
+//! This module contains the definition of the struct [`CallTrace`] which keeps
+//! the program counters visited by each contract call of a transaction in
+//! order of execution.
+//!
+//! [`blockifier`] records the program counters of an entry point execution
+//! when the execution completes. Therefore, the executions of a transaction are
+//! recorded in post-order of the call tree and they are matched with the call
+//! tree of [`TransactionExecutionInfo`] visiting the inner calls first.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `CallTrace`
+
+use std::collections::{HashMap, VecDeque};
+
+use blockifier::execution::call_info::CallInfo;
+use blockifier::transaction::objects::TransactionExecutionInfo;
+use blockifier::transaction::transaction_types::TransactionType;
+use starknet_api::core::{ClassHash, ContractAddress};
+use starknet_core::types::Felt;
+
+use crate::block_number::BlockNumber;
+use crate::runner::replay_class_hash::ReplayClassHash;
+
+/// [`CallTrace`] contains the program counters visited by a contract call and
+/// the traces of its inner calls.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CallTrace {
+    /// The contract class executed. It's `None` if the class hash isn't known.
+    pub class_hash: Option<ReplayClassHash>,
+
+    /// The address of the contract called. With library calls, it's the
+    /// address of the caller because the class is executed in its context.
+    pub contract_address: ContractAddress,
+
+    /// The selector of the entry point called.
+    pub selector: Felt,
+
+    /// The program counters visited by the call in order of execution. The
+    /// program counters of the inner calls are not included. It's empty for
+    /// Cairo 0 contracts because [`blockifier`] doesn't record their program
+    /// counters.
+    pub pcs: Vec<usize>,
+
+    /// The number of CASM steps executed by the call, excluding the steps of
+    /// its inner calls. Unlike `pcs`, it's known for Cairo 0 contracts too
+    /// because it's taken from the execution resources of the call.
+    pub n_steps: usize,
+
+    /// The traces of the calls made by this call in order of execution.
+    pub inner_calls: Vec<CallTrace>,
+}
+impl CallTrace {
+    /// Builds the call traces of a transaction.
+    ///
+    /// Executions that can't be matched with a call are discarded.
+    ///
+    /// # Arguments
+    ///
+    /// - `execution_info`: The execution info of the transaction.
+    /// - `transaction_type`: The type of the transaction.
+    /// - `executions`: The program counters visited by each entry point
+    ///   execution of each class in order of execution.
+    /// - `block_number`: The block number of the transaction.
+    #[must_use]
+    pub fn from_transaction(
+        execution_info: &TransactionExecutionInfo,
+        transaction_type: TransactionType,
+        executions: HashMap<ClassHash, Vec<Vec<usize>>>,
+        block_number: BlockNumber,
+    ) -> Vec<CallTrace> {
+        let mut executions: HashMap<ClassHash, VecDeque<Vec<usize>>> = executions
+            .into_iter()
+            .map(|(class_hash, class_executions)| (class_hash, class_executions.into()))
+            .collect();
+
+        // The constructor of `DEPLOY_ACCOUNT` transactions is executed before
+        // validation.
+        let mut call_infos: Vec<&CallInfo> = execution_info.non_optional_call_infos().collect();
+        if transaction_type == TransactionType::DeployAccount {
+            if let (Some(validate), Some(execute)) = (
+                &execution_info.validate_call_info,
+                &execution_info.execute_call_info,
+            ) {
+                call_infos = vec![execute, validate];
+                call_infos.extend(&execution_info.fee_transfer_call_info);
+            }
+        }
+
+        call_infos
+            .into_iter()
+            .map(|call_info| Self::new(call_info, &mut executions, block_number))
+            .collect()
+    }
+
+    /// Builds the call trace of `call_info` taking the executions of the
+    /// classes from `executions`.
+    ///
+    /// # Arguments
+    ///
+    /// - `call_info`: The call to trace.
+    /// - `executions`: The queue of executions of each class not matched yet.
+    /// - `block_number`: The block number of the transaction.
+    fn new(
+        call_info: &CallInfo,
+        executions: &mut HashMap<ClassHash, VecDeque<Vec<usize>>>,
+        block_number: BlockNumber,
+    ) -> Self {
+        // Inner calls complete before the outer call, therefore their
+        // executions are recorded first.
+        let inner_calls = call_info
+            .inner_calls
+            .iter()
+            .map(|inner_call| Self::new(inner_call, executions, block_number))
+            .collect();
+        // The execution resources of a call include the resources of its inner
+        // calls.
+        let inner_steps: usize = call_info
+            .inner_calls
+            .iter()
+            .map(|inner_call| inner_call.resources.n_steps)
+            .sum();
+        let n_steps = call_info.resources.n_steps.saturating_sub(inner_steps);
+        let class_hash = call_info.call.class_hash;
+        let pcs = class_hash
+            .and_then(|class_hash| executions.get_mut(&class_hash)?.pop_front())
+            .unwrap_or_default();
+        CallTrace {
+            class_hash: class_hash.map(|class_hash| ReplayClassHash {
+                block_number,
+                class_hash,
+            }),
+            contract_address: call_info.call.storage_address,
+            selector: call_info.call.entry_point_selector.0,
+            pcs,
+            n_steps,
+            inner_calls,
+        }
+    }
+
+    /// Returns an iterator over this call and all its inner calls.
+    pub fn calls(&self) -> impl Iterator<Item = &CallTrace> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let call_trace = stack.pop()?;
+            stack.extend(call_trace.inner_calls.iter().rev());
+            Some(call_trace)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockifier::execution::entry_point::CallEntryPoint;
+    use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
+    use starknet_api::core::{EntryPointSelector, PatriciaKey};
+    use starknet_api::{contract_address, felt, patricia_key};
+
+    use super::*;
+
+    fn call_info(
+        class_hash: u64,
+        selector: u64,
+        n_steps: usize,
+        inner_calls: Vec<CallInfo>,
+    ) -> CallInfo {
+        CallInfo {
+            call: CallEntryPoint {
+                class_hash: Some(ClassHash(Felt::from(class_hash))),
+                storage_address: ContractAddress(
+                    PatriciaKey::try_from(Felt::from(class_hash + 100)).unwrap(),
+                ),
+                entry_point_selector: EntryPointSelector(Felt::from(selector)),
+                ..CallEntryPoint::default()
+            },
+            resources: ExecutionResources {
+                n_steps,
+                ..ExecutionResources::default()
+            },
+            inner_calls,
+            ..CallInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_from_transaction() {
+        let block_number = BlockNumber::new(632_917);
+        let account = ClassHash(Felt::from(1_u64));
+        let token = ClassHash(Felt::from(2_u64));
+
+        // The account calls the token twice during execution.
+        let execution_info = TransactionExecutionInfo {
+            validate_call_info: Some(call_info(1, 10, 2, vec![])),
+            execute_call_info: Some(call_info(
+                1,
+                11,
+                6,
+                vec![call_info(2, 20, 2, vec![]), call_info(2, 21, 1, vec![])],
+            )),
+            ..TransactionExecutionInfo::default()
+        };
+        let executions = HashMap::from([
+            (account, vec![vec![0, 1], vec![5, 6, 7]]),
+            (token, vec![vec![3, 4], vec![8]]),
+        ]);
+
+        let call_traces = CallTrace::from_transaction(
+            &execution_info,
+            TransactionType::InvokeFunction,
+            executions,
+            block_number,
+        );
+
+        assert_eq!(call_traces.len(), 2);
+        assert_eq!(call_traces[0].pcs, vec![0, 1]);
+        assert_eq!(call_traces[1].pcs, vec![5, 6, 7]);
+        assert_eq!(call_traces[1].selector, Felt::from(11_u64));
+        assert_eq!(call_traces[1].inner_calls[0].pcs, vec![3, 4]);
+        assert_eq!(call_traces[1].inner_calls[1].pcs, vec![8]);
+        assert_eq!(call_traces[1].n_steps, 3);
+        assert_eq!(call_traces[1].inner_calls[0].n_steps, 2);
+        assert_eq!(
+            call_traces[1].inner_calls[1].class_hash,
+            Some(ReplayClassHash {
+                block_number,
+                class_hash: token
+            })
+        );
+        assert_eq!(
+            call_traces[1].inner_calls[1].contract_address,
+            contract_address!("0x66")
+        );
+        assert_eq!(call_traces[1].calls().count(), 3);
+    }
+}
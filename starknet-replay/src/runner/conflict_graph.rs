@@ -0,0 +1,344 @@
+//! This module contains the types to measure how parallelizable the
+//! transactions of a block are.
+//!
+//! While replaying a block, the storage records the set of state entries read
+//! and written by each transaction. Two transactions conflict if one of them
+//! writes an entry accessed by the other one. Conflicts are represented as a
+//! directed acyclic graph where the edge `i -> j` means that transaction `j`
+//! must be executed after transaction `i`.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `BlockConflictGraph`
+
+use std::collections::HashSet;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use starknet_api::core::{ClassHash, ContractAddress};
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::TransactionHash;
+
+use crate::block_number::BlockNumber;
+use crate::error::RunnerError;
+
+/// An entry of the blockchain state accessed by a transaction.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StateEntry {
+    /// A storage slot of a contract.
+    Storage(ContractAddress, StorageKey),
+
+    /// The nonce of a contract.
+    Nonce(ContractAddress),
+
+    /// The class hash of a contract.
+    ClassHash(ContractAddress),
+
+    /// The declaration of a class (i.e. its compiled class hash and compiled
+    /// contract class).
+    Class(ClassHash),
+}
+
+/// [`TransactionAccessSet`] contains the state entries read and written by a
+/// transaction.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TransactionAccessSet {
+    /// The state entries read by the transaction.
+    pub reads: HashSet<StateEntry>,
+
+    /// The state entries written by the transaction.
+    pub writes: HashSet<StateEntry>,
+}
+impl TransactionAccessSet {
+    /// Returns `true` if the transaction must be executed after `earlier`.
+    ///
+    /// This happens if any of the two transactions writes a state entry
+    /// accessed by the other one.
+    ///
+    /// # Arguments
+    ///
+    /// - `earlier`: The access set of a transaction preceding `self` in the
+    ///   block.
+    #[must_use]
+    pub fn depends_on(&self, earlier: &TransactionAccessSet) -> bool {
+        !earlier.writes.is_disjoint(&self.reads)
+            || !earlier.writes.is_disjoint(&self.writes)
+            || !earlier.reads.is_disjoint(&self.writes)
+    }
+}
+
+/// [`BlockConflictGraph`] contains the conflicts between the transactions of a
+/// block and the metrics of parallelism derived from them.
+#[derive(Clone, Debug, Serialize)]
+pub struct BlockConflictGraph {
+    /// The block replayed.
+    pub block_number: BlockNumber,
+
+    /// The hashes of the transactions in the block, in the same order of the
+    /// block.
+    pub transactions: Vec<TransactionHash>,
+
+    /// The list of conflicts. The edge `(i, j)` means that transaction `j`
+    /// must be executed after transaction `i`. It is always `i < j`.
+    pub edges: Vec<(usize, usize)>,
+
+    /// The number of transactions in the longest chain of conflicts.
+    pub critical_path_length: usize,
+
+    /// The number of groups of transactions which don't conflict with any
+    /// transaction of another group.
+    pub independent_groups: usize,
+
+    /// The ratio between the number of transactions and the length of the
+    /// critical path. It is the maximum speedup of a parallel execution of
+    /// the block with unlimited resources.
+    pub theoretical_speedup: f64,
+}
+impl BlockConflictGraph {
+    /// Constructs a new [`BlockConflictGraph`] from the access sets of the
+    /// transactions in a block.
+    ///
+    /// # Arguments
+    ///
+    /// - `block_number`: The block replayed.
+    /// - `transactions`: The hashes of the transactions in the block.
+    /// - `access_sets`: The access sets of the transactions, in the same order
+    ///   of `transactions`.
+    #[must_use]
+    pub fn new(
+        block_number: BlockNumber,
+        transactions: Vec<TransactionHash>,
+        access_sets: &[TransactionAccessSet],
+    ) -> Self {
+        let mut edges = Vec::new();
+        for (j, later) in access_sets.iter().enumerate() {
+            for (i, earlier) in access_sets.iter().enumerate().take(j) {
+                if later.depends_on(earlier) {
+                    edges.push((i, j));
+                }
+            }
+        }
+
+        let critical_path_length = Self::critical_path_length(access_sets.len(), &edges);
+        let independent_groups = Self::independent_groups(access_sets.len(), &edges);
+        #[allow(clippy::cast_precision_loss)] // The number of transactions in a block is small.
+        let theoretical_speedup = if critical_path_length == 0 {
+            1.0
+        } else {
+            access_sets.len() as f64 / critical_path_length as f64
+        };
+
+        BlockConflictGraph {
+            block_number,
+            transactions,
+            edges,
+            critical_path_length,
+            independent_groups,
+            theoretical_speedup,
+        }
+    }
+
+    /// Returns the number of transactions in the longest chain of conflicts.
+    ///
+    /// # Arguments
+    ///
+    /// - `number_of_transactions`: The number of transactions in the block.
+    /// - `edges`: The list of conflicts sorted by the second transaction.
+    fn critical_path_length(number_of_transactions: usize, edges: &[(usize, usize)]) -> usize {
+        // `depth[j]` is the length of the longest chain ending in transaction `j`.
+        // Edges are sorted by `j` and `i < j`, therefore `depth[i]` is final when
+        // processing the edge `(i, j)`.
+        let mut depth = vec![1; number_of_transactions];
+        for &(i, j) in edges {
+            depth[j] = depth[j].max(depth[i] + 1);
+        }
+        depth.into_iter().max().unwrap_or_default()
+    }
+
+    /// Returns the number of connected components of the conflict graph.
+    ///
+    /// # Arguments
+    ///
+    /// - `number_of_transactions`: The number of transactions in the block.
+    /// - `edges`: The list of conflicts.
+    fn independent_groups(number_of_transactions: usize, edges: &[(usize, usize)]) -> usize {
+        /// Returns the representative of the group containing `node`.
+        fn find(parent: &mut [usize], node: usize) -> usize {
+            let mut root = node;
+            while parent[root] != root {
+                root = parent[root];
+            }
+            let mut node = node;
+            while parent[node] != root {
+                let next = parent[node];
+                parent[node] = root;
+                node = next;
+            }
+            root
+        }
+
+        let mut parent: Vec<usize> = (0..number_of_transactions).collect();
+        let mut groups = number_of_transactions;
+        for &(i, j) in edges {
+            let root_i = find(&mut parent, i);
+            let root_j = find(&mut parent, j);
+            if root_i != root_j {
+                parent[root_j] = root_i;
+                groups -= 1;
+            }
+        }
+        groups
+    }
+}
+
+/// Writes the list of [`BlockConflictGraph`] in JSON format to a file.
+///
+/// If the file already exists, it is overwritten.
+///
+/// # Arguments
+///
+/// - `filename`: The file to write.
+/// - `graphs`: The conflict graphs of the blocks replayed.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `filename` can't be written to or there is an error
+/// serialising the graphs.
+pub fn write_conflict_graph_json(
+    filename: &PathBuf,
+    graphs: &[BlockConflictGraph],
+) -> Result<(), RunnerError> {
+    let json = serde_json::to_string_pretty(graphs)?;
+    fs::write(filename, json)?;
+    Ok(())
+}
+
+/// Writes the list of [`BlockConflictGraph`] in DOT format to a file.
+///
+/// Each block is a cluster of the graph and each transaction is a node
+/// labelled with its position in the block.
+///
+/// If the file already exists, it is overwritten.
+///
+/// # Arguments
+///
+/// - `filename`: The file to write.
+/// - `graphs`: The conflict graphs of the blocks replayed.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `filename` can't be written to.
+pub fn write_conflict_graph_dot(
+    filename: &PathBuf,
+    graphs: &[BlockConflictGraph],
+) -> Result<(), RunnerError> {
+    fs::write(filename, to_dot(graphs))?;
+    Ok(())
+}
+
+/// Returns the list of [`BlockConflictGraph`] in DOT format.
+///
+/// # Arguments
+///
+/// - `graphs`: The conflict graphs of the blocks replayed.
+fn to_dot(graphs: &[BlockConflictGraph]) -> String {
+    // Writing to a `String` never fails.
+    let mut dot = String::from("digraph conflicts {\n");
+    for graph in graphs {
+        let block_number = graph.block_number;
+        let _ = writeln!(dot, "  subgraph cluster_{block_number} {{");
+        let _ = writeln!(
+            dot,
+            "    label=\"Block {block_number} | critical path {} | groups {} | speedup {:.2}\";",
+            graph.critical_path_length, graph.independent_groups, graph.theoretical_speedup
+        );
+        for (idx, transaction_hash) in graph.transactions.iter().enumerate() {
+            let _ = writeln!(
+                dot,
+                "    b{block_number}_t{idx} [label=\"{idx}\", tooltip=\"{:#x}\"];",
+                transaction_hash.0
+            );
+        }
+        for (i, j) in &graph.edges {
+            let _ = writeln!(dot, "    b{block_number}_t{i} -> b{block_number}_t{j};");
+        }
+        dot.push_str("  }\n");
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_api::core::PatriciaKey;
+    use starknet_api::{contract_address, felt, patricia_key};
+    use starknet_core::types::Felt;
+
+    use super::*;
+
+    fn storage_entry(address: &str, key: u64) -> StateEntry {
+        StateEntry::Storage(contract_address!(address), StorageKey::from(key))
+    }
+
+    fn access_set(reads: &[StateEntry], writes: &[StateEntry]) -> TransactionAccessSet {
+        TransactionAccessSet {
+            reads: reads.iter().copied().collect(),
+            writes: writes.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn test_depends_on() {
+        let a = storage_entry("0x1", 1);
+        let b = storage_entry("0x1", 2);
+
+        // Read after write.
+        assert!(access_set(&[a], &[]).depends_on(&access_set(&[], &[a])));
+        // Write after write.
+        assert!(access_set(&[], &[a]).depends_on(&access_set(&[], &[a])));
+        // Write after read.
+        assert!(access_set(&[], &[a]).depends_on(&access_set(&[a], &[])));
+        // Read after read.
+        assert!(!access_set(&[a], &[]).depends_on(&access_set(&[a], &[])));
+        // Disjoint entries.
+        assert!(!access_set(&[a], &[a]).depends_on(&access_set(&[b], &[b])));
+    }
+
+    #[test]
+    fn test_block_conflict_graph() {
+        let a = storage_entry("0x1", 1);
+        let b = storage_entry("0x2", 1);
+        let nonce = StateEntry::Nonce(contract_address!("0x3"));
+        let access_sets = vec![
+            access_set(&[a], &[a]),
+            access_set(&[a], &[a]),
+            access_set(&[b], &[]),
+            access_set(&[a, nonce], &[nonce]),
+            access_set(&[], &[]),
+        ];
+        let transactions = (0..access_sets.len() as u64)
+            .map(|hash| TransactionHash(Felt::from(hash)))
+            .collect();
+
+        let graph = BlockConflictGraph::new(BlockNumber::new(632_917), transactions, &access_sets);
+
+        assert_eq!(graph.edges, vec![(0, 1), (0, 3), (1, 3)]);
+        assert_eq!(graph.critical_path_length, 3);
+        assert_eq!(graph.independent_groups, 3);
+        assert!((graph.theoretical_speedup - 5.0 / 3.0).abs() < f64::EPSILON);
+
+        let dot = to_dot(&[graph]);
+        assert!(dot.contains("b632917_t0 -> b632917_t1;"));
+        assert!(dot.contains("b632917_t1 -> b632917_t3;"));
+    }
+
+    #[test]
+    fn test_empty_block_conflict_graph() {
+        let graph = BlockConflictGraph::new(BlockNumber::new(1), Vec::new(), &[]);
+
+        assert!(graph.edges.is_empty());
+        assert_eq!(graph.critical_path_length, 0);
+        assert_eq!(graph.independent_groups, 0);
+        assert!((graph.theoretical_speedup - 1.0).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,214 @@
+//! This module contains the types to compare the outcome of transactions
+//! replayed in isolation with the outcome of the same transactions executed in
+//! sequence.
+//!
+//! When a transaction is replayed in isolation, it runs against the state of
+//! the parent block, ignoring the effects of the preceding transactions in the
+//! same block. The outcome in sequence is the execution status recorded in the
+//! transaction receipt. If the two outcomes differ, the transaction depends on
+//! some earlier transaction in the same block.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `IsolationMismatch`
+
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use starknet_api::transaction::{TransactionExecutionStatus, TransactionHash};
+
+use crate::block_number::BlockNumber;
+use crate::error::RunnerError;
+use crate::runner::replay_output::TransactionOutput;
+
+/// The outcome of the execution of a transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TransactionOutcome {
+    /// The transaction has been executed successfully.
+    Succeeded,
+
+    /// The transaction has been executed and reverted.
+    Reverted,
+
+    /// The transaction couldn't be executed (e.g. because of an invalid
+    /// nonce).
+    Rejected,
+}
+impl Display for TransactionOutcome {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TransactionOutcome::Succeeded => write!(f, "SUCCEEDED"),
+            TransactionOutcome::Reverted => write!(f, "REVERTED"),
+            TransactionOutcome::Rejected => write!(f, "REJECTED"),
+        }
+    }
+}
+impl From<&TransactionExecutionStatus> for TransactionOutcome {
+    fn from(status: &TransactionExecutionStatus) -> Self {
+        match status {
+            TransactionExecutionStatus::Succeeded => TransactionOutcome::Succeeded,
+            TransactionExecutionStatus::Reverted(_) => TransactionOutcome::Reverted,
+        }
+    }
+}
+
+/// [`IsolationMismatch`] identifies a transaction whose outcome in isolation
+/// is different from the outcome in sequence.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IsolationMismatch {
+    /// The block containing the transaction.
+    pub block_number: BlockNumber,
+
+    /// The position of the transaction in the block.
+    pub transaction_index: usize,
+
+    /// The hash of the transaction.
+    pub transaction_hash: TransactionHash,
+
+    /// The outcome of the transaction in sequence.
+    pub in_sequence: TransactionOutcome,
+
+    /// The outcome of the transaction in isolation.
+    pub in_isolation: TransactionOutcome,
+
+    /// The revert reason or the rejection error of the transaction replayed in
+    /// isolation.
+    pub reason: Option<String>,
+}
+
+/// Returns the list of transactions whose outcome in isolation is different
+/// from the outcome in sequence.
+///
+/// # Arguments
+///
+/// - `block_number`: The block containing the transactions.
+/// - `outputs`: The results of the isolated replay of the transactions in the
+///   block, in the same order of the transactions in the block.
+#[must_use]
+pub fn find_mismatches(
+    block_number: BlockNumber,
+    outputs: &[TransactionOutput],
+) -> Vec<IsolationMismatch> {
+    outputs
+        .iter()
+        .enumerate()
+        .filter(|(_, output)| output.expected != output.outcome)
+        .map(|(transaction_index, output)| IsolationMismatch {
+            block_number,
+            transaction_index,
+            transaction_hash: output.transaction_hash,
+            in_sequence: output.expected,
+            in_isolation: output.outcome,
+            reason: output.reason.clone(),
+        })
+        .collect()
+}
+
+/// Writes the list of [`IsolationMismatch`] in CSV format to a file.
+///
+/// If the file already exists, it is overwritten.
+///
+/// # Arguments
+///
+/// - `filename`: The file to write.
+/// - `mismatches`: The list of transactions with a different outcome in
+///   isolation.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `filename` can't be written to.
+pub fn write_isolation_report(
+    filename: &PathBuf,
+    mismatches: &[IsolationMismatch],
+) -> Result<(), RunnerError> {
+    let mut f = Vec::new();
+    writeln!(
+        f,
+        "Block Number,Transaction Index,Transaction Hash,Outcome In Sequence,Outcome In \
+         Isolation,Reason"
+    )?;
+    for mismatch in mismatches {
+        // The reason is quoted because it may contain commas.
+        let reason = mismatch
+            .reason
+            .as_deref()
+            .unwrap_or_default()
+            .replace('"', "\"\"")
+            .replace('\n', " ");
+        writeln!(
+            f,
+            "{},{},{:#x},{},{},\"{reason}\"",
+            mismatch.block_number,
+            mismatch.transaction_index,
+            mismatch.transaction_hash.0,
+            mismatch.in_sequence,
+            mismatch.in_isolation,
+        )?;
+    }
+    fs::write(filename, f)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_core::types::Felt;
+
+    use super::*;
+    use crate::runner::replay_class_hash::VisitedPcs;
+
+    fn isolated_output(
+        hash: u64,
+        expected: TransactionOutcome,
+        outcome: TransactionOutcome,
+    ) -> TransactionOutput {
+        TransactionOutput {
+            transaction_hash: TransactionHash(Felt::from(hash)),
+            expected,
+            outcome,
+            reason: None,
+            execution_info: None,
+            visited_pcs: VisitedPcs::default(),
+            call_traces: Vec::new(),
+            access_set: None,
+        }
+    }
+
+    #[test]
+    fn test_find_mismatches() {
+        let block_number = BlockNumber::new(632_917);
+        let outputs = vec![
+            isolated_output(
+                1,
+                TransactionOutcome::Succeeded,
+                TransactionOutcome::Succeeded,
+            ),
+            isolated_output(
+                2,
+                TransactionOutcome::Succeeded,
+                TransactionOutcome::Rejected,
+            ),
+            isolated_output(
+                3,
+                TransactionOutcome::Reverted,
+                TransactionOutcome::Reverted,
+            ),
+            isolated_output(
+                4,
+                TransactionOutcome::Reverted,
+                TransactionOutcome::Succeeded,
+            ),
+        ];
+
+        let mismatches = find_mismatches(block_number, &outputs);
+
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(mismatches[0].transaction_index, 1);
+        assert_eq!(mismatches[0].in_sequence, TransactionOutcome::Succeeded);
+        assert_eq!(mismatches[0].in_isolation, TransactionOutcome::Rejected);
+        assert_eq!(mismatches[1].transaction_index, 3);
+        assert_eq!(
+            mismatches[1].transaction_hash,
+            TransactionHash(Felt::from(4_u64))
+        );
+    }
+}
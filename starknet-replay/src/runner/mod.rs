@@ -1,26 +1,38 @@
 //! The module runner contains the code to replay transactions and extract the
 //! sequence of visited program counters from each transaction replayed.
 
-use std::path::PathBuf;
-use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
 
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use tracing::info;
 
-use self::replay_class_hash::TransactionOutput;
+use self::conflict_graph::BlockConflictGraph;
+use self::isolation::find_mismatches;
+use self::replay_options::ReplayOptions;
+use self::replay_output::{ReplayOutput, TransactionOutput};
+use self::replay_record::{transaction_records, BlockRecord};
+use self::transaction_visited_pcs::TransactionVisitedPcs;
 use crate::block_number::BlockNumber;
 use crate::runner::replay_class_hash::VisitedPcs;
 use crate::runner::replay_range::ReplayRange;
 use crate::storage::Storage;
 use crate::{ReplayBlock, RunnerError};
 
+pub mod call_trace;
+pub mod conflict_graph;
+pub mod isolation;
+pub mod pc_histogram;
 pub mod replay_block;
 pub mod replay_class_hash;
+pub mod replay_options;
+pub mod replay_output;
 pub mod replay_range;
+pub mod replay_record;
 pub mod report;
+pub mod transaction_visited_pcs;
 
-/// Replays transactions as indicated by `replay_range` and extracts the list of
-/// visited program counters.
+/// Replays transactions as indicated by `replay_range` and returns the visited
+/// program counters together with the other data requested in `options`.
 ///
 /// Parallel replay always queries initial block state from the RPC server. The
 /// consequence is that initial state of block `n+1` may be different from final
@@ -32,12 +44,9 @@ pub mod report;
 /// # Arguments
 ///
 /// - `replay_range`: The range of blocks to be replayed.
-/// - `trace_out`: The location to save the output trace of the replayed
-///   transactions.
 /// - `storage`: The object to query the starknet blockchain using the RPC
 ///   protocol.
-/// - `serial_replay`: Set to true to do serial blocks replay instead of
-///   parallel.
+/// - `options`: The settings of the replay.
 ///
 /// # Errors
 ///
@@ -48,10 +57,9 @@ pub mod report;
 /// - There is any error during transaction replay.
 pub fn run_replay<T>(
     replay_range: &ReplayRange,
-    trace_out: &Option<PathBuf>,
     storage: &T,
-    serial_replay: bool,
-) -> Result<VisitedPcs, RunnerError>
+    options: &ReplayOptions,
+) -> Result<ReplayOutput, RunnerError>
 where
     T: Storage + Sync + Send,
 {
@@ -60,11 +68,7 @@ where
 
     // Iterate through each block in `replay_work` and replay all the
     // transactions
-    if serial_replay {
-        replay_blocks_serial(storage, trace_out, &replay_work)
-    } else {
-        replay_blocks_parallel(storage, trace_out, &replay_work)
-    }
+    replay_blocks(storage, &replay_work, options)
 }
 
 /// Generates the list of transactions to be replayed.
@@ -134,24 +138,31 @@ where
 /// - `transaction_simulations`: The list of transaction traces from the
 ///   replayer.
 #[must_use]
-pub fn process_transaction_traces(transaction_simulations: Vec<TransactionOutput>) -> VisitedPcs {
+pub fn process_transaction_traces(transaction_simulations: &[TransactionOutput]) -> VisitedPcs {
     let mut cumulative_visited_pcs = VisitedPcs::default();
     for simulation in transaction_simulations {
-        let visited_pcs = simulation.1;
-        if visited_pcs.is_empty() {
-            continue;
-        }
-
-        for (contract, pcs) in visited_pcs {
-            let key = cumulative_visited_pcs.entry(contract).or_insert(Vec::new());
-            key.extend(pcs.into_iter());
-        }
+        add_visited_pcs(&mut cumulative_visited_pcs, &simulation.visited_pcs);
     }
     cumulative_visited_pcs
 }
 
-/// Re-executes the list of blocks in `replay_work` in parallel and returns the
-/// statistics on libfunc usage.
+/// Adds the histograms of `visited_pcs` to `cumulative_visited_pcs`.
+///
+/// # Arguments
+///
+/// - `cumulative_visited_pcs`: The visited program counters to update.
+/// - `visited_pcs`: The visited program counters to add.
+fn add_visited_pcs(cumulative_visited_pcs: &mut VisitedPcs, visited_pcs: &VisitedPcs) {
+    for (contract, pcs) in visited_pcs {
+        cumulative_visited_pcs
+            .entry(*contract)
+            .or_default()
+            .merge(pcs);
+    }
+}
+
+/// Re-executes the list of blocks in `replay_work` and returns the visited
+/// program counters together with the other data requested in `options`.
 ///
 /// With parallel replay, initial state is always queried from the RPC server.
 /// The consequence is that initial state of block `n+1` may be different from
@@ -161,83 +172,116 @@ pub fn process_transaction_traces(transaction_simulations: Vec<TransactionOutput
 ///
 /// - `storage`: The object to query the starknet blockchain using the RPC
 ///   protocol.
-/// - `trace_out`: The output file of the transaction traces.
 /// - `replay_work`: The list of transactions to replay grouped by block.
+/// - `options`: The settings of the replay.
 ///
 /// # Errors
 ///
 /// Returns [`Err`] if the function `execute_block` fails to replay any
-/// transaction.
-pub fn replay_blocks_parallel<T>(
+/// block.
+pub fn replay_blocks<T>(
     storage: &T,
-    trace_out: &Option<PathBuf>,
     replay_work: &[ReplayBlock],
-) -> Result<VisitedPcs, RunnerError>
+    options: &ReplayOptions,
+) -> Result<ReplayOutput, RunnerError>
 where
     T: Storage + Sync + Send,
 {
-    info!("Starting parallel blocks replay");
-    let (sender, receiver) = channel();
-    replay_work
-        .par_iter()
-        .try_for_each_with(
-            (storage, trace_out, sender),
-            |(storage, trace_out, sender), block| -> anyhow::Result<()> {
-                let block_transaction_traces = storage.execute_block(block, trace_out)?;
-                let block_number = BlockNumber::new(block.header.block_number.0);
-                info!("Replay completed block {block_number}");
-                let visited_pcs = process_transaction_traces(block_transaction_traces);
-                sender.send(visited_pcs)?;
-                Ok(())
-            },
-        )
-        .map_err(RunnerError::ReplayBlocks)?;
-
-    let res: Vec<_> = receiver.iter().collect();
+    let replay_block = |block: &ReplayBlock| -> Result<ReplayOutput, RunnerError> {
+        let start_time = Instant::now();
+        let mut outputs = storage.execute_block(block, options)?;
+        let elapsed = start_time.elapsed();
+        let block_number = BlockNumber::new(block.header.block_number.0);
+        info!("Replay completed block {block_number}");
+        Ok(process_block_outputs(
+            block,
+            block_number,
+            elapsed,
+            &mut outputs,
+            options,
+        ))
+    };
 
-    let mut cumulative_visited_pcs = VisitedPcs::default();
-    for visited_pcs in res {
-        cumulative_visited_pcs.extend(visited_pcs.into_iter());
-    }
+    let block_outputs: Vec<ReplayOutput> = if options.serial_replay {
+        info!("Starting serial blocks replay");
+        replay_work
+            .iter()
+            .map(replay_block)
+            .collect::<Result<_, _>>()?
+    } else {
+        info!("Starting parallel blocks replay");
+        replay_work
+            .par_iter()
+            .map(replay_block)
+            .collect::<Result<_, _>>()?
+    };
 
-    Ok(cumulative_visited_pcs)
+    let mut replay_output = ReplayOutput::default();
+    for block_output in block_outputs {
+        replay_output.append(block_output);
+    }
+    Ok(replay_output)
 }
 
-/// Serially re-executes the list of blocks in `replay_work` and returns the
-/// statistics on libfunc usage.
-///
-/// Serial replay is slower than parallel, however it ensures state consistency
-/// between initial state of block `n+1` and final state of block `n`.
+/// Collects the data recorded during the replay of a block.
 ///
 /// # Arguments
 ///
-/// - `storage`: The object to query the starknet blockchain using the RPC
-///   protocol.
-/// - `trace_out`: The output file of the transaction traces.
-/// - `replay_work`: The list of transactions to replay grouped by block.
-///
-/// # Errors
-///
-/// Returns [`Err`] if the function `execute_block` fails to replay any
-/// transaction.
-pub fn replay_blocks_serial<T>(
-    storage: &T,
-    trace_out: &Option<PathBuf>,
-    replay_work: &[ReplayBlock],
-) -> Result<VisitedPcs, RunnerError>
-where
-    T: Storage + Sync + Send,
-{
-    info!("Starting serial blocks replay");
+/// - `block`: The block replayed.
+/// - `block_number`: The number of the block replayed.
+/// - `elapsed`: The time spent replaying the block.
+/// - `outputs`: The results of the replay of the transactions of the block. The
+///   call traces, the access sets and the visited program counters of each
+///   transaction are moved out of `outputs`.
+/// - `options`: The settings of the replay.
+fn process_block_outputs(
+    block: &ReplayBlock,
+    block_number: BlockNumber,
+    elapsed: Duration,
+    outputs: &mut [TransactionOutput],
+    options: &ReplayOptions,
+) -> ReplayOutput {
+    let mut block_output = ReplayOutput::default();
 
-    let mut cumulative_visited_pcs = VisitedPcs::default();
-    for block in replay_work {
-        let block_transaction_traces = storage.execute_block(block, trace_out)?;
-        let block_number = BlockNumber::new(block.header.block_number.0);
-        info!("Replay completed block {block_number}");
-        let visited_pcs = process_transaction_traces(block_transaction_traces);
-        cumulative_visited_pcs.extend(visited_pcs.into_iter());
+    let block_record = BlockRecord {
+        block_number,
+        starknet_version: block.header.starknet_version.0.clone(),
+        transactions: outputs.len(),
+        elapsed,
+    };
+    let transaction_records = transaction_records(block_number, &block.transactions, outputs);
+    block_output
+        .records
+        .add_block(block_record, transaction_records);
+
+    if options.isolated_replay {
+        block_output.isolation_mismatches = find_mismatches(block_number, outputs);
+    }
+
+    if options.record_access_sets {
+        let transaction_hashes = outputs
+            .iter()
+            .map(|output| output.transaction_hash)
+            .collect();
+        let access_sets: Vec<_> = outputs
+            .iter_mut()
+            .map(|output| output.access_set.take().unwrap_or_default())
+            .collect();
+        let graph = BlockConflictGraph::new(block_number, transaction_hashes, &access_sets);
+        block_output.conflict_graphs.push(graph);
+    }
+
+    for output in outputs {
+        block_output.call_traces.append(&mut output.call_traces);
+        add_visited_pcs(&mut block_output.visited_pcs, &output.visited_pcs);
+        if options.record_transaction_visited_pcs && !output.visited_pcs.is_empty() {
+            block_output.transactions.push(TransactionVisitedPcs {
+                block_number,
+                transaction_hash: output.transaction_hash,
+                visited_pcs: std::mem::take(&mut output.visited_pcs),
+            });
+        }
     }
 
-    Ok(cumulative_visited_pcs)
+    block_output
 }
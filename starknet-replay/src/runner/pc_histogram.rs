@@ -0,0 +1,300 @@
+//! This module contains the definition of the structs [`PcHistogram`] and
+//! [`EntryPointHistograms`].
+
+use std::collections::hash_map::Iter;
+use std::collections::HashMap;
+
+/// [`PcHistogram`] stores how many times each program counter has been
+/// visited.
+///
+/// The order in which program counters are visited is not kept. Memory usage
+/// grows with the number of distinct program counters, rather than with the
+/// number of steps executed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PcHistogram(HashMap<usize, usize>);
+impl PcHistogram {
+    /// Constructs a new empty [`PcHistogram`].
+    #[must_use]
+    pub fn new() -> Self {
+        PcHistogram(HashMap::new())
+    }
+
+    /// Adds `count` visits of the program counter `pc`.
+    ///
+    /// # Arguments
+    ///
+    /// - `pc`: The program counter visited.
+    /// - `count`: The number of visits to add.
+    pub fn add(&mut self, pc: usize, count: usize) {
+        *self.0.entry(pc).or_insert(0) += count;
+    }
+
+    /// Adds the visits recorded in `other` to `self`.
+    ///
+    /// # Arguments
+    ///
+    /// - `other`: The histogram to merge.
+    pub fn merge(&mut self, other: &PcHistogram) {
+        for (pc, count) in other {
+            self.add(*pc, *count);
+        }
+    }
+
+    /// Returns the number of visits of the program counter `pc`.
+    ///
+    /// # Arguments
+    ///
+    /// - `pc`: The program counter to query.
+    #[must_use]
+    pub fn count(&self, pc: usize) -> usize {
+        self.0.get(&pc).copied().unwrap_or_default()
+    }
+
+    /// Returns the total number of visits recorded.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.0.values().sum()
+    }
+
+    /// Returns `true` if no program counter has been visited.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns an iterator over the pairs of program counter and number of
+    /// visits. The order is arbitrary.
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_, usize, usize> {
+        self.0.iter()
+    }
+
+    /// Returns the list of visited program counters where each program counter
+    /// is repeated as many times as it has been visited. The order is
+    /// arbitrary.
+    #[must_use]
+    pub fn to_vec(&self) -> Vec<usize> {
+        let mut pcs = Vec::with_capacity(self.total());
+        for (pc, count) in self {
+            pcs.extend(std::iter::repeat(*pc).take(*count));
+        }
+        pcs
+    }
+}
+impl<'a> IntoIterator for &'a PcHistogram {
+    type IntoIter = Iter<'a, usize, usize>;
+    type Item = (&'a usize, &'a usize);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl Extend<usize> for PcHistogram {
+    fn extend<T: IntoIterator<Item = usize>>(&mut self, iter: T) {
+        for pc in iter {
+            self.add(pc, 1);
+        }
+    }
+}
+impl FromIterator<usize> for PcHistogram {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        let mut histogram = PcHistogram::new();
+        histogram.extend(iter);
+        histogram
+    }
+}
+
+/// [`EntryPointHistograms`] stores the [`PcHistogram`] of each entry point of
+/// a contract class.
+///
+/// [`blockifier`] records the program counters of each entry point execution
+/// separately and the first program counter of an execution is the offset of
+/// the entry point. Therefore, the entry point is identified by the first
+/// program counter of the execution.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EntryPointHistograms(HashMap<usize, PcHistogram>);
+impl EntryPointHistograms {
+    /// Constructs a new empty [`EntryPointHistograms`].
+    #[must_use]
+    pub fn new() -> Self {
+        EntryPointHistograms(HashMap::new())
+    }
+
+    /// Adds the program counters visited during an entry point execution.
+    ///
+    /// # Arguments
+    ///
+    /// - `pcs`: The program counters visited in order of execution.
+    pub fn record_execution(&mut self, pcs: &[usize]) {
+        let Some(entry_point_pc) = pcs.first() else {
+            return;
+        };
+        self.0
+            .entry(*entry_point_pc)
+            .or_default()
+            .extend(pcs.iter().copied());
+    }
+
+    /// Adds `count` visits of the program counter `pc` during executions of
+    /// the entry point starting at `entry_point_pc`.
+    ///
+    /// # Arguments
+    ///
+    /// - `entry_point_pc`: The program counter of the entry point.
+    /// - `pc`: The program counter visited.
+    /// - `count`: The number of visits to add.
+    pub fn add(&mut self, entry_point_pc: usize, pc: usize, count: usize) {
+        self.0.entry(entry_point_pc).or_default().add(pc, count);
+    }
+
+    /// Adds the visits recorded in `other` to `self`.
+    ///
+    /// # Arguments
+    ///
+    /// - `other`: The histograms to merge.
+    pub fn merge(&mut self, other: &EntryPointHistograms) {
+        for (entry_point_pc, histogram) in other {
+            self.0.entry(*entry_point_pc).or_default().merge(histogram);
+        }
+    }
+
+    /// Returns the number of visits of the program counter `pc` across all
+    /// entry points.
+    ///
+    /// # Arguments
+    ///
+    /// - `pc`: The program counter to query.
+    #[must_use]
+    pub fn count(&self, pc: usize) -> usize {
+        self.0.values().map(|histogram| histogram.count(pc)).sum()
+    }
+
+    /// Returns the total number of visits recorded across all entry points.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.0.values().map(PcHistogram::total).sum()
+    }
+
+    /// Returns `true` if no entry point has been executed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns an iterator over the pairs of entry point program counter and
+    /// histogram of the program counters visited from that entry point. The
+    /// order is arbitrary.
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_, usize, PcHistogram> {
+        self.0.iter()
+    }
+
+    /// Returns the histogram of visited program counters across all entry
+    /// points.
+    #[must_use]
+    pub fn histogram(&self) -> PcHistogram {
+        let mut histogram = PcHistogram::new();
+        for entry_point_histogram in self.0.values() {
+            histogram.merge(entry_point_histogram);
+        }
+        histogram
+    }
+
+    /// Returns one list of visited program counters for each entry point. Each
+    /// list starts with the program counter of the entry point, the order of
+    /// the remaining program counters is arbitrary.
+    ///
+    /// Recording each list with [`EntryPointHistograms::record_execution`]
+    /// gives back the same object.
+    #[must_use]
+    pub fn to_executions(&self) -> Vec<Vec<usize>> {
+        self.0
+            .iter()
+            .map(|(entry_point_pc, histogram)| {
+                let mut pcs = Vec::with_capacity(histogram.total());
+                pcs.push(*entry_point_pc);
+                for (pc, count) in histogram {
+                    let count = if pc == entry_point_pc {
+                        count.saturating_sub(1)
+                    } else {
+                        *count
+                    };
+                    pcs.extend(std::iter::repeat(*pc).take(count));
+                }
+                pcs
+            })
+            .collect()
+    }
+}
+impl<'a> IntoIterator for &'a EntryPointHistograms {
+    type IntoIter = Iter<'a, usize, PcHistogram>;
+    type Item = (&'a usize, &'a PcHistogram);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+/// The program counters are considered a single execution starting from the
+/// first program counter.
+impl Extend<usize> for EntryPointHistograms {
+    fn extend<T: IntoIterator<Item = usize>>(&mut self, iter: T) {
+        let pcs: Vec<usize> = iter.into_iter().collect();
+        self.record_execution(&pcs);
+    }
+}
+/// The program counters are considered a single execution starting from the
+/// first program counter.
+impl FromIterator<usize> for EntryPointHistograms {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        let mut histograms = EntryPointHistograms::new();
+        histograms.extend(iter);
+        histograms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pc_histogram() {
+        let mut histogram: PcHistogram = [1, 4, 6, 4].into_iter().collect();
+        assert_eq!(histogram.count(4), 2);
+        assert_eq!(histogram.count(5), 0);
+        assert_eq!(histogram.total(), 4);
+
+        let other: PcHistogram = [4, 7].into_iter().collect();
+        histogram.merge(&other);
+        assert_eq!(histogram.count(4), 3);
+        assert_eq!(histogram.count(7), 1);
+        assert_eq!(histogram.total(), 6);
+
+        let mut pcs = histogram.to_vec();
+        pcs.sort_unstable();
+        assert_eq!(pcs, vec![1, 4, 4, 4, 6, 7]);
+    }
+
+    #[test]
+    fn test_entry_point_histograms() {
+        let mut histograms = EntryPointHistograms::new();
+        histograms.record_execution(&[10, 12, 10, 14]);
+        histograms.record_execution(&[20, 12]);
+        histograms.record_execution(&[]);
+
+        let other: EntryPointHistograms = [10, 16].into_iter().collect();
+        histograms.merge(&other);
+
+        assert_eq!(histograms.iter().count(), 2);
+        assert_eq!(histograms.count(10), 3);
+        assert_eq!(histograms.count(12), 2);
+        assert_eq!(histograms.total(), 8);
+        assert_eq!(histograms.histogram().count(12), 2);
+
+        let mut restored = EntryPointHistograms::new();
+        for pcs in histograms.to_executions() {
+            assert!(pcs[0] == 10 || pcs[0] == 20);
+            restored.record_execution(&pcs);
+        }
+        assert_eq!(restored, histograms);
+    }
+}
@@ -2,10 +2,10 @@
 
 use std::collections::HashMap;
 
-use blockifier::transaction::objects::TransactionExecutionInfo;
 use serde::{Deserialize, Serialize};
 use starknet_api::core::ClassHash as StarknetClassHash;
 
+use crate::runner::pc_histogram::EntryPointHistograms;
 use crate::runner::BlockNumber;
 
 /// [`ReplayClassHash`] combines [`StarknetClassHash`] with
@@ -22,10 +22,7 @@ pub struct ReplayClassHash {
     pub class_hash: StarknetClassHash,
 }
 
-/// The type [`VisitedPcs`] is a hashmap to store the visited program counters
-/// for each contract invocation during replay.
-pub type VisitedPcs = HashMap<ReplayClassHash, Vec<usize>>;
-
-/// The type [`TransactionOutput`] contains the combination of transaction
-/// receipt and list of visited program counters.
-pub type TransactionOutput = (TransactionExecutionInfo, VisitedPcs);
+/// The type [`VisitedPcs`] is a hashmap to store the histograms of visited
+/// program counters of each entry point for each contract invocation during
+/// replay.
+pub type VisitedPcs = HashMap<ReplayClassHash, EntryPointHistograms>;
@@ -0,0 +1,49 @@
+//! This module contains the definition of the struct [`ReplayOptions`] to
+//! select how the blocks are replayed and which data is recorded during the
+//! replay.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `ReplayOptions`
+
+use std::path::PathBuf;
+
+/// [`ReplayOptions`] contains the settings of a replay.
+///
+/// Recording more data slows down the replay and increases the memory usage,
+/// therefore each kind of data is recorded only if requested.
+#[allow(clippy::struct_excessive_bools)] // Added because each flag is independent from the others.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReplayOptions {
+    /// The output file of the transaction traces. If `None`, the traces are
+    /// not saved.
+    pub trace_out: Option<PathBuf>,
+
+    /// Set to replay the blocks serially instead of in parallel.
+    ///
+    /// Serial replay is slower than parallel, however it ensures state
+    /// consistency between initial state of block `n+1` and final state of
+    /// block `n`.
+    pub serial_replay: bool,
+
+    /// Set to replay each transaction against the state of the parent block,
+    /// ignoring the effects of the preceding transactions in the same block.
+    ///
+    /// Transactions within a block are replayed in parallel and transactions
+    /// rejected by the [`blockifier`] don't interrupt the replay. Not
+    /// compatible with `serial_replay` because the state changes of the
+    /// transactions are not consistent with each other.
+    pub isolated_replay: bool,
+
+    /// Set to record the call trace of each contract call. The program
+    /// counters of each entry point execution are kept in order of execution
+    /// until the end of each transaction.
+    pub record_call_traces: bool,
+
+    /// Set to record the state entries read and written by each transaction
+    /// to build the conflict graph of each block. With `isolated_replay`, the
+    /// access sets are relative to the state of the parent block.
+    pub record_access_sets: bool,
+
+    /// Set to keep the visited program counters of each transaction in
+    /// addition to the visited program counters of the whole replay.
+    pub record_transaction_visited_pcs: bool,
+}
@@ -0,0 +1,101 @@
+//! This module contains the output of the replay of a transaction,
+//! [`TransactionOutput`], and the output of the replay of a range of blocks,
+//! [`ReplayOutput`].
+
+#![allow(clippy::module_name_repetitions)] // Added because of `ReplayOutput`
+
+use blockifier::transaction::objects::TransactionExecutionInfo;
+use starknet_api::transaction::TransactionHash;
+
+use crate::runner::call_trace::CallTrace;
+use crate::runner::conflict_graph::{BlockConflictGraph, TransactionAccessSet};
+use crate::runner::isolation::{IsolationMismatch, TransactionOutcome};
+use crate::runner::replay_class_hash::VisitedPcs;
+use crate::runner::replay_record::ReplayRecords;
+use crate::runner::transaction_visited_pcs::TransactionVisitedPcs;
+
+/// [`TransactionOutput`] contains the result of the replay of a transaction.
+///
+/// The fields `call_traces` and `access_set` are filled only if requested in
+/// the [`crate::runner::replay_options::ReplayOptions`].
+#[derive(Debug)]
+pub struct TransactionOutput {
+    /// The hash of the transaction replayed.
+    pub transaction_hash: TransactionHash,
+
+    /// The outcome of the transaction as recorded in the transaction receipt.
+    pub expected: TransactionOutcome,
+
+    /// The outcome of the transaction replayed.
+    pub outcome: TransactionOutcome,
+
+    /// The revert reason or the rejection error of the transaction replayed.
+    pub reason: Option<String>,
+
+    /// The execution info of the transaction. It is `None` if the transaction
+    /// has been rejected.
+    pub execution_info: Option<TransactionExecutionInfo>,
+
+    /// The histograms of visited program counters of each contract class
+    /// called by the transaction.
+    pub visited_pcs: VisitedPcs,
+
+    /// The call trace of each contract call of the transaction.
+    pub call_traces: Vec<CallTrace>,
+
+    /// The state entries read and written by the transaction.
+    pub access_set: Option<TransactionAccessSet>,
+}
+
+/// [`ReplayOutput`] contains the data recorded during the replay of a range of
+/// blocks.
+///
+/// The fields `transactions`, `isolation_mismatches`, `conflict_graphs` and
+/// `call_traces` are filled only if requested in the
+/// [`crate::runner::replay_options::ReplayOptions`]. All the lists are sorted
+/// by block number and position of the transaction in the block.
+#[derive(Clone, Debug, Default)]
+pub struct ReplayOutput {
+    /// The histograms of visited program counters of all the transactions
+    /// replayed.
+    pub visited_pcs: VisitedPcs,
+
+    /// The visited program counters of each transaction. Transactions which
+    /// don't visit any program counter of Sierra contract classes are
+    /// skipped.
+    pub transactions: Vec<TransactionVisitedPcs>,
+
+    /// The records of the blocks and transactions replayed.
+    pub records: ReplayRecords,
+
+    /// The transactions whose outcome in isolation is different from the
+    /// outcome in sequence.
+    pub isolation_mismatches: Vec<IsolationMismatch>,
+
+    /// The conflict graph of each block.
+    pub conflict_graphs: Vec<BlockConflictGraph>,
+
+    /// The call traces of the transactions in order of execution.
+    pub call_traces: Vec<CallTrace>,
+}
+impl ReplayOutput {
+    /// Appends the output of the replay of the following blocks.
+    ///
+    /// # Arguments
+    ///
+    /// - `other`: The output of the blocks replayed after the blocks in `self`.
+    pub fn append(&mut self, other: ReplayOutput) {
+        for (replay_class_hash, histograms) in other.visited_pcs {
+            self.visited_pcs
+                .entry(replay_class_hash)
+                .or_default()
+                .merge(&histograms);
+        }
+        self.transactions.extend(other.transactions);
+        self.records.blocks.extend(other.records.blocks);
+        self.records.transactions.extend(other.records.transactions);
+        self.isolation_mismatches.extend(other.isolation_mismatches);
+        self.conflict_graphs.extend(other.conflict_graphs);
+        self.call_traces.extend(other.call_traces);
+    }
+}
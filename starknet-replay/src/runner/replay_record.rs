@@ -0,0 +1,190 @@
+//! This module contains the records of a replay: one record for each
+//! transaction replayed and one record for each block replayed.
+//!
+//! The records are built from the output of
+//! [`crate::storage::Storage::execute_block`]. They are meant to be saved in
+//! the results database.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `ReplayRecords`
+
+use std::time::Duration;
+
+use starknet_api::transaction::{Transaction, TransactionHash};
+
+use crate::block_number::BlockNumber;
+use crate::runner::isolation::TransactionOutcome;
+use crate::runner::replay_output::TransactionOutput;
+
+/// The record of a transaction replayed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TransactionRecord {
+    /// The block containing the transaction.
+    pub block_number: BlockNumber,
+
+    /// The position of the transaction in the block.
+    pub transaction_index: usize,
+
+    /// The hash of the transaction.
+    pub transaction_hash: TransactionHash,
+
+    /// The type of the transaction (e.g. `INVOKE`).
+    pub transaction_type: &'static str,
+
+    /// `true` if the transaction has been reverted in the replay.
+    pub reverted: bool,
+
+    /// `true` if the transaction has been reverted both in the replay and on
+    /// chain, or in neither. It is `false` if the transaction has been
+    /// rejected in the replay.
+    pub revert_status_matches: bool,
+
+    /// The fee charged to the transaction in the replay. It is 0 if the
+    /// transaction has been rejected in the replay.
+    pub fee: u128,
+
+    /// The number of CASM steps executed by the transaction in the replay. It
+    /// is 0 if the transaction has been rejected in the replay.
+    pub steps: usize,
+}
+
+/// The record of a block replayed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockRecord {
+    /// The number of the block.
+    pub block_number: BlockNumber,
+
+    /// The Starknet version of the block (e.g. `0.13.1.1`).
+    pub starknet_version: String,
+
+    /// The number of transactions replayed in the block.
+    pub transactions: usize,
+
+    /// The time spent replaying the block.
+    pub elapsed: Duration,
+}
+
+/// The records of the blocks and the transactions replayed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReplayRecords {
+    /// The records of the blocks, sorted by block number.
+    pub blocks: Vec<BlockRecord>,
+
+    /// The records of the transactions, sorted by block number and position
+    /// in the block.
+    pub transactions: Vec<TransactionRecord>,
+}
+
+impl ReplayRecords {
+    /// Adds the records of a block replayed.
+    ///
+    /// # Arguments
+    ///
+    /// - `block`: The record of the block.
+    /// - `transactions`: The records of the transactions of the block.
+    pub fn add_block(&mut self, block: BlockRecord, transactions: Vec<TransactionRecord>) {
+        self.blocks.push(block);
+        self.transactions.extend(transactions);
+    }
+}
+
+/// Returns the name of the type of `transaction`, as used by the Starknet RPC
+/// specification.
+///
+/// # Arguments
+///
+/// - `transaction`: The transaction.
+#[must_use]
+pub fn transaction_type_name(transaction: &Transaction) -> &'static str {
+    match transaction {
+        Transaction::Declare(_) => "DECLARE",
+        Transaction::Deploy(_) => "DEPLOY",
+        Transaction::DeployAccount(_) => "DEPLOY_ACCOUNT",
+        Transaction::Invoke(_) => "INVOKE",
+        Transaction::L1Handler(_) => "L1_HANDLER",
+    }
+}
+
+/// Returns the records of the transactions replayed in a block.
+///
+/// `transactions` and `outputs` must be in the same order of the transactions
+/// in the block.
+///
+/// # Arguments
+///
+/// - `block_number`: The block containing the transactions.
+/// - `transactions`: The transactions of the block.
+/// - `outputs`: The results of the replay of the transactions.
+#[must_use]
+pub fn transaction_records(
+    block_number: BlockNumber,
+    transactions: &[Transaction],
+    outputs: &[TransactionOutput],
+) -> Vec<TransactionRecord> {
+    transactions
+        .iter()
+        .zip(outputs)
+        .enumerate()
+        .map(|(transaction_index, (transaction, output))| {
+            let (fee, steps) = output
+                .execution_info
+                .as_ref()
+                .map(|tx_info| {
+                    (
+                        tx_info.transaction_receipt.fee.0,
+                        tx_info.transaction_receipt.resources.vm_resources.n_steps,
+                    )
+                })
+                .unwrap_or_default();
+            TransactionRecord {
+                block_number,
+                transaction_index,
+                transaction_hash: output.transaction_hash,
+                transaction_type: transaction_type_name(transaction),
+                reverted: output.outcome == TransactionOutcome::Reverted,
+                revert_status_matches: output.outcome == output.expected,
+                fee,
+                steps,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_api::transaction::{InvokeTransaction, InvokeTransactionV1, L1HandlerTransaction};
+
+    use super::*;
+
+    #[test]
+    fn test_transaction_type_name() {
+        let invoke = Transaction::Invoke(InvokeTransaction::V1(InvokeTransactionV1::default()));
+        assert_eq!(transaction_type_name(&invoke), "INVOKE");
+        let l1_handler = Transaction::L1Handler(L1HandlerTransaction::default());
+        assert_eq!(transaction_type_name(&l1_handler), "L1_HANDLER");
+    }
+
+    #[test]
+    fn test_replay_records() {
+        let block_number = BlockNumber::new(632_917);
+        let mut records = ReplayRecords::default();
+        let transaction = TransactionRecord {
+            block_number,
+            transaction_index: 0,
+            transaction_hash: TransactionHash::default(),
+            transaction_type: "INVOKE",
+            reverted: false,
+            revert_status_matches: true,
+            fee: 10,
+            steps: 100,
+        };
+        let block = BlockRecord {
+            block_number,
+            starknet_version: "0.13.1.1".to_string(),
+            transactions: 1,
+            elapsed: Duration::from_millis(20),
+        };
+        records.add_block(block.clone(), vec![transaction.clone()]);
+        assert_eq!(records.blocks, vec![block]);
+        assert_eq!(records.transactions, vec![transaction]);
+    }
+}
@@ -0,0 +1,415 @@
+//! This module contains the definition of the struct [`TransactionVisitedPcs`]
+//! and the functions to save and load the visited program counters of the
+//! replayed transactions.
+//!
+//! Replaying blocks is much slower than analysing the visited program
+//! counters. Saving the visited program counters allows new reports to be
+//! generated without replaying the blocks again.
+//!
+//! The file is gzipped. The uncompressed content starts with the magic bytes
+//! `SRPC` and the format version, followed by the list of transactions. All
+//! integers are encoded as LEB128 varints and hashes as 32 big-endian bytes.
+//! Each transaction is encoded as:
+//!
+//! - the block number and the transaction hash;
+//! - the number of contract classes, followed by the block number and class
+//!   hash of each class (i.e. the [`ReplayClassHash`]);
+//! - for each class, the number of entry points, followed by the program
+//!   counter of each entry point;
+//! - for each entry point, the number of distinct program counters visited,
+//!   followed by pairs of program counter and number of visits. Program
+//!   counters are sorted and encoded as the difference with the previous one.
+
+#![allow(clippy::module_name_repetitions)] // Added because of `TransactionVisitedPcs`
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use itertools::Itertools;
+use starknet_api::core::ClassHash;
+use starknet_api::transaction::TransactionHash;
+use starknet_core::types::Felt;
+
+use crate::block_number::BlockNumber;
+use crate::error::RunnerError;
+use crate::profiler::pprof::write_varint;
+use crate::runner::pc_histogram::EntryPointHistograms;
+use crate::runner::replay_class_hash::{ReplayClassHash, VisitedPcs};
+
+/// The magic bytes at the start of the file of visited program counters.
+const MAGIC: &[u8; 4] = b"SRPC";
+
+/// The version of the format of the file of visited program counters.
+const VERSION: u8 = 1;
+
+/// [`TransactionVisitedPcs`] contains the program counters visited during the
+/// replay of a transaction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TransactionVisitedPcs {
+    /// The block of the transaction.
+    pub block_number: BlockNumber,
+
+    /// The hash of the transaction.
+    pub transaction_hash: TransactionHash,
+
+    /// The histograms of visited program counters of each contract class
+    /// called by the transaction.
+    pub visited_pcs: VisitedPcs,
+}
+
+/// Returns the visited program counters of all the transactions in
+/// `transactions`.
+///
+/// # Arguments
+///
+/// - `transactions`: The visited program counters of each transaction.
+#[must_use]
+pub fn merge_visited_pcs(transactions: &[TransactionVisitedPcs]) -> VisitedPcs {
+    let mut cumulative_visited_pcs = VisitedPcs::default();
+    for transaction in transactions {
+        for (replay_class_hash, histograms) in &transaction.visited_pcs {
+            cumulative_visited_pcs
+                .entry(*replay_class_hash)
+                .or_default()
+                .merge(histograms);
+        }
+    }
+    cumulative_visited_pcs
+}
+
+/// Appends `value` to `buffer` as LEB128 varint.
+///
+/// # Arguments
+///
+/// - `buffer`: The buffer to write.
+/// - `value`: The integer to encode.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `value` doesn't fit in `u64`.
+fn write_usize(buffer: &mut Vec<u8>, value: usize) -> Result<(), RunnerError> {
+    write_varint(buffer, value.try_into()?);
+    Ok(())
+}
+
+/// Reads a LEB128 varint from the start of `input` and advances `input` past
+/// it.
+///
+/// # Arguments
+///
+/// - `input`: The bytes to read.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `input` is truncated or the varint overflows `u64`.
+fn read_varint(input: &mut &[u8]) -> Result<u64, RunnerError> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let Some((byte, rest)) = input.split_first() else {
+            return Err(RunnerError::InvalidVisitedPcsFile(
+                "unexpected end of file".to_string(),
+            ));
+        };
+        *input = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(RunnerError::InvalidVisitedPcsFile(
+        "integer too large".to_string(),
+    ))
+}
+
+/// Reads a LEB128 varint from the start of `input` as `usize` and advances
+/// `input` past it.
+///
+/// # Arguments
+///
+/// - `input`: The bytes to read.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `input` is truncated or the integer doesn't fit in
+/// `usize`.
+fn read_usize(input: &mut &[u8]) -> Result<usize, RunnerError> {
+    Ok(read_varint(input)?.try_into()?)
+}
+
+/// Reads a 32 bytes big-endian felt from the start of `input` and advances
+/// `input` past it.
+///
+/// # Arguments
+///
+/// - `input`: The bytes to read.
+///
+/// # Errors
+///
+/// Returns [`Err`] if `input` is truncated.
+fn read_felt(input: &mut &[u8]) -> Result<Felt, RunnerError> {
+    if input.len() < 32 {
+        return Err(RunnerError::InvalidVisitedPcsFile(
+            "unexpected end of file".to_string(),
+        ));
+    }
+    let (bytes, rest) = input.split_at(32);
+    *input = rest;
+    let mut felt = [0; 32];
+    felt.copy_from_slice(bytes);
+    Ok(Felt::from_bytes_be(&felt))
+}
+
+/// Serialises the visited program counters of `transactions` to the gzipped
+/// binary format described in the module documentation.
+///
+/// Contract classes, entry points and program counters are sorted, therefore
+/// the output doesn't depend on the order of iteration of the hashmaps.
+///
+/// # Arguments
+///
+/// - `transactions`: The visited program counters of each transaction.
+///
+/// # Errors
+///
+/// Returns [`Err`] if there is an IO error compressing the output.
+pub fn to_bytes(transactions: &[TransactionVisitedPcs]) -> Result<Vec<u8>, RunnerError> {
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(MAGIC);
+    buffer.push(VERSION);
+    write_usize(&mut buffer, transactions.len())?;
+    for transaction in transactions {
+        write_varint(&mut buffer, transaction.block_number.get());
+        buffer.extend_from_slice(&transaction.transaction_hash.0.to_bytes_be());
+        let classes: Vec<_> = transaction
+            .visited_pcs
+            .iter()
+            .sorted_by_key(|(replay_class_hash, _)| **replay_class_hash)
+            .collect();
+        write_usize(&mut buffer, classes.len())?;
+        for (replay_class_hash, _) in &classes {
+            write_varint(&mut buffer, replay_class_hash.block_number.get());
+            buffer.extend_from_slice(&replay_class_hash.class_hash.0.to_bytes_be());
+        }
+        for (_, histograms) in &classes {
+            let entry_points: Vec<_> = histograms
+                .iter()
+                .sorted_by_key(|(entry_point_pc, _)| **entry_point_pc)
+                .collect();
+            write_usize(&mut buffer, entry_points.len())?;
+            for (entry_point_pc, _) in &entry_points {
+                write_usize(&mut buffer, **entry_point_pc)?;
+            }
+            for (_, histogram) in &entry_points {
+                write_usize(&mut buffer, histogram.iter().len())?;
+                let mut previous_pc = 0;
+                for (pc, count) in histogram.iter().sorted() {
+                    write_usize(&mut buffer, *pc - previous_pc)?;
+                    write_usize(&mut buffer, *count)?;
+                    previous_pc = *pc;
+                }
+            }
+        }
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&buffer)?;
+    Ok(encoder.finish()?)
+}
+
+/// Deserialises the visited program counters of each transaction from the
+/// gzipped binary format described in the module documentation.
+///
+/// # Arguments
+///
+/// - `input`: The output of [`to_bytes`].
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - `input` isn't gzipped.
+/// - `input` doesn't start with the magic bytes or has a different version.
+/// - `input` is truncated or contains trailing bytes.
+pub fn from_bytes(input: &[u8]) -> Result<Vec<TransactionVisitedPcs>, RunnerError> {
+    let mut buffer = Vec::new();
+    GzDecoder::new(input).read_to_end(&mut buffer)?;
+
+    let Some(mut input) = buffer.strip_prefix(MAGIC.as_slice()) else {
+        return Err(RunnerError::InvalidVisitedPcsFile(
+            "missing magic bytes".to_string(),
+        ));
+    };
+    let Some((version, rest)) = input.split_first() else {
+        return Err(RunnerError::InvalidVisitedPcsFile(
+            "unexpected end of file".to_string(),
+        ));
+    };
+    if *version != VERSION {
+        return Err(RunnerError::InvalidVisitedPcsFile(format!(
+            "unsupported version {version}"
+        )));
+    }
+    input = rest;
+
+    let number_of_transactions = read_usize(&mut input)?;
+    let mut transactions = Vec::new();
+    for _ in 0..number_of_transactions {
+        let block_number = BlockNumber::new(read_varint(&mut input)?);
+        let transaction_hash = TransactionHash(read_felt(&mut input)?);
+        let number_of_classes = read_usize(&mut input)?;
+        let mut replay_class_hashes = Vec::new();
+        for _ in 0..number_of_classes {
+            replay_class_hashes.push(ReplayClassHash {
+                block_number: BlockNumber::new(read_varint(&mut input)?),
+                class_hash: ClassHash(read_felt(&mut input)?),
+            });
+        }
+        let mut visited_pcs = VisitedPcs::default();
+        for replay_class_hash in replay_class_hashes {
+            let number_of_entry_points = read_usize(&mut input)?;
+            let mut entry_point_pcs = Vec::new();
+            for _ in 0..number_of_entry_points {
+                entry_point_pcs.push(read_usize(&mut input)?);
+            }
+            let mut histograms = EntryPointHistograms::new();
+            for entry_point_pc in entry_point_pcs {
+                let number_of_pcs = read_usize(&mut input)?;
+                let mut pc: usize = 0;
+                for _ in 0..number_of_pcs {
+                    pc = pc.checked_add(read_usize(&mut input)?).ok_or_else(|| {
+                        RunnerError::InvalidVisitedPcsFile("program counter too large".to_string())
+                    })?;
+                    let count = read_usize(&mut input)?;
+                    histograms.add(entry_point_pc, pc, count);
+                }
+            }
+            visited_pcs.insert(replay_class_hash, histograms);
+        }
+        transactions.push(TransactionVisitedPcs {
+            block_number,
+            transaction_hash,
+            visited_pcs,
+        });
+    }
+
+    if !input.is_empty() {
+        return Err(RunnerError::InvalidVisitedPcsFile(
+            "unexpected trailing bytes".to_string(),
+        ));
+    }
+    Ok(transactions)
+}
+
+/// Writes the visited program counters of `transactions` to `filename`.
+///
+/// # Arguments
+///
+/// - `filename`: The file to output the visited program counters.
+/// - `transactions`: The visited program counters of each transaction.
+///
+/// # Errors
+///
+/// Returns [`Err`] if there is an error serialising or writing the file.
+pub fn write_visited_pcs(
+    filename: &PathBuf,
+    transactions: &[TransactionVisitedPcs],
+) -> Result<(), RunnerError> {
+    fs::write(filename, to_bytes(transactions)?)?;
+    Ok(())
+}
+
+/// Reads the visited program counters of each transaction from `filename`.
+///
+/// # Arguments
+///
+/// - `filename`: The file saved with [`write_visited_pcs`].
+///
+/// # Errors
+///
+/// Returns [`Err`] if the file can't be read or isn't a valid file of visited
+/// program counters.
+pub fn read_visited_pcs(filename: &PathBuf) -> Result<Vec<TransactionVisitedPcs>, RunnerError> {
+    from_bytes(&fs::read(filename)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(block_number: u64, hash: u64, pcs: &[(u64, &[usize])]) -> TransactionVisitedPcs {
+        let mut visited_pcs = VisitedPcs::default();
+        for (class_hash, execution) in pcs {
+            visited_pcs
+                .entry(ReplayClassHash {
+                    block_number: BlockNumber::new(block_number),
+                    class_hash: ClassHash(Felt::from(*class_hash)),
+                })
+                .or_insert_with(EntryPointHistograms::new)
+                .record_execution(execution);
+        }
+        TransactionVisitedPcs {
+            block_number: BlockNumber::new(block_number),
+            transaction_hash: TransactionHash(Felt::from(hash)),
+            visited_pcs,
+        }
+    }
+
+    #[test]
+    fn test_to_bytes_round_trip() {
+        let transactions = vec![
+            transaction(
+                10,
+                1,
+                &[(100, &[0, 1, 2, 1, 300][..]), (100, &[7, 8]), (200, &[0])],
+            ),
+            transaction(10, 2, &[]),
+            transaction(11, u64::MAX, &[(100, &[0, 1_000_000, 1][..])]),
+        ];
+
+        let bytes = to_bytes(&transactions).unwrap();
+        assert_eq!(from_bytes(&bytes).unwrap(), transactions);
+
+        let visited_pcs = merge_visited_pcs(&transactions);
+        let replay_class_hash = ReplayClassHash {
+            block_number: BlockNumber::new(10),
+            class_hash: ClassHash(Felt::from(100_u64)),
+        };
+        assert_eq!(visited_pcs.len(), 3);
+        assert_eq!(visited_pcs[&replay_class_hash].count(1), 2);
+        assert_eq!(visited_pcs[&replay_class_hash].total(), 7);
+    }
+
+    #[test]
+    fn test_from_bytes_invalid() {
+        let bytes = to_bytes(&[transaction(10, 1, &[(100, &[0, 1][..])])]).unwrap();
+        let mut uncompressed = Vec::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+
+        let compress = |data: &[u8]| {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        let truncated = compress(&uncompressed[..uncompressed.len() - 1]);
+        assert!(matches!(
+            from_bytes(&truncated),
+            Err(RunnerError::InvalidVisitedPcsFile(_))
+        ));
+
+        let mut wrong_version = uncompressed.clone();
+        wrong_version[MAGIC.len()] = VERSION + 1;
+        assert!(matches!(
+            from_bytes(&compress(&wrong_version)),
+            Err(RunnerError::InvalidVisitedPcsFile(_))
+        ));
+
+        assert!(from_bytes(&uncompressed).is_err());
+    }
+}
@@ -2,15 +2,17 @@
 //! and `starknet-replay`. Implementing this trait allows adding compatibility
 //! with a new `Starknet` node.
 
-use std::path::PathBuf;
-
+use cairo_lang_utils::bigint::BigUintAsHex;
 use starknet_api::block::BlockHeader;
+use starknet_api::core::{ChainId, ClassHash};
 use starknet_api::transaction::{Transaction, TransactionReceipt};
 use starknet_core::types::ContractClass;
 
 use crate::block_number::BlockNumber;
 use crate::error::DatabaseError;
-use crate::runner::replay_class_hash::{ReplayClassHash, TransactionOutput};
+use crate::runner::replay_class_hash::ReplayClassHash;
+use crate::runner::replay_options::ReplayOptions;
+use crate::runner::replay_output::TransactionOutput;
 use crate::{ReplayBlock, RunnerError};
 
 pub mod rpc;
@@ -29,6 +31,13 @@ pub trait Storage {
     /// Returns [`Err`] if the low level API with the storage returns an error.
     fn get_most_recent_block_number(&self) -> Result<BlockNumber, DatabaseError>;
 
+    /// Returns the chain id of the network.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the low level API with the storage returns an error.
+    fn get_chain_id(&self) -> Result<ChainId, DatabaseError>;
+
     /// Returns the [`starknet_core::types::ContractClass`] object of a
     /// `class_hash`.
     ///
@@ -45,6 +54,21 @@ pub trait Storage {
         replay_class_hash: &ReplayClassHash,
     ) -> Result<ContractClass, DatabaseError>;
 
+    /// Returns the CASM bytecode compiled by the node from the Sierra contract
+    /// class `class_hash`.
+    ///
+    /// The replay executes this bytecode when it's available, otherwise the
+    /// Sierra contract class is compiled locally.
+    ///
+    /// Returns [`None`] if the node doesn't return the compiled class, e.g.
+    /// because it doesn't support the method `starknet_getCompiledCasm` or
+    /// it's a legacy contract class.
+    ///
+    /// # Arguments
+    ///
+    /// - `class_hash`: The class hash of the contract class.
+    fn get_compiled_casm_bytecode(&self, class_hash: &ClassHash) -> Option<Vec<BigUintAsHex>>;
+
     /// Returns the header of a block.
     ///
     /// # Arguments
@@ -71,21 +95,31 @@ pub trait Storage {
         block_number: BlockNumber,
     ) -> Result<BlockWithReceipts, DatabaseError>;
 
-    /// Replays the list of transactions in a block and returns the list of
-    /// transactions traces.
+    /// Replays the list of transactions in a block and returns the result of
+    /// the replay of each transaction, in the same order of the block.
+    ///
+    /// The data recorded for each transaction and whether the transactions
+    /// are replayed in sequence or in isolation are selected with `options`.
+    /// With [`ReplayOptions::isolated_replay`], each transaction is replayed
+    /// against the state of the parent block, ignoring the effects of the
+    /// preceding transactions in the same block. The storage must not save
+    /// the state changes of the transactions replayed in isolation because
+    /// they are not consistent with each other.
     ///
     /// # Arguments
     ///
     /// - `work`: The block to be re-executed.
-    /// - `trace_out`: The output file of the transaction trace.
+    /// - `options`: The settings of the replay.
     ///
     /// # Errors
     ///
-    /// Returns [`Err`] if any transaction fails execution or if there is any
-    /// error communicating with the storage layer.
+    /// Returns [`Err`] if there is any error communicating with the storage
+    /// layer. When replaying in sequence, transactions failing execution are
+    /// an error too. When replaying in isolation, they are reported in the
+    /// returned [`TransactionOutput`] instead.
     fn execute_block(
         &self,
         work: &ReplayBlock,
-        trace_out: &Option<PathBuf>,
+        options: &ReplayOptions,
     ) -> Result<Vec<TransactionOutput>, RunnerError>;
 }
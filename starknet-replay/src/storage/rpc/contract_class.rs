@@ -17,8 +17,8 @@ use starknet_core::types::{CompressedLegacyContractClass, Felt, FlattenedSierraC
 use crate::error::DatabaseError;
 use crate::storage::rpc::contract_class;
 
-/// This function converts [`starknet_core::types::FlattenedSierraClass`]
-/// into [`blockifier::execution::contract_class::ContractClass`].
+/// This function compiles [`starknet_core::types::FlattenedSierraClass`]
+/// into the [`CasmContractClass`] executed by [`blockifier`].
 ///
 /// # Arguments
 ///
@@ -30,9 +30,7 @@ use crate::storage::rpc::contract_class;
 ///
 /// - Serialisation of `input` fails.
 /// - Compilation of Sierra into CASM fails.
-pub fn decompress_sierra(
-    input: FlattenedSierraClass,
-) -> Result<BlockifierContractClass, DatabaseError> {
+pub fn compile_sierra(input: FlattenedSierraClass) -> Result<CasmContractClass, DatabaseError> {
     let mut contract_class = serde_json::to_value(input)?;
     contract_class
         .as_object_mut()
@@ -42,6 +40,43 @@ pub fn decompress_sierra(
         .remove("abi");
     let sierra_cc: CairoContractClass = serde_json::from_value(contract_class)?;
     let casm_definition = CasmContractClass::from_contract_class(sierra_cc, false, usize::MAX)?;
+    Ok(casm_definition)
+}
+
+/// This function converts [`starknet_core::types::FlattenedSierraClass`]
+/// into [`blockifier::execution::contract_class::ContractClass`].
+///
+/// # Arguments
+///
+/// - `input`: The compressesed Sierra program.
+///
+/// # Errors
+///
+/// Returns [`Err`] if:
+///
+/// - Serialisation of `input` fails.
+/// - Compilation of Sierra into CASM fails.
+pub fn decompress_sierra(
+    input: FlattenedSierraClass,
+) -> Result<BlockifierContractClass, DatabaseError> {
+    let casm_definition = compile_sierra(input)?;
+    convert_casm_class(casm_definition)
+}
+
+/// This function converts the [`CasmContractClass`] compiled from a Sierra
+/// class into [`blockifier::execution::contract_class::ContractClass`].
+///
+/// # Arguments
+///
+/// - `casm_definition`: The compiled Sierra class.
+///
+/// # Errors
+///
+/// Returns [`Err`] if the conversion into
+/// [`blockifier::execution::contract_class::ContractClassV1`] fails.
+pub fn convert_casm_class(
+    casm_definition: CasmContractClass,
+) -> Result<BlockifierContractClass, DatabaseError> {
     let contract_class: ContractClassV1 = casm_definition.try_into().map_err(|_| {
         DatabaseError::IntoInvalid(
             "CasmContractClass".to_string(),
@@ -66,15 +101,7 @@ pub fn decompress_sierra(
 /// - Serialisation of `input` fails.
 /// - Compilation of Sierra into CASM fails.
 pub fn get_sierra_compiled_class_hash(input: FlattenedSierraClass) -> Result<Felt, DatabaseError> {
-    let mut contract_class = serde_json::to_value(input)?;
-    contract_class
-        .as_object_mut()
-        .ok_or(DatabaseError::Unknown(
-            "Contract class is not an object".to_string(),
-        ))?
-        .remove("abi");
-    let sierra_cc: CairoContractClass = serde_json::from_value(contract_class)?;
-    let casm_definition = CasmContractClass::from_contract_class(sierra_cc, false, usize::MAX)?;
+    let casm_definition = compile_sierra(input)?;
     Ok(casm_definition.compiled_class_hash())
 }
 
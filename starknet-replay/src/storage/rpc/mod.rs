@@ -3,27 +3,31 @@
 
 #![allow(clippy::module_name_repetitions)] // Added because of `generate_class_info` in `class_info.rs`, `convert_receipt`
                                            // in `receipt.rs`, `convert_transaction` in `transaction.rs`,
-                                           // `VisitedPcsRaw` in `visited_pcs. rs`, `RpcStorage` in `mod.rs`
+                                           // `VisitedPcsRaw` in `visited_pcs.rs`, `RpcStorage` in `mod.rs`
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::num::NonZeroU128;
-use std::path::PathBuf;
 
+use blockifier::abi::abi_utils::get_fee_token_var_address;
+use blockifier::abi::sierra_types::next_storage_key;
 use blockifier::blockifier::block::{pre_process_block, BlockInfo, BlockNumberHashPair, GasPrices};
 use blockifier::bouncer::BouncerConfig;
 use blockifier::context::{BlockContext, ChainInfo};
-use blockifier::state::cached_state::CachedState;
+use blockifier::state::cached_state::{CachedState, StateMaps, TransactionalState};
 use blockifier::state::errors::StateError;
 use blockifier::state::state_api::StateReader;
+use blockifier::transaction::objects::{TransactionExecutionInfo, TransactionExecutionResult};
 use blockifier::transaction::transaction_execution::Transaction as BlockifierTransaction;
 use blockifier::transaction::transaction_types::TransactionType;
 use blockifier::transaction::transactions::ExecutableTransaction;
 use blockifier::versioned_constants::VersionedConstants;
+use cairo_lang_utils::bigint::BigUintAsHex;
 use once_cell::sync::Lazy;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use starknet_api::block::{BlockHeader, StarknetVersion};
-use starknet_api::core::{ClassHash, ContractAddress, PatriciaKey};
+use starknet_api::core::{ChainId, ClassHash, ContractAddress, PatriciaKey};
 use starknet_api::data_availability::L1DataAvailabilityMode;
-use starknet_api::transaction::{Transaction, TransactionExecutionStatus};
+use starknet_api::transaction::{Transaction, TransactionExecutionStatus, TransactionReceipt};
 use starknet_api::{contract_address, felt, patricia_key};
 use starknet_core::types::{
     ContractClass,
@@ -37,16 +41,22 @@ use starknet_core::types::{
     StorageEntry,
 };
 use state::permanent_state::PermanentState;
+use state::recording_state_reader::RecordingStateReader;
 use state::replay_state_reader::ReplayStateReader;
 use tracing::{error, info, trace, warn};
 use url::Url;
 
-use self::visited_pcs::VisitedPcsRaw;
+use self::visited_pcs::{PcRecorder, VisitedPcsHistograms, VisitedPcsRaw};
 use super::BlockWithReceipts;
 use crate::block_number::BlockNumber;
 use crate::error::{DatabaseError, RunnerError};
+use crate::runner::call_trace::CallTrace;
+use crate::runner::conflict_graph::{StateEntry, TransactionAccessSet};
+use crate::runner::isolation::TransactionOutcome;
 use crate::runner::replay_block::ReplayBlock;
-use crate::runner::replay_class_hash::{ReplayClassHash, TransactionOutput, VisitedPcs};
+use crate::runner::replay_class_hash::{ReplayClassHash, VisitedPcs};
+use crate::runner::replay_options::ReplayOptions;
+use crate::runner::replay_output::TransactionOutput;
 use crate::runner::report::write_to_file;
 use crate::storage::Storage as ReplayStorage;
 
@@ -370,6 +380,422 @@ impl RpcStorage {
         }
         Ok(transactions)
     }
+
+    /// Returns the block number and block hash which become available to the
+    /// Starknet OS when replaying the block with header `header`.
+    ///
+    /// Returns `None` for the first 10 blocks of the chain.
+    ///
+    /// # Arguments
+    ///
+    /// - `header`: the block header of the block replay.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the request of the block header fails.
+    fn old_block_number_and_hash(
+        &self,
+        header: &BlockHeader,
+    ) -> Result<Option<BlockNumberHashPair>, DatabaseError> {
+        if header.block_number.0 < 10 {
+            return Ok(None);
+        }
+        let block_number_whose_hash_becomes_available =
+            BlockNumber::new(header.block_number.0 - 10);
+        // TODO: in case of multiple blocks replay, the block hash is already queried
+        // when the vector of `ReplayBlock` is generated. This data could be reused in a
+        // shared variabled.
+        let block_hash = self
+            .get_block_header(block_number_whose_hash_becomes_available)?
+            .block_hash;
+
+        Ok(Some(BlockNumberHashPair::new(
+            block_number_whose_hash_becomes_available.get(),
+            block_hash.0,
+        )))
+    }
+
+    /// Constructs the [`blockifier::context::BlockContext`] to replay the
+    /// transactions of the block with header `header`.
+    ///
+    /// # Arguments
+    ///
+    /// - `header`: the block header of the block replay.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the request of chain id fails.
+    fn block_context(&self, header: &BlockHeader) -> Result<BlockContext, DatabaseError> {
+        let allow_use_kzg_data = true;
+        let chain_info = self.chain_info()?;
+        let block_info = Self::block_info(header, allow_use_kzg_data);
+        let versioned_constants = Self::versioned_constants(&header.starknet_version);
+        Ok(BlockContext::new(
+            block_info,
+            chain_info,
+            versioned_constants.clone(),
+            BouncerConfig::max(),
+        ))
+    }
+
+    /// Returns the storage entries of the fee token balances of the sequencer.
+    ///
+    /// Every transaction paying a fee reads and writes the balance of the
+    /// sequencer. These entries are excluded from the access sets because
+    /// otherwise all the transactions of a block would conflict with each
+    /// other, while a real sequencer accumulates the fees separately.
+    ///
+    /// # Arguments
+    ///
+    /// - `block_context`: the context of the block replayed.
+    fn sequencer_fee_entries(block_context: &BlockContext) -> HashSet<StateEntry> {
+        let sequencer_address = block_context.block_info().sequencer_address;
+        let fee_token_addresses = &block_context.chain_info().fee_token_addresses;
+        let balance_low = get_fee_token_var_address(sequencer_address);
+        let balance_high = next_storage_key(&balance_low).ok();
+        [
+            fee_token_addresses.eth_fee_token_address,
+            fee_token_addresses.strk_fee_token_address,
+        ]
+        .into_iter()
+        .flat_map(|fee_token_address| {
+            std::iter::once(balance_low)
+                .chain(balance_high)
+                .map(move |key| StateEntry::Storage(fee_token_address, key))
+        })
+        .collect()
+    }
+
+    /// Returns the state entries written by a transaction.
+    ///
+    /// # Arguments
+    ///
+    /// - `writes`: the state changes of the transaction.
+    /// - `declared_classes`: the classes declared by the transaction.
+    fn written_entries(writes: &StateMaps, declared_classes: &[ClassHash]) -> HashSet<StateEntry> {
+        let storage = writes
+            .storage
+            .keys()
+            .map(|(address, key)| StateEntry::Storage(*address, *key));
+        let nonces = writes.nonces.keys().copied().map(StateEntry::Nonce);
+        let class_hashes = writes
+            .class_hashes
+            .keys()
+            .copied()
+            .map(StateEntry::ClassHash);
+        let classes = declared_classes.iter().copied().map(StateEntry::Class);
+        storage
+            .chain(nonces)
+            .chain(class_hashes)
+            .chain(classes)
+            .collect()
+    }
+
+    /// Executes a transaction on top of `state` and returns the result of the
+    /// execution together with the state entries read and written by the
+    /// transaction.
+    ///
+    /// The transaction is executed on a [`TransactionalState`] wrapping
+    /// `state` through a [`RecordingStateReader`], so that only the reads of
+    /// this transaction are recorded. The changes of the transaction are then
+    /// committed to `state`.
+    ///
+    /// # Arguments
+    ///
+    /// - `state`: the state of the block replayed.
+    /// - `transaction`: the transaction to execute.
+    /// - `block_context`: the context of the block replayed.
+    /// - `charge_fee`: if `true`, the fee is charged to the transaction.
+    /// - `validate`: if `true`, the transaction is validated.
+    /// - `declared_deprecated_class`: the Cairo0 class declared by the
+    ///   transaction, otherwise `None`.
+    /// - `sequencer_fee_entries`: the state entries excluded from the access
+    ///   set.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the state changes of the transaction can't be
+    /// computed.
+    fn execute_recording_access_set<S: StateReader, V: PcRecorder>(
+        state: &mut CachedState<S, V>,
+        transaction: &BlockifierTransaction,
+        block_context: &BlockContext,
+        charge_fee: bool,
+        validate: bool,
+        declared_deprecated_class: Option<ClassHash>,
+        sequencer_fee_entries: &HashSet<StateEntry>,
+    ) -> Result<
+        (
+            TransactionExecutionResult<TransactionExecutionInfo>,
+            TransactionAccessSet,
+        ),
+        StateError,
+    > {
+        let mut recorder = RecordingStateReader::new(state);
+        let mut tx_state: TransactionalState<'_, _, V> =
+            TransactionalState::create_transactional(&mut recorder);
+        let tx_info = transaction.execute(&mut tx_state, block_context, charge_fee, validate);
+        let writes = tx_state.to_state_diff()?;
+        tx_state.commit();
+        let reads = recorder.take_reads();
+
+        let declared_class_hashes: Vec<ClassHash> = writes
+            .compiled_class_hashes
+            .keys()
+            .copied()
+            .chain(declared_deprecated_class)
+            .collect();
+        let access_set = TransactionAccessSet {
+            reads: &reads - sequencer_fee_entries,
+            writes: &Self::written_entries(&writes, &declared_class_hashes) - sequencer_fee_entries,
+        };
+        Ok((tx_info, access_set))
+    }
+
+    /// Returns the state of the parent block of `work`, ready to replay the
+    /// transactions of `work`.
+    ///
+    /// # Arguments
+    ///
+    /// - `work`: the block to be re-executed.
+    /// - `old_block_number_and_hash`: the block hash which becomes available
+    ///   when replaying `work`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the block hash can't be stored in the state.
+    fn block_state<V: PcRecorder>(
+        &self,
+        work: &ReplayBlock,
+        old_block_number_and_hash: Option<&BlockNumberHashPair>,
+    ) -> Result<CachedState<ReplayStateReader<'_>, V>, RunnerError> {
+        // Transactions are replayed with the call to `ExecutableTransaction::execute`.
+        // When simulating transactions, the storage layer should match the data of the
+        // parent block (i.e. before the transaction is executed)
+        let block_number_minus_one = BlockNumber::new(work.header.block_number.0 - 1);
+        // rpc_client --> permanent_state --> state_reader --> state
+        let state_reader = ReplayStateReader::new(&self.permanent_state, block_number_minus_one);
+        let mut state: CachedState<_, V> = CachedState::new(state_reader);
+        // `BlockNumberHashPair` doesn't implement `Clone`.
+        let old_block_number_and_hash = old_block_number_and_hash
+            .map(|pair| BlockNumberHashPair::new(pair.number.0, pair.hash.0));
+        pre_process_block(
+            &mut state,
+            old_block_number_and_hash,
+            work.header.block_number,
+        )?;
+        Ok(state)
+    }
+
+    /// Returns the program counters recorded by `state` and resets the
+    /// recorder.
+    ///
+    /// `state` may be shared by all the transactions of the block, therefore
+    /// the recorded program counters are taken after each transaction so
+    /// that each transaction reports only the program counters it visited.
+    ///
+    /// # Arguments
+    ///
+    /// - `state`: the state on top of which the transaction has been executed.
+    fn take_visited_pcs<S: StateReader, V: PcRecorder>(state: &mut CachedState<S, V>) -> V {
+        std::mem::replace(&mut state.visited_pcs, V::new())
+    }
+
+    /// Replays a transaction on top of `state` and returns the result of the
+    /// replay.
+    ///
+    /// A transaction rejected by the [`blockifier`] (e.g. because of an invalid
+    /// nonce) is reported as [`TransactionOutcome::Rejected`], it's up to the
+    /// caller to decide if it's an error.
+    ///
+    /// # Arguments
+    ///
+    /// - `state`: the state on top of which the transaction is executed.
+    /// - `transaction`: the transaction to replay.
+    /// - `receipt`: the receipt of `transaction`.
+    /// - `block_context`: the context of the block replayed.
+    /// - `sequencer_fee_entries`: the state entries excluded from the access
+    ///   set.
+    /// - `options`: the settings of the replay.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is any error communicating with the storage
+    /// layer or saving the transaction trace.
+    fn execute_transaction<S: StateReader, V: PcRecorder>(
+        &self,
+        state: &mut CachedState<S, V>,
+        transaction: &BlockifierTransaction,
+        receipt: &TransactionReceipt,
+        block_context: &BlockContext,
+        sequencer_fee_entries: &HashSet<StateEntry>,
+        options: &ReplayOptions,
+    ) -> Result<TransactionOutput, RunnerError> {
+        let block_number = BlockNumber::new(block_context.block_info().block_number.0);
+        let charge_fee = true;
+        let validate = true;
+        let tx_hash = receipt.transaction_hash;
+        let expected = TransactionOutcome::from(receipt.output.execution_status());
+        let tx_type = Self::transaction_type(transaction);
+        let transaction_declared_deprecated_class_hash =
+            Self::transaction_declared_deprecated_class(transaction);
+
+        let (tx_info, access_set) = if options.record_access_sets {
+            let (tx_info, access_set) = Self::execute_recording_access_set(
+                state,
+                transaction,
+                block_context,
+                charge_fee,
+                validate,
+                transaction_declared_deprecated_class_hash,
+                sequencer_fee_entries,
+            )?;
+            (tx_info, Some(access_set))
+        } else {
+            let tx_info = transaction.execute(state, block_context, charge_fee, validate);
+            (tx_info, None)
+        };
+        let executions = Self::take_visited_pcs(state);
+        let state_diff = self.to_state_diff(state, transaction_declared_deprecated_class_hash)?;
+
+        let tx_info = match tx_info {
+            Ok(tx_info) => tx_info,
+            Err(err) => {
+                trace!("Transaction rejected. {tx_hash:?} | {err}");
+                return Ok(TransactionOutput {
+                    transaction_hash: tx_hash,
+                    expected,
+                    outcome: TransactionOutcome::Rejected,
+                    reason: Some(err.to_string()),
+                    execution_info: None,
+                    visited_pcs: VisitedPcs::default(),
+                    call_traces: Vec::new(),
+                    access_set,
+                });
+            }
+        };
+
+        if let Some(filename) = &options.trace_out {
+            write_to_file(filename, &tx_info, tx_type, Some(state_diff))?;
+            trace!("Saved transaction trace block {block_number} | hash {tx_hash:?}");
+        }
+        let visited_pcs = executions.to_visited_pcs(block_number);
+        let call_traces = match executions.into_executions() {
+            Some(executions) => {
+                CallTrace::from_transaction(&tx_info, tx_type, executions, block_number)
+            }
+            None => Vec::new(),
+        };
+        let outcome = match tx_info.revert_error {
+            Some(_) => TransactionOutcome::Reverted,
+            None => TransactionOutcome::Succeeded,
+        };
+        Ok(TransactionOutput {
+            transaction_hash: tx_hash,
+            expected,
+            outcome,
+            reason: tx_info.revert_error.clone(),
+            execution_info: Some(tx_info),
+            visited_pcs,
+            call_traces,
+            access_set,
+        })
+    }
+
+    /// Replays the list of transactions in a block.
+    ///
+    /// The transactions are replayed in sequence on top of the same state,
+    /// unless [`ReplayOptions::isolated_replay`] is set. In that case, each
+    /// transaction is replayed in parallel against a fresh copy of the state
+    /// of the parent block.
+    ///
+    /// The visited program counters are recorded with `V`. The call traces of
+    /// each transaction are returned only if `V` keeps the order of execution
+    /// of the program counters (i.e. [`VisitedPcsRaw`]).
+    ///
+    /// # Arguments
+    ///
+    /// - `work`: The block to be re-executed.
+    /// - `options`: The settings of the replay.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if there is any error communicating with the storage
+    /// layer or if any transaction fails execution when replaying in
+    /// sequence.
+    fn replay_block<V: PcRecorder>(
+        &self,
+        work: &ReplayBlock,
+        options: &ReplayOptions,
+    ) -> Result<Vec<TransactionOutput>, RunnerError> {
+        let block_number = BlockNumber::new(work.header.block_number.0);
+        info!("Replay started block {block_number}");
+
+        let old_block_number_and_hash = self.old_block_number_and_hash(&work.header)?;
+        let block_context = self.block_context(&work.header)?;
+        let sequencer_fee_entries = Self::sequencer_fee_entries(&block_context);
+        let transactions = self.preprocess_transactions(work)?;
+
+        if options.isolated_replay {
+            return transactions
+                .par_iter()
+                .zip(work.receipts.par_iter())
+                .map(|(transaction, receipt)| {
+                    let mut state =
+                        self.block_state::<V>(work, old_block_number_and_hash.as_ref())?;
+                    self.execute_transaction(
+                        &mut state,
+                        transaction,
+                        receipt,
+                        &block_context,
+                        &sequencer_fee_entries,
+                        options,
+                    )
+                })
+                .collect();
+        }
+
+        let mut state = self.block_state::<V>(work, old_block_number_and_hash.as_ref())?;
+        let mut transaction_result: Vec<_> = Vec::with_capacity(work.transactions.len());
+        for (transaction, receipt) in transactions.iter().zip(work.receipts.iter()) {
+            let output = self.execute_transaction(
+                &mut state,
+                transaction,
+                receipt,
+                &block_context,
+                &sequencer_fee_entries,
+                options,
+            )?;
+            let tx_hash = output.transaction_hash;
+            let reason = output.reason.as_deref().unwrap_or_default();
+            match (output.outcome, receipt.output.execution_status()) {
+                (TransactionOutcome::Rejected, _) => {
+                    error!(
+                        "Interrupting {block_number} block replay. Transaction {tx_hash:?} \
+                         exception {reason}"
+                    );
+                    return Err(RunnerError::Unknown(reason.to_string()));
+                }
+                (
+                    TransactionOutcome::Succeeded,
+                    TransactionExecutionStatus::Reverted(revert_error),
+                ) => {
+                    let revert_error = &revert_error.revert_reason;
+                    warn!(
+                        "Transaction replay succeeded, expected reverted. {tx_hash:?} | \
+                         {revert_error}"
+                    );
+                }
+                (TransactionOutcome::Reverted, TransactionExecutionStatus::Succeeded) => {
+                    warn!("Transaction replay reverted, expected succeess. {tx_hash:?} | {reason}");
+                }
+                (TransactionOutcome::Reverted, TransactionExecutionStatus::Reverted(_))
+                | (TransactionOutcome::Succeeded, TransactionExecutionStatus::Succeeded) => (),
+            };
+            transaction_result.push(output);
+        }
+        Ok(transaction_result)
+    }
 }
 impl ReplayStorage for RpcStorage {
     fn get_most_recent_block_number(&self) -> Result<BlockNumber, DatabaseError> {
@@ -377,6 +803,11 @@ impl ReplayStorage for RpcStorage {
         Ok(block_number)
     }
 
+    fn get_chain_id(&self) -> Result<ChainId, DatabaseError> {
+        let chain_id = self.permanent_state.starknet_get_chain_id()?;
+        Ok(chain_id)
+    }
+
     fn get_contract_class_at_block(
         &self,
         replay_class_hash: &ReplayClassHash,
@@ -385,6 +816,12 @@ impl ReplayStorage for RpcStorage {
         Ok(contract_class)
     }
 
+    fn get_compiled_casm_bytecode(&self, class_hash: &ClassHash) -> Option<Vec<BigUintAsHex>> {
+        self.permanent_state
+            .starknet_get_compiled_casm(class_hash)
+            .map(|casm_class| casm_class.bytecode)
+    }
+
     fn get_block_header(&self, block_number: BlockNumber) -> Result<BlockHeader, DatabaseError> {
         let block_header = self
             .permanent_state
@@ -402,134 +839,64 @@ impl ReplayStorage for RpcStorage {
         Ok(transactions)
     }
 
-    #[allow(clippy::too_many_lines)] // Added because it can't be meaningfully split further in smaller blocks.
     fn execute_block(
         &self,
         work: &ReplayBlock,
-        trace_out: &Option<PathBuf>,
+        options: &ReplayOptions,
     ) -> Result<Vec<TransactionOutput>, RunnerError> {
-        let block_number = BlockNumber::new(work.header.block_number.0);
-        info!("Replay started block {block_number}");
-
-        // Transactions are replayed with the call to `ExecutableTransaction::execute`.
-        // When simulating transactions, the storage layer should match the data of the
-        // parent block (i.e. before the transaction is executed)
-        let block_number_minus_one = BlockNumber::new(work.header.block_number.0 - 1);
-        // rpc_client --> permanent_state --> state_reader --> state
-        let state_reader = ReplayStateReader::new(&self.permanent_state, block_number_minus_one);
-        let charge_fee = true;
-        let validate = true;
-        let allow_use_kzg_data = true;
-        let chain_info = self.chain_info()?;
-        let block_info = Self::block_info(&work.header, allow_use_kzg_data);
-        let old_block_number_and_hash = if work.header.block_number.0 >= 10 {
-            let block_number_whose_hash_becomes_available =
-                BlockNumber::new(work.header.block_number.0 - 10);
-            // TODO: in case of multiple blocks replay, the block hash is already queried
-            // when the vector of `ReplayBlock` is generated. This data could be reused in a
-            // shared variabled.
-            let block_hash = self
-                .get_block_header(block_number_whose_hash_becomes_available)?
-                .block_hash;
-
-            Some(BlockNumberHashPair::new(
-                block_number_whose_hash_becomes_available.get(),
-                block_hash.0,
-            ))
+        // The program counters are kept in order of execution only if the call
+        // traces are required.
+        if options.record_call_traces {
+            self.replay_block::<VisitedPcsRaw>(work, options)
         } else {
-            None
-        };
-        let starknet_version = work.header.starknet_version.clone();
-        let versioned_constants = Self::versioned_constants(&starknet_version);
-        let mut state: CachedState<_, VisitedPcsRaw> = CachedState::new(state_reader);
-        let block_context = BlockContext::new(
-            block_info,
-            chain_info,
-            versioned_constants.clone(),
-            BouncerConfig::max(),
-        );
-        pre_process_block(
-            &mut state,
-            old_block_number_and_hash,
-            work.header.block_number,
-        )?;
-
-        let mut transaction_result: Vec<_> = Vec::with_capacity(work.transactions.len());
-        for (idx, transaction) in self.preprocess_transactions(work)?.iter().enumerate() {
-            let tx_type = Self::transaction_type(transaction);
-            let transaction_declared_deprecated_class_hash =
-                Self::transaction_declared_deprecated_class(transaction);
-            // No fee is being calculated.
-            let tx_info = transaction.execute(&mut state, &block_context, charge_fee, validate);
-            let state_diff =
-                self.to_state_diff(&mut state, transaction_declared_deprecated_class_hash)?;
-            // TODO: Cache the storage changes for faster storage access.
-            match tx_info {
-                // TODO: This clone should be avoided for efficiency.
-                Ok(tx_info) => {
-                    let receipt = &work.receipts[idx];
-                    let tx_hash = receipt.transaction_hash;
-                    match (&tx_info.revert_error, receipt.output.execution_status()) {
-                        (None, TransactionExecutionStatus::Reverted(revert_error)) => {
-                            let revert_error = &revert_error.revert_reason;
-                            warn!(
-                                "Transaction replay succeeded, expected reverted. {tx_hash:?} | \
-                                 {revert_error}"
-                            );
-                        }
-                        (Some(revert_error), TransactionExecutionStatus::Succeeded) => {
-                            warn!(
-                                "Transaction replay reverted, expected succeess. {tx_hash:?} | \
-                                 {revert_error}"
-                            );
-                        }
-                        (Some(_), TransactionExecutionStatus::Reverted(_))
-                        | (None, TransactionExecutionStatus::Succeeded) => (),
-                    };
-
-                    let visited_pcs: VisitedPcs = state
-                        .visited_pcs
-                        .0
-                        .clone()
-                        .into_iter()
-                        .map(|(class_hash, pcs)| {
-                            let replay_class_hash = ReplayClassHash {
-                                block_number,
-                                class_hash,
-                            };
-                            (replay_class_hash, pcs.clone())
-                        })
-                        .collect();
-                    if let Some(filename) = trace_out {
-                        write_to_file(filename, &tx_info, tx_type, Some(state_diff))?;
-                        trace!("Saved transaction trace block {block_number} | hash {tx_hash:?}");
-                    }
-                    transaction_result.push((tx_info, visited_pcs));
-                }
-                Err(err) => {
-                    let receipt = &work.receipts[idx];
-                    let tx_hash = receipt.transaction_hash;
-                    error!(
-                        "Interrupting {block_number} block replay. Transaction {tx_hash:?} \
-                         exception {err:?}"
-                    );
-                    return Err(RunnerError::Unknown(err.to_string()));
-                }
-            }
+            self.replay_block::<VisitedPcsHistograms>(work, options)
         }
-        Ok(transaction_result)
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use starknet_api::core::{ChainId, Nonce};
+    use std::collections::HashMap;
+
+    use blockifier::execution::contract_class::ContractClass as BlockifierContractClass;
+    use blockifier::state::state_api::{State, StateResult};
+    use blockifier::state::visited_pcs::VisitedPcs as _;
+    use starknet_api::core::{ChainId, CompiledClassHash, Nonce};
     use starknet_api::felt;
     use starknet_api::hash::StarkHash;
     use starknet_api::state::StorageKey;
 
     use super::*;
+    use crate::runner::pc_histogram::EntryPointHistograms;
+    use crate::runner::process_transaction_traces;
+
+    /// The state reader of an empty state.
+    struct EmptyStateReader;
+    impl StateReader for EmptyStateReader {
+        fn get_storage_at(&self, _: ContractAddress, _: StorageKey) -> StateResult<Felt> {
+            Ok(Felt::ZERO)
+        }
+
+        fn get_nonce_at(&self, _: ContractAddress) -> StateResult<Nonce> {
+            Ok(Nonce::default())
+        }
+
+        fn get_class_hash_at(&self, _: ContractAddress) -> StateResult<ClassHash> {
+            Ok(ClassHash::default())
+        }
+
+        fn get_compiled_contract_class(
+            &self,
+            class_hash: ClassHash,
+        ) -> StateResult<BlockifierContractClass> {
+            Err(StateError::UndeclaredClassHash(class_hash))
+        }
+
+        fn get_compiled_class_hash(&self, _: ClassHash) -> StateResult<CompiledClassHash> {
+            Ok(CompiledClassHash::default())
+        }
+    }
 
     fn build_rpc_storage() -> RpcStorage {
         let endpoint: Url =
@@ -691,4 +1058,90 @@ mod tests {
         let constants = RpcStorage::versioned_constants(&starknet_version);
         assert_eq!(constants.invoke_tx_max_n_steps, 4_000_000);
     }
+
+    #[test]
+    fn test_take_visited_pcs() {
+        let mut state: CachedState<_, VisitedPcsRaw> = CachedState::new(EmptyStateReader);
+        let class_hash = ClassHash(felt!("0x1"));
+        let other_class_hash = ClassHash(felt!("0x2"));
+
+        // Two consecutive transactions executed on top of the same state.
+        state.add_visited_pcs(class_hash, &[1, 2, 3].to_vec());
+        let first_transaction = RpcStorage::take_visited_pcs(&mut state);
+        state.add_visited_pcs(class_hash, &[4, 5].to_vec());
+        state.add_visited_pcs(other_class_hash, &[6].to_vec());
+        let second_transaction = RpcStorage::take_visited_pcs(&mut state);
+
+        assert_eq!(
+            first_transaction,
+            VisitedPcsRaw([(class_hash, vec![vec![1, 2, 3]])].into())
+        );
+        assert_eq!(
+            second_transaction,
+            VisitedPcsRaw(
+                [
+                    (class_hash, vec![vec![4, 5]]),
+                    (other_class_hash, vec![vec![6]]),
+                ]
+                .into()
+            )
+        );
+        assert_eq!(state.visited_pcs, VisitedPcsRaw::new());
+    }
+
+    #[test]
+    fn test_visited_pcs_of_each_transaction() {
+        let rpc_storage = build_rpc_storage();
+        let block_number = BlockNumber::new(632_917);
+        let (header, transactions, receipts) = rpc_storage
+            .get_transactions_and_receipts_for_block(block_number)
+            .unwrap();
+        let work = ReplayBlock::new(header, transactions, receipts).unwrap();
+
+        // All the transactions of the block are executed on top of the same
+        // state, which records the visited program counters.
+        let outputs = rpc_storage
+            .execute_block(&work, &ReplayOptions::default())
+            .unwrap();
+        assert_eq!(outputs.len(), work.transactions.len());
+
+        let mut transactions_by_class: HashMap<ReplayClassHash, Vec<usize>> = HashMap::new();
+        for output in &outputs {
+            for (replay_class_hash, histograms) in &output.visited_pcs {
+                transactions_by_class
+                    .entry(*replay_class_hash)
+                    .or_default()
+                    .push(histograms.total());
+            }
+            // Each visited program counter is a step of the transaction. The
+            // program counters of the preceding transactions would exceed the
+            // steps of the transaction.
+            if output.outcome == TransactionOutcome::Succeeded {
+                let steps = output
+                    .execution_info
+                    .as_ref()
+                    .unwrap()
+                    .transaction_receipt
+                    .resources
+                    .vm_resources
+                    .n_steps;
+                let visited_pcs: usize = output
+                    .visited_pcs
+                    .values()
+                    .map(EntryPointHistograms::total)
+                    .sum();
+                assert!(visited_pcs <= steps);
+            }
+        }
+
+        let block_visited_pcs = process_transaction_traces(&outputs);
+        let (replay_class_hash, totals) = transactions_by_class
+            .iter()
+            .find(|(_, totals)| totals.len() >= 2)
+            .unwrap();
+        assert_eq!(
+            block_visited_pcs[replay_class_hash].total(),
+            totals.iter().sum::<usize>()
+        );
+    }
 }
@@ -5,6 +5,7 @@
 
 pub mod permanent_state;
 pub mod receipt;
+pub mod recording_state_reader;
 pub mod replay_state_reader;
 pub mod rpc_client;
 pub mod transaction;
@@ -1,9 +1,11 @@
 //! This module is an interface to access the blockchain data. If the data is
 //! not available locally, it is pulled using the RPC protocol.
 
+use std::collections::HashMap;
 use std::sync::RwLock;
 
 use blockifier::state::cached_state::StateMaps;
+use cairo_lang_starknet_classes::casm_contract_class::CasmContractClass;
 use starknet_api::block::BlockHeader;
 use starknet_api::core::{ChainId, ClassHash, ContractAddress, Nonce};
 use starknet_api::state::StorageKey;
@@ -37,6 +39,11 @@ pub struct PermanentState {
     /// When `false`, the element `state` in this structure is kept empty to
     /// ensure all data is queried through the RPC request.
     read_from_state: bool,
+
+    /// The CASM contract classes compiled by the node, keyed by class hash.
+    ///
+    /// The value is [`None`] if the node doesn't return the compiled class.
+    compiled_casm: RwLock<HashMap<ClassHash, Option<CasmContractClass>>>,
 }
 impl PermanentState {
     /// Constructs a new `PermanentState` object.
@@ -54,7 +61,43 @@ impl PermanentState {
             rpc_client,
             state,
             read_from_state,
+            compiled_casm: RwLock::default(),
+        }
+    }
+
+    /// This function queries the CASM contract class compiled by the node
+    /// from the Sierra contract class `class_hash`.
+    ///
+    /// The result is cached because the contract class is queried both by the
+    /// replay and by the profiler. Returns [`None`] if the node doesn't
+    /// return the compiled class, in which case a failed request is logged.
+    ///
+    /// # Arguments
+    ///
+    /// - `class_hash`: The class hash of the contract class.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [`std::sync::RwLock`] is poisoned. It's best to panic
+    /// instead of handling the error because it implies data is corrupted.
+    #[allow(clippy::unwrap_used)]
+    #[must_use]
+    pub fn starknet_get_compiled_casm(&self, class_hash: &ClassHash) -> Option<CasmContractClass> {
+        if let Some(casm_class) = self.compiled_casm.read().unwrap().get(class_hash) {
+            return casm_class.clone();
         }
+        let casm_class = self
+            .rpc_client
+            .starknet_get_compiled_casm(class_hash)
+            .unwrap_or_else(|err| {
+                tracing::warn!("Failed querying the compiled CASM of {class_hash:?}: {err}");
+                None
+            });
+        self.compiled_casm
+            .write()
+            .unwrap()
+            .insert(*class_hash, casm_class.clone());
+        casm_class
     }
 
     /// Updates the local state with the data in the `state_diff`.
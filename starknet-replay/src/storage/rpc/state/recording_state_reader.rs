@@ -0,0 +1,105 @@
+//! This module implements [`blockifier::state::state_api::StateReader`] to
+//! record the state entries read by a transaction during replay.
+//!
+//! [`RecordingStateReader`] wraps the state of the block being replayed. Each
+//! transaction is executed on a
+//! [`blockifier::state::cached_state::TransactionalState`] on top of it so that
+//! only the reads of a single transaction reach the recorder. Committing the
+//! transactional state forwards the writes of the transaction to the state of
+//! the block.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use blockifier::execution::contract_class::ContractClass as BlockifierContractClass;
+use blockifier::state::cached_state::{ContractClassMapping, StateMaps};
+use blockifier::state::state_api::{StateReader, StateResult, UpdatableState};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::state::StorageKey;
+use starknet_core::types::Felt;
+
+use crate::runner::conflict_graph::StateEntry;
+
+/// This structure forwards the queries of [`blockifier`] to the underlying
+/// state and records the state entries read.
+pub struct RecordingStateReader<'a, S: StateReader> {
+    /// The state queried and updated.
+    state: &'a mut S,
+
+    /// The state entries read so far.
+    reads: RefCell<HashSet<StateEntry>>,
+}
+impl<'a, S: StateReader> RecordingStateReader<'a, S> {
+    /// Constructs a new [`RecordingStateReader`] object.
+    ///
+    /// # Arguments
+    ///
+    /// - `state`: The state to query and update.
+    #[must_use]
+    pub fn new(state: &'a mut S) -> Self {
+        RecordingStateReader {
+            state,
+            reads: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Returns the state entries read so far and clears the record.
+    pub fn take_reads(&self) -> HashSet<StateEntry> {
+        self.reads.take()
+    }
+
+    /// Adds `entry` to the list of state entries read.
+    ///
+    /// # Arguments
+    ///
+    /// - `entry`: The state entry read.
+    fn record(&self, entry: StateEntry) {
+        self.reads.borrow_mut().insert(entry);
+    }
+}
+impl<S: StateReader> StateReader for RecordingStateReader<'_, S> {
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<Felt> {
+        self.record(StateEntry::Storage(contract_address, key));
+        self.state.get_storage_at(contract_address, key)
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        self.record(StateEntry::Nonce(contract_address));
+        self.state.get_nonce_at(contract_address)
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        self.record(StateEntry::ClassHash(contract_address));
+        self.state.get_class_hash_at(contract_address)
+    }
+
+    fn get_compiled_contract_class(
+        &self,
+        class_hash: ClassHash,
+    ) -> StateResult<BlockifierContractClass> {
+        self.record(StateEntry::Class(class_hash));
+        self.state.get_compiled_contract_class(class_hash)
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        self.record(StateEntry::Class(class_hash));
+        self.state.get_compiled_class_hash(class_hash)
+    }
+}
+impl<S: UpdatableState> UpdatableState for RecordingStateReader<'_, S> {
+    type Pcs = S::Pcs;
+
+    fn apply_writes(
+        &mut self,
+        writes: &StateMaps,
+        class_hash_to_class: &ContractClassMapping,
+        visited_pcs: &HashMap<ClassHash, Self::Pcs>,
+    ) {
+        self.state
+            .apply_writes(writes, class_hash_to_class, visited_pcs);
+    }
+}
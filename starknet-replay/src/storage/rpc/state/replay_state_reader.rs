@@ -103,7 +103,16 @@ impl StateReader for ReplayStateReader<'_> {
             })?;
         match contract_class {
             StarknetContractClass::Sierra(flattened_sierra_cc) => {
-                let compiled_contract = contract_class::decompress_sierra(flattened_sierra_cc)
+                // The class compiled by the node is executed if available
+                // because it's the bytecode executed on chain. Otherwise, the
+                // Sierra class is compiled locally.
+                let casm_definition =
+                    match self.permanent_state.starknet_get_compiled_casm(&class_hash) {
+                        Some(casm_definition) => Ok(casm_definition),
+                        None => contract_class::compile_sierra(flattened_sierra_cc),
+                    };
+                let compiled_contract = casm_definition
+                    .and_then(contract_class::convert_casm_class)
                     .map_err(|err| {
                         StateError::StateReadError(
                             format!("failed extraction of BlockifierContractClass {err}")
@@ -1,6 +1,7 @@
 //! This module uses the Starknet RPC protocol to query the data from the
 //! Starknet RPC server.
 
+use cairo_lang_starknet_classes::casm_contract_class::CasmContractClass;
 use starknet_api::block::{
     BlockHash,
     BlockHeader,
@@ -126,6 +127,53 @@ impl RpcClient {
         Ok(contract_class)
     }
 
+    /// This function queries the CASM contract class compiled by the node
+    /// from the Sierra contract class `class_hash`.
+    ///
+    /// The method `starknet_getCompiledCasm` was added in version 0.8.0 of the
+    /// RPC specification and isn't supported by [`starknet_providers`],
+    /// therefore it's sent as a plain JSON-RPC request.
+    ///
+    /// Returns [`None`] if the node replies with an error, e.g. because it
+    /// doesn't support the method or `class_hash` is a legacy class.
+    ///
+    /// # Arguments
+    ///
+    /// - `class_hash`: class hash of the contract class.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Err`] if the request fails or the result isn't a CASM
+    /// contract class.
+    #[allow(clippy::missing_panics_doc)] // Needed because `tokio::main` calls `unwrap()`
+    #[tokio::main]
+    pub async fn starknet_get_compiled_casm(
+        &self,
+        class_hash: &ClassHash,
+    ) -> Result<Option<CasmContractClass>, RpcClientError> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "starknet_getCompiledCasm",
+            "params": { "class_hash": class_hash.0.to_hex_string() },
+        });
+        let mut response: serde_json::Value = reqwest::Client::new()
+            .post(self.endpoint.clone())
+            .json(&request)
+            .send()
+            .await?
+            .json()
+            .await?;
+        let Some(result) = response.get_mut("result") else {
+            trace!(
+                "starknet_getCompiledCasm failed for {class_hash:?}: {}",
+                response["error"]
+            );
+            return Ok(None);
+        };
+        Ok(Some(serde_json::from_value(result.take())?))
+    }
+
     /// This function queries the block header.
     ///
     /// # Arguments
@@ -1,7 +1,17 @@
 //! This module implements the [`blockifier::state::visited_pcs::VisitedPcs`]
-//! trait to allow full record of visited program counters during transaction
-//! execution. The default trait used by the blockifier is not enough because it
-//! saves all visited program counters in a set.
+//! trait to record how many times each program counter is visited during
+//! transaction execution. The default trait used by the blockifier is not
+//! enough because it saves all visited program counters in a set.
+//!
+//! Two recorders are available:
+//!
+//! - [`VisitedPcsHistograms`] adds the program counters of each entry point
+//!   execution to the histograms of the entry point as soon as the execution
+//!   completes. It's used by default because its memory usage doesn't grow with
+//!   the number of steps executed.
+//! - [`VisitedPcsRaw`] keeps the program counters of each entry point execution
+//!   in order of execution until the end of the transaction. It's used only
+//!   when the call traces of the transactions are required.
 
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
@@ -10,19 +20,107 @@ use blockifier::state::state_api::State;
 use blockifier::state::visited_pcs::VisitedPcs;
 use starknet_api::core::ClassHash;
 
+use crate::block_number::BlockNumber;
+use crate::runner::pc_histogram::EntryPointHistograms;
+use crate::runner::replay_class_hash::{ReplayClassHash, VisitedPcs as ReplayVisitedPcs};
+
+/// The interface of the recorders of visited program counters used by the
+/// replay.
+pub trait PcRecorder: VisitedPcs {
+    /// Returns the histograms of the program counters recorded keyed by
+    /// [`ReplayClassHash`].
+    ///
+    /// # Arguments
+    ///
+    /// - `block_number`: the block number replayed.
+    fn to_visited_pcs(&self, block_number: BlockNumber) -> ReplayVisitedPcs;
+
+    /// Returns the list of entry point executions of each class, each
+    /// execution being the list of program counters visited in order of
+    /// execution.
+    ///
+    /// Returns `None` if the recorder doesn't keep the order of execution.
+    fn into_executions(self) -> Option<HashMap<ClassHash, Vec<Vec<usize>>>>;
+}
+
+/// The hashmap of [`VisitedPcsHistograms`] is a map from a
+/// [`starknet_api::core::ClassHash`] to the histograms of the program counters
+/// visited by each entry point of the class.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VisitedPcsHistograms(pub HashMap<ClassHash, EntryPointHistograms>);
+impl VisitedPcs for VisitedPcsHistograms {
+    type Pcs = EntryPointHistograms;
+
+    fn new() -> Self {
+        VisitedPcsHistograms(HashMap::default())
+    }
+
+    fn insert(&mut self, class_hash: &ClassHash, pcs: &[usize]) {
+        // Each call of `insert` contains the program counters of a single entry
+        // point execution.
+        self.0.entry(*class_hash).or_default().record_execution(pcs);
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&ClassHash, &Self::Pcs)> {
+        self.0.iter()
+    }
+
+    fn entry(&mut self, class_hash: ClassHash) -> Entry<'_, ClassHash, Self::Pcs> {
+        self.0.entry(class_hash)
+    }
+
+    fn extend(&mut self, class_hash: &ClassHash, pcs: &Self::Pcs) {
+        self.0.entry(*class_hash).or_default().merge(pcs);
+    }
+
+    fn to_set(pcs: Self::Pcs) -> HashSet<usize> {
+        pcs.histogram().iter().map(|(pc, _)| *pc).collect()
+    }
+
+    fn add_visited_pcs(state: &mut dyn State, class_hash: &ClassHash, pcs: Self::Pcs) {
+        // `state` records the program counters again with `insert`, therefore
+        // each entry point is added as a separate execution.
+        for execution in pcs.to_executions() {
+            state.add_visited_pcs(*class_hash, &execution);
+        }
+    }
+}
+impl PcRecorder for VisitedPcsHistograms {
+    fn to_visited_pcs(&self, block_number: BlockNumber) -> ReplayVisitedPcs {
+        self.0
+            .iter()
+            .map(|(class_hash, histograms)| {
+                let replay_class_hash = ReplayClassHash {
+                    block_number,
+                    class_hash: *class_hash,
+                };
+                (replay_class_hash, histograms.clone())
+            })
+            .collect()
+    }
+
+    fn into_executions(self) -> Option<HashMap<ClassHash, Vec<Vec<usize>>>> {
+        None
+    }
+}
+
 /// The hashmap of [`VisitedPcsRaw`] is a map from a
-/// [`starknet_api::core::ClassHash`] to a vector of visited program counters.
+/// [`starknet_api::core::ClassHash`] to the list of entry point executions of
+/// the class. Each execution is the list of program counters visited in order
+/// of execution.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
-pub struct VisitedPcsRaw(pub HashMap<ClassHash, Vec<usize>>);
+pub struct VisitedPcsRaw(pub HashMap<ClassHash, Vec<Vec<usize>>>);
 impl VisitedPcs for VisitedPcsRaw {
-    type Pcs = Vec<usize>;
+    type Pcs = Vec<Vec<usize>>;
 
     fn new() -> Self {
         VisitedPcsRaw(HashMap::default())
     }
 
     fn insert(&mut self, class_hash: &ClassHash, pcs: &[usize]) {
-        self.0.entry(*class_hash).or_default().extend(pcs.iter());
+        // Each call of `insert` contains the program counters of a single entry
+        // point execution.
+        self.0.entry(*class_hash).or_default().push(pcs.to_vec());
     }
 
     fn iter(&self) -> impl Iterator<Item = (&ClassHash, &Self::Pcs)> {
@@ -34,18 +132,78 @@ impl VisitedPcs for VisitedPcsRaw {
     }
 
     fn extend(&mut self, class_hash: &ClassHash, pcs: &Self::Pcs) {
-        self.0.entry(*class_hash).or_default().extend(pcs.clone());
+        self.0
+            .entry(*class_hash)
+            .or_default()
+            .extend(pcs.iter().cloned());
     }
 
     fn to_set(pcs: Self::Pcs) -> HashSet<usize> {
-        let mut set = HashSet::new();
-        for p in pcs {
-            set.insert(p);
-        }
-        set
+        pcs.into_iter().flatten().collect()
     }
 
     fn add_visited_pcs(state: &mut dyn State, class_hash: &ClassHash, pcs: Self::Pcs) {
-        state.add_visited_pcs(*class_hash, &pcs);
+        // `state` records the program counters again with `insert`, therefore
+        // each execution is added separately to keep the order of execution.
+        for execution in pcs {
+            state.add_visited_pcs(*class_hash, &execution);
+        }
+    }
+}
+impl PcRecorder for VisitedPcsRaw {
+    fn to_visited_pcs(&self, block_number: BlockNumber) -> ReplayVisitedPcs {
+        self.0
+            .iter()
+            .map(|(class_hash, executions)| {
+                let replay_class_hash = ReplayClassHash {
+                    block_number,
+                    class_hash: *class_hash,
+                };
+                let mut histograms = EntryPointHistograms::new();
+                for execution in executions {
+                    histograms.record_execution(execution);
+                }
+                (replay_class_hash, histograms)
+            })
+            .collect()
+    }
+
+    fn into_executions(self) -> Option<HashMap<ClassHash, Vec<Vec<usize>>>> {
+        Some(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_api::felt;
+
+    use super::*;
+
+    #[test]
+    fn test_recorders() {
+        let block_number = BlockNumber::new(632_917);
+        let class_hash = ClassHash(felt!("0x1"));
+        let replay_class_hash = ReplayClassHash {
+            block_number,
+            class_hash,
+        };
+
+        let mut histograms = VisitedPcsHistograms::new();
+        let mut raw = VisitedPcsRaw::new();
+        for execution in [&[10, 12, 10][..], &[20, 12]] {
+            histograms.insert(&class_hash, execution);
+            raw.insert(&class_hash, execution);
+        }
+
+        let visited_pcs = histograms.to_visited_pcs(block_number);
+        assert_eq!(visited_pcs, raw.to_visited_pcs(block_number));
+        assert_eq!(visited_pcs[&replay_class_hash].count(10), 2);
+        assert_eq!(visited_pcs[&replay_class_hash].total(), 5);
+
+        assert_eq!(histograms.into_executions(), None);
+        assert_eq!(
+            raw.into_executions().unwrap()[&class_hash],
+            vec![vec![10, 12, 10], vec![20, 12]]
+        );
     }
 }
@@ -10,9 +10,11 @@ use starknet_api::transaction::TransactionHash;
 use starknet_core::types::Felt;
 use starknet_replay::block_number::BlockNumber;
 use starknet_replay::profiler::analysis::extract_libfuncs_weight;
+use starknet_replay::profiler::class_cache::ClassCache;
 use starknet_replay::profiler::replay_statistics::ReplayStatistics;
 use starknet_replay::runner::replay_block::ReplayBlock;
-use starknet_replay::runner::replay_blocks_parallel;
+use starknet_replay::runner::replay_blocks;
+use starknet_replay::runner::replay_options::ReplayOptions;
 use starknet_replay::storage::rpc::RpcStorage;
 use starknet_replay::storage::Storage;
 use test_log::test;
@@ -47,10 +49,14 @@ fn test_replay_blocks() {
     let replay_block = ReplayBlock::new(block_header, transactions, receipts).unwrap();
     replay_work.push(replay_block);
 
-    let trace_out = None;
-    let visited_pcs = replay_blocks_parallel(&storage, &trace_out, &replay_work).unwrap();
+    let options = ReplayOptions::default();
+    let visited_pcs = replay_blocks(&storage, &replay_work, &options)
+        .unwrap()
+        .visited_pcs;
 
-    let libfunc_stats = extract_libfuncs_weight(&visited_pcs, &storage).unwrap();
+    let (libfunc_stats, skipped_classes) =
+        extract_libfuncs_weight(&visited_pcs, &storage, &ClassCache::new());
+    assert!(skipped_classes.undercounted().is_empty());
 
     let mut replay_statistics_expected = ReplayStatistics::new();
     let contents = fs::read_to_string("./test_data/test_replay_blocks.out").unwrap();
@@ -63,5 +69,8 @@ fn test_replay_blocks() {
         };
         replay_statistics_expected.update(&libfunc_name, frequency);
     }
-    assert_eq!(libfunc_stats, replay_statistics_expected);
+    assert_eq!(
+        libfunc_stats.concrete_libfunc,
+        replay_statistics_expected.concrete_libfunc
+    );
 }